@@ -1,12 +1,24 @@
 use super::errors::ParseError;
 use super::syntax::{Process, AtomicAction};
 
-// operator precedence (highest priority first)
-// Paranthesis
-// Atomic Action
-// New
-// Sum
-// Parralel
+// Concrete syntax of pi calculus terms (lowest priority first)
+//
+//   P ::= S | S | ..          parallel composition (left associative)
+//   S ::= N + N + ..          choice (left associative)
+//   N ::= \x.N                restriction
+//       | !N                  replication
+//       | [x=y]N              matching
+//       | A.N                 prefix
+//       | A                   prefix followed by 0
+//       | 0
+//       | (P)
+//   A ::= a<b>                send b on a
+//       | a[b]                receive on a into b
+//       | tau                 silent action
+//
+// Names are made of letters, digits and '_' and start with a letter, 'tau' is a keyword.
+// Whitespace can separate tokens but not split a name, so "a b<c>" is an error and not the name ab.
+// A '[' starts a matching where a process is expected and a receive right after a channel name.
 
 
 type ParseState<'a> = (Process, &'a str);
@@ -14,131 +26,156 @@ type ParseState<'a> = (Process, &'a str);
 // Parse a string into the corresponding pi calculus term
 pub fn parse(input: &str) -> Result<Process, ParseError>
 {
-    // eliminate spaces
-    let input = input.replace(" ", "");
+    let (term, rest) = parse_par(input)?;
 
-    let (term, rest) = parse_par(&input)?;
-
-    if rest.len() > 0 {
+    let rest = rest.trim_start();
+    if !rest.is_empty() {
         return Err(ParseError::Msg(format!("Unexpected token(s) - expected empty end of input: {}", rest)))
     }
 
-    return Ok(term)
+    Ok(term)
+}
+
+// The input after the symbol, if it comes next once the whitespace is skipped
+fn symbol<'a>(input: &'a str, sym: &str) -> Option<&'a str>
+{
+    input.trim_start().strip_prefix(sym)
+}
+
+fn expect<'a>(input: &'a str, sym: &str, context: &str) -> Result<&'a str, ParseError>
+{
+    symbol(input, sym).ok_or_else(|| ParseError::Msg(format!("Unexpected token(s) for {} - expected '{}': {}", context, sym, input.trim_start())))
 }
 
-fn parse_par(input: &str) -> Result<ParseState, ParseError>
+// The name that comes next, keywords included, and the input after it
+fn next_name(input: &str) -> Option<(&str, &str)>
+{
+    let input = input.trim_start();
+    if !input.starts_with(|c: char| c.is_alphabetic()) {
+        return None
+    }
+
+    let end = input.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(input.len());
+    Some((&input[..end], &input[end..]))
+}
+
+fn parse_name<'a>(input: &'a str, context: &str) -> Result<(String, &'a str), ParseError>
+{
+    match next_name(input) {
+        Some((name, rest)) if name != "tau" => Ok((name.to_string(), rest)),
+        _ => Err(ParseError::Msg(format!("Unexpected token(s) for {} - expected a name: {}", context, input.trim_start()))),
+    }
+}
+
+fn parse_par(input: &str) -> Result<ParseState<'_>, ParseError>
 {
     let (mut term, mut rest) = parse_sum(input)?;
 
-    while rest.starts_with("|") {
-        let (term2, rest2) = parse_sum(&rest[1..])?;
+    while let Some(after) = symbol(rest, "|") {
+        let (term2, rest2) = parse_sum(after)?;
         term = Process::Par(Box::new(term), Box::new(term2));
         rest = rest2;
     }
 
-    return Ok((term, rest))
+    Ok((term, rest))
 }
 
-fn parse_sum(input: &str) -> Result<ParseState, ParseError>
+fn parse_sum(input: &str) -> Result<ParseState<'_>, ParseError>
 {
     let (mut term, mut rest) = parse_new(input)?;
 
-    while rest.starts_with("+") {
-        let (term2, rest2) = parse_new(&rest[1..])?;
+    while let Some(after) = symbol(rest, "+") {
+        let (term2, rest2) = parse_new(after)?;
         term = Process::Sum(Box::new(term), Box::new(term2));
         rest = rest2;
     }
 
-    return Ok((term, rest))
+    Ok((term, rest))
 }
 
-fn parse_new(input: &str) -> Result<ParseState, ParseError>
+// Restriction, replication and matching, which all take the process right after them
+fn parse_new(input: &str) -> Result<ParseState<'_>, ParseError>
 {
-    if input.starts_with("\\") {
-        if let Some((name, rest)) = &input[1..].split_once('.') {
-            if name.len() > 0 {
-                let (term, rest) = parse_new(&rest)?;
-                return Ok((Process::New(name.to_string(), Box::new(term)), rest))
-            }
-        }
-        return Err(ParseError::Msg(format!("Unexpected token for new expression - expected \\x.P: {}", input)))
-    }
-    else {
-        return parse_atomic_action(input)
+    if let Some(rest) = symbol(input, "\\") {
+        let (name, rest) = parse_name(rest, "new expression \\x.P")?;
+        let rest = expect(rest, ".", "new expression \\x.P")?;
+        let (term, rest) = parse_new(rest)?;
+        return Ok((Process::New(name, Box::new(term)), rest))
+    }
+
+    if let Some(rest) = symbol(input, "!") {
+        let (term, rest) = parse_new(rest)?;
+        return Ok((Process::Repl(Box::new(term)), rest))
     }
+
+    if let Some(rest) = symbol(input, "[") {
+        let (x, rest) = parse_name(rest, "matching [x=y]P")?;
+        let rest = expect(rest, "=", "matching [x=y]P")?;
+        let (y, rest) = parse_name(rest, "matching [x=y]P")?;
+        let rest = expect(rest, "]", "matching [x=y]P")?;
+        let (term, rest) = parse_new(rest)?;
+        return Ok((Process::Match(x, y, Box::new(term)), rest))
+    }
+
+    parse_atomic_action(input)
 }
 
 fn parse_action(input: &str) -> Result<(AtomicAction, &str), ParseError>
 {
-    let action_parser = |input: &str, start_ch: char, end_ch: char| -> Option<AtomicAction> {
-        if let Some((name_ch, rest)) = input.split_once(start_ch) {
-            if let Some((name_val, _)) = rest.split_once(end_ch) {
-                if name_ch.len() > 0 && name_val.len() > 0 && name_val != name_ch {
-                    if start_ch == '<' {
-                        return Some(AtomicAction::Send(name_ch.to_string(), name_val.to_string()))
-                    }
-                    else if start_ch == '[' {
-                        return Some(AtomicAction::Receive(name_ch.to_string(), name_val.to_string()))
-                    }
-                }
-            }
-        }
+    if let Some(("tau", rest)) = next_name(input) {
+        return Ok((AtomicAction::Tau, rest))
+    }
 
-        return None
-    };
+    let (channel, rest) = parse_name(input, "action a<b>, a[b] or tau")?;
 
-    if let Some(action) = action_parser(input, '<', '>') {
-        let suffix_pos = input.find('>').unwrap() + 1;
-        return Ok((action, &input[suffix_pos..]))
+    if let Some(rest) = symbol(rest, "<") {
+        let (value, rest) = parse_name(rest, "send a<b>")?;
+        let rest = expect(rest, ">", "send a<b>")?;
+        return Ok((AtomicAction::Send(channel, value), rest))
     }
-    else if let Some(action) = action_parser(input, '[', ']') {
-        let suffix_pos = input.find(']').unwrap() + 1;
-        return Ok((action, &input[suffix_pos..]))
+
+    if let Some(rest) = symbol(rest, "[") {
+        let (var, rest) = parse_name(rest, "receive a[b]")?;
+        let rest = expect(rest, "]", "receive a[b]")?;
+        return Ok((AtomicAction::Receive(channel, var), rest))
     }
 
-    return Err(ParseError::Msg(format!("Unexpected token(s) for action - expected a<b> or a[b]: {}", input)))
+    Err(ParseError::Msg(format!("Unexpected token(s) for action - expected a<b> or a[b]: {}", input.trim_start())))
 }
 
-fn parse_atomic_action(input: &str) -> Result<ParseState, ParseError>
+// A prefix without a '.' after it is followed by 0
+fn parse_atomic_action(input: &str) -> Result<ParseState<'_>, ParseError>
 {
-    // TODO: Make this into a while, make clear distinction between E3 and E4
-
-    if input.starts_with(|c: char| c.is_alphabetic()) {
-        let (action, rest) = parse_action(&input)?;
-        if rest.len() == 0 {
-            return Ok((Process::Prefix(action, Box::new(Process::End)), rest))
-        } 
-        else if rest.starts_with(".") {
-            let (term, rest) = parse_atomic_action(&rest[1..])?;
-            return Ok((Process::Prefix(action, Box::new(term)), rest))
-        }
-        else {
-            return Err(ParseError::Msg(format!("Unexpected token for atomic action - expected '.' or end of input: {}", input)))
-        }
-    } else {
+    if next_name(input).is_none() {
         return parse_primary(input)
     }
+
+    let (action, rest) = parse_action(input)?;
+    if let Some(rest) = symbol(rest, ".") {
+        let (term, rest) = parse_new(rest)?;
+        return Ok((Process::Prefix(action, Box::new(term)), rest))
+    }
+
+    Ok((Process::Prefix(action, Box::new(Process::End)), rest))
 }
 
-fn parse_primary(input: &str) -> Result<ParseState, ParseError>
+fn parse_primary(input: &str) -> Result<ParseState<'_>, ParseError>
 {
-    if input.len() == 0
+    let input = input.trim_start();
+    if input.is_empty()
     {
         return Err(ParseError::Msg("Empty input".to_string()))
     }
 
-    if input.starts_with("(") {
-        let (term, rest) = parse_par(&input[1..])?;
-        if rest.starts_with(")") {
-            return Ok((term, &rest[1..]))
-        }
-        return Err(ParseError::Msg(format!("Unexpected token for paranthesis expression - expected ')': {}", input)))
+    if let Some(rest) = symbol(input, "(") {
+        let (term, rest) = parse_par(rest)?;
+        let rest = expect(rest, ")", "paranthesis expression")?;
+        return Ok((term, rest))
     }
 
-    if input.starts_with("0") {
-        return Ok((Process::End, &input[1..]))
+    if let Some(rest) = symbol(input, "0") {
+        return Ok((Process::End, rest))
     }
 
     Err(ParseError::Msg(format!("Unexpected token for primary expression - expected 0 or '(': {}", input)))
 }
-
@@ -0,0 +1,407 @@
+use std::collections::HashSet;
+
+use super::syntax::{AtomicAction, Process};
+
+// Reduction semantics of the pi calculus
+//
+//   a<b>.P + M | a[c].Q + N  ->  P | Q{b/c}
+//   tau.P + M  ->  P
+//   P -> P'  gives  P | Q -> P' | Q  and  \x.P -> \x.P'
+//   P == Q, Q -> Q', Q' == P'  gives  P -> P'
+//
+// Structural congruence (==) is the least congruence with alpha-conversion of bound names and
+//   P | 0 == P,  P | Q == Q | P,  (P | Q) | R == P | (Q | R)
+//   P + 0 == P,  P + Q == Q + P,  (P + Q) + R == P + (Q + R)
+//   \x.0 == 0,  \x.\y.P == \y.\x.P
+//   \x.P | Q == \x.(P | Q)    if x is not free in Q (scope extrusion)
+//   [x=x]P == P,  !P == P | !P
+//
+// Sums are expected to be guarded: only the summands that are prefixes can be chosen,
+//     a matching of different names can not act
+// Replication is unfolded by reduce only, congruent compares two replications by their bodies
+
+// The names that occur in the process outside of a binder for them
+pub fn free_names(p: &Process) -> HashSet<String>
+{
+    match p {
+        Process::End => HashSet::new(),
+        Process::Par(p1, p2) | Process::Sum(p1, p2) => {
+            let mut names = free_names(p1);
+            names.extend(free_names(p2));
+            names
+        },
+        Process::New(x, body) => {
+            let mut names = free_names(body);
+            names.remove(x);
+            names
+        },
+        Process::Prefix(AtomicAction::Send(a, b), cont) => {
+            let mut names = free_names(cont);
+            names.insert(a.clone());
+            names.insert(b.clone());
+            names
+        },
+        Process::Prefix(AtomicAction::Receive(a, x), cont) => {
+            let mut names = free_names(cont);
+            names.remove(x);
+            names.insert(a.clone());
+            names
+        },
+        Process::Prefix(AtomicAction::Tau, cont) | Process::Repl(cont) => free_names(cont),
+        Process::Match(x, y, body) => {
+            let mut names = free_names(body);
+            names.insert(x.clone());
+            names.insert(y.clone());
+            names
+        },
+    }
+}
+
+// A name made from the base that is not in avoid
+pub fn fresh_name(base: &str, avoid: &HashSet<String>) -> String
+{
+    (1..).map(|i| format!("{}_{}", base, i))
+        .find(|name| !avoid.contains(name))
+        .unwrap()
+}
+
+// P{to/from}: replaces the free occurrences of from by to
+// A binder that would capture to is renamed first
+pub fn substitute(p: &Process, from: &str, to: &str) -> Process
+{
+    let rename = |n: &String| if n == from { to.to_string() } else { n.clone() };
+
+    // The binder x of body, renamed if it would capture to, and the body with the substitution done
+    let under_binder = |x: &String, body: &Process| -> (String, Process) {
+        if x == from {
+            return (x.clone(), body.clone())
+        }
+        if x == to {
+            let mut avoid = free_names(body);
+            avoid.insert(from.to_string());
+            avoid.insert(to.to_string());
+            let fresh = fresh_name(x, &avoid);
+            let renamed = substitute(body, x, &fresh);
+            return (fresh, substitute(&renamed, from, to))
+        }
+        (x.clone(), substitute(body, from, to))
+    };
+
+    match p {
+        Process::End => Process::End,
+        Process::Par(p1, p2) => Process::Par(Box::new(substitute(p1, from, to)), Box::new(substitute(p2, from, to))),
+        Process::Sum(p1, p2) => Process::Sum(Box::new(substitute(p1, from, to)), Box::new(substitute(p2, from, to))),
+        Process::New(x, body) => {
+            let (x, body) = under_binder(x, body);
+            Process::New(x, Box::new(body))
+        },
+        Process::Prefix(AtomicAction::Send(a, b), cont) => {
+            Process::Prefix(AtomicAction::Send(rename(a), rename(b)), Box::new(substitute(cont, from, to)))
+        },
+        Process::Prefix(AtomicAction::Receive(a, x), cont) => {
+            let (x, cont) = under_binder(x, cont);
+            Process::Prefix(AtomicAction::Receive(rename(a), x), Box::new(cont))
+        },
+        Process::Prefix(AtomicAction::Tau, cont) => Process::Prefix(AtomicAction::Tau, Box::new(substitute(cont, from, to))),
+        Process::Match(x, y, body) => Process::Match(rename(x), rename(y), Box::new(substitute(body, from, to))),
+        Process::Repl(body) => Process::Repl(Box::new(substitute(body, from, to))),
+    }
+}
+
+// \x1...\xn.(P1 | ... | Pm) with every Pi a prefix, a sum, a replication or a matching of different names
+// The restrictions are lifted to the top, renamed when they would capture a free name of another part
+pub fn standard_form(p: &Process) -> (Vec<String>, Vec<Process>)
+{
+    let mut taken = free_names(p);
+    let mut names = vec![];
+    let mut comps = vec![];
+    _standard_form(p.clone(), &mut taken, &mut names, &mut comps);
+
+    (names, comps)
+}
+
+fn _standard_form(p: Process, taken: &mut HashSet<String>, names: &mut Vec<String>, comps: &mut Vec<Process>)
+{
+    match p {
+        Process::End => (),
+        Process::Par(p1, p2) => {
+            _standard_form(*p1, taken, names, comps);
+            _standard_form(*p2, taken, names, comps);
+        },
+        Process::New(x, body) => {
+            let (x, body) = if taken.contains(&x) {
+                let fresh = fresh_name(&x, taken);
+                let body = substitute(&body, &x, &fresh);
+                (fresh, body)
+            } else {
+                (x, *body)
+            };
+            taken.insert(x.clone());
+            names.push(x);
+            _standard_form(body, taken, names, comps);
+        },
+        Process::Match(x, y, body) if x == y => _standard_form(*body, taken, names, comps),
+        guarded => comps.push(guarded),
+    }
+}
+
+// Rebuilds the process from a standard form, leaving out the restrictions that are not used
+pub fn from_standard_form(names: Vec<String>, comps: Vec<Process>) -> Process
+{
+    let mut comps = comps.into_iter().filter(|c| *c != Process::End);
+    let body = match comps.next() {
+        None => Process::End,
+        Some(first) => comps.fold(first, |acc, c| Process::Par(Box::new(acc), Box::new(c))),
+    };
+
+    let used = free_names(&body);
+    names.into_iter().rev()
+        .filter(|x| used.contains(x))
+        .fold(body, |acc, x| Process::New(x, Box::new(acc)))
+}
+
+// The summands of a sum, the 0s left out and the matchings of the same name opened
+fn summands(p: &Process) -> Vec<&Process>
+{
+    match p {
+        Process::Sum(p1, p2) => {
+            let mut all = summands(p1);
+            all.extend(summands(p2));
+            all
+        },
+        Process::Match(x, y, body) if x == y => summands(body),
+        Process::End => vec![],
+        other => vec![other],
+    }
+}
+
+// The summands that can be chosen, a matching of different names can not act
+fn active_summands(p: &Process) -> Vec<&Process>
+{
+    summands(p).into_iter()
+        .filter(|s| !matches!(s, Process::Match(_, _, _)))
+        .collect()
+}
+
+// Where a part of the parallel composition comes from once the replications are unfolded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Origin
+{
+    Part,
+    // (position of the replication, first or second copy of its body)
+    Copy(usize, usize),
+}
+
+// Every process the given one reduces to in one step, up to structural congruence
+// A communication between two parts of the parallel composition picks a sending and a receiving summand on the same channel,
+//     a tau summand reduces on its own
+// Every replication is unfolded twice, !P == P | P | !P, so that a copy can act with the other parts or with the other copy,
+//     the copies that do not act are left out of the successor
+pub fn reduce(p: &Process) -> Vec<Process>
+{
+    let (mut names, comps) = standard_form(p);
+    let mut taken = free_names(p);
+    taken.extend(names.iter().cloned());
+
+    let mut parts = comps.iter().map(|c| (Origin::Part, c.clone())).collect::<Vec<_>>();
+    for (r, comp) in comps.iter().enumerate() {
+        if let Process::Repl(body) = comp {
+            for copy in 1..=2 {
+                let mut copy_comps = vec![];
+                _standard_form((**body).clone(), &mut taken, &mut names, &mut copy_comps);
+                parts.extend(copy_comps.into_iter().map(|c| (Origin::Copy(r, copy), c)));
+            }
+        }
+    }
+
+    // The successor with the parts at the positions replaced, none when the second copy acts without the first,
+    //     as the same step is found with the first copy
+    let successor = |changes: Vec<(usize, Process)>| -> Option<Process> {
+        let acting = changes.iter().map(|(i, _)| parts[*i].0).collect::<Vec<_>>();
+        if acting.iter().any(|o| matches!(o, Origin::Copy(r, 2) if !acting.contains(&Origin::Copy(*r, 1)))) {
+            return None
+        }
+
+        let mut next = parts.clone();
+        for (i, c) in changes {
+            next[i].1 = c;
+        }
+        let kept = next.into_iter()
+            .filter(|(o, _)| *o == Origin::Part || acting.contains(o))
+            .map(|(_, c)| c)
+            .collect();
+        Some(from_standard_form(names.clone(), kept))
+    };
+
+    let mut succs = vec![];
+    let mut add = |succ: Option<Process>| {
+        if let Some(succ) = succ {
+            if !succs.contains(&succ) {
+                succs.push(succ);
+            }
+        }
+    };
+
+    for (i, (_, part)) in parts.iter().enumerate() {
+        for tau in active_summands(part) {
+            if let Process::Prefix(AtomicAction::Tau, cont) = tau {
+                add(successor(vec![(i, (**cont).clone())]));
+            }
+        }
+    }
+
+    for (i, (_, sender)) in parts.iter().enumerate() {
+        for (j, (_, receiver)) in parts.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+
+            for snd in active_summands(sender) {
+                for rcv in active_summands(receiver) {
+                    if let (Process::Prefix(AtomicAction::Send(a, b), p_cont), Process::Prefix(AtomicAction::Receive(a_r, x), q_cont)) = (snd, rcv) {
+                        if a != a_r {
+                            continue;
+                        }
+
+                        add(successor(vec![(i, (**p_cont).clone()), (j, substitute(q_cont, x, b))]));
+                    }
+                }
+            }
+        }
+    }
+
+    succs
+}
+
+// Whether the two processes are structurally congruent
+pub fn congruent(p: &Process, q: &Process) -> bool
+{
+    equiv(&Normal::of(p), &Normal::of(q), &mut vec![])
+}
+
+// Standard form in which the parts of the parallel composition and of the sums are unordered,
+//     down to the continuations of the prefixes
+#[derive(Debug)]
+struct Normal
+{
+    names: Vec<String>,
+    // Every part is a sum, a prefix being a sum of one summand
+    comps: Vec<Vec<Summand>>,
+}
+
+// A prefix with its continuation, a matching of different names, a replication, or a summand that is not guarded
+#[derive(Debug)]
+enum Summand
+{
+    Prefix(AtomicAction, Normal),
+    Match(String, String, Normal),
+    Repl(Normal),
+    Unguarded(Normal),
+}
+
+impl Normal
+{
+    fn of(p: &Process) -> Normal
+    {
+        let (names, comps) = standard_form(p);
+        let used = comps.iter().flat_map(free_names).collect::<HashSet<_>>();
+
+        Normal {
+            names: names.into_iter().filter(|x| used.contains(x)).collect(),
+            comps: comps.iter()
+                .map(|c| summands(c).into_iter().map(|s| match s {
+                    Process::Prefix(action, cont) => Summand::Prefix(action.clone(), Normal::of(cont)),
+                    Process::Match(x, y, body) => Summand::Match(x.clone(), y.clone(), Normal::of(body)),
+                    Process::Repl(body) => Summand::Repl(Normal::of(body)),
+                    other => Summand::Unguarded(Normal::of(other)),
+                }).collect::<Vec<_>>())
+                .filter(|sum| !sum.is_empty())
+                .collect(),
+        }
+    }
+}
+
+// The bound names of both sides that correspond, innermost last
+type Env = Vec<(String, String)>;
+
+fn same_name(a: &String, b: &String, env: &Env) -> bool
+{
+    match env.iter().rev().find(|(x, y)| x == a || y == b) {
+        Some((x, y)) => x == a && y == b,
+        None => a == b,
+    }
+}
+
+// Tries every way to pair the restrictions of the two sides,
+//     under a given pairing the parts are matched greedily, as equivalent parts can be swapped
+fn equiv(p: &Normal, q: &Normal, env: &mut Env) -> bool
+{
+    if p.names.len() != q.names.len() || p.comps.len() != q.comps.len() {
+        return false
+    }
+
+    permutations(q.names.len()).into_iter().any(|perm| {
+        let depth = env.len();
+        env.extend(p.names.iter().cloned().zip(perm.iter().map(|&k| q.names[k].clone())));
+        let matched = match_all(&p.comps, &q.comps, env, |p, q, env| equiv_sum(p, q, env));
+        env.truncate(depth);
+        matched
+    })
+}
+
+fn equiv_sum(p: &[Summand], q: &[Summand], env: &mut Env) -> bool
+{
+    p.len() == q.len() && match_all(p, q, env, equiv_summand)
+}
+
+fn equiv_summand(p: &Summand, q: &Summand, env: &mut Env) -> bool
+{
+    match (p, q) {
+        (Summand::Unguarded(p), Summand::Unguarded(q)) | (Summand::Repl(p), Summand::Repl(q)) => equiv(p, q, env),
+        (Summand::Match(a, b, p), Summand::Match(c, d, q)) => same_name(a, c, env) && same_name(b, d, env) && equiv(p, q, env),
+        (Summand::Prefix(AtomicAction::Tau, p), Summand::Prefix(AtomicAction::Tau, q)) => equiv(p, q, env),
+        (Summand::Prefix(AtomicAction::Send(a, b), p), Summand::Prefix(AtomicAction::Send(c, d), q)) => {
+            same_name(a, c, env) && same_name(b, d, env) && equiv(p, q, env)
+        },
+        (Summand::Prefix(AtomicAction::Receive(a, x), p), Summand::Prefix(AtomicAction::Receive(c, y), q)) => {
+            if !same_name(a, c, env) {
+                return false
+            }
+            env.push((x.clone(), y.clone()));
+            let matched = equiv(p, q, env);
+            env.pop();
+            matched
+        },
+        _ => false,
+    }
+}
+
+// Whether every item of ps has its own equivalent item in qs, the two having the same length
+fn match_all<T>(ps: &[T], qs: &[T], env: &mut Env, eq: impl Fn(&T, &T, &mut Env) -> bool) -> bool
+{
+    let mut used = vec![false; qs.len()];
+    ps.iter().all(|p| {
+        match (0..qs.len()).find(|&k| !used[k] && eq(p, &qs[k], env)) {
+            Some(k) => {
+                used[k] = true;
+                true
+            },
+            None => false,
+        }
+    })
+}
+
+fn permutations(n: usize) -> Vec<Vec<usize>>
+{
+    if n == 0 {
+        return vec![vec![]]
+    }
+
+    permutations(n - 1).into_iter()
+        .flat_map(|perm| (0..n).map(move |pos| {
+            let mut perm = perm.clone();
+            perm.insert(pos, n - 1);
+            perm
+        }))
+        .collect()
+}
@@ -12,6 +12,10 @@ pub enum Process
     Sum(Box<Process>, Box<Process>),
     // Atomic action
     Prefix(AtomicAction, Box<Process>),
+    // Matching [x=y]P, behaves as P when the two names are the same
+    Match(String, String, Box<Process>),
+    // Replication !P, as many copies of P in parallel as needed
+    Repl(Box<Process>),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -19,5 +23,6 @@ pub enum AtomicAction
 {
     Send(String, String),
     Receive(String, String),
-    // Tau,
+    // Silent action
+    Tau,
 }
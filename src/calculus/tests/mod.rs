@@ -1,3 +1,5 @@
 #[cfg(test)]
 #[allow(non_snake_case)] // Annoying snake case warning
 mod parser;
+#[cfg(test)]
+mod reductions;
\ No newline at end of file
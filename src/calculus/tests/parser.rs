@@ -3,8 +3,6 @@ use crate::calculus::syntax::AtomicAction;
 use super::super::syntax::Process;
 use super::super::parser::parse;
 
-// TODO: Add tests for "+"
-
 fn run_tests(tests: Vec<(&str, &Process)>) 
 {
     for (test, expected) in tests {
@@ -16,6 +14,40 @@ fn run_tests(tests: Vec<(&str, &Process)>)
     }
 }
 
+fn run_error_tests(tests: Vec<&str>)
+{
+    for test in tests {
+        if let Ok(process) = parse(test) {
+            panic!("Expected an error for {}, but got {:?}", test, process);
+        }
+    }
+}
+
+fn send(a: &str, b: &str, cont: Process) -> Process
+{
+    Process::Prefix(AtomicAction::Send(a.to_string(), b.to_string()), Box::new(cont))
+}
+
+fn recv(a: &str, b: &str, cont: Process) -> Process
+{
+    Process::Prefix(AtomicAction::Receive(a.to_string(), b.to_string()), Box::new(cont))
+}
+
+fn tau(cont: Process) -> Process
+{
+    Process::Prefix(AtomicAction::Tau, Box::new(cont))
+}
+
+fn par(p1: Process, p2: Process) -> Process
+{
+    Process::Par(Box::new(p1), Box::new(p2))
+}
+
+fn sum(p1: Process, p2: Process) -> Process
+{
+    Process::Sum(Box::new(p1), Box::new(p2))
+}
+
 #[test]
 fn test_primary() 
 {
@@ -111,3 +143,99 @@ fn test_restriction_par_prio()
     ])
 }
 
+
+#[test]
+fn test_sum()
+{
+    let ac = sum(send("a", "b", Process::End), recv("c", "d", Process::End));
+
+    // Left associative, binds tighter than | and looser than restriction
+    let sum3 = sum(ac.clone(), send("e", "f", Process::End));
+    let sum_par = par(ac.clone(), send("e", "f", Process::End));
+    let new_sum = sum(Process::New("x".to_string(), Box::new(send("a", "b", Process::End))), recv("c", "d", Process::End));
+
+    run_tests(vec![
+        ("a<b>.0 + c[d].0", &ac),
+        ("a<b> + c[d]", &ac),
+        ("(a<b>.0 + c[d].0)", &ac),
+        ("a<b> + c[d] + e<f>", &sum3),
+        ("a<b> + c[d] | e<f>", &sum_par),
+        ("\\x.a<b> + c[d]", &new_sum),
+    ]);
+    run_error_tests(vec!["a<b> +", "+ a<b>", "a<b> + + c<d>"]);
+}
+
+#[test]
+fn test_tau()
+{
+    let tau_send = tau(send("a", "b", Process::End));
+    let tau_sum = sum(tau(Process::End), tau(Process::End));
+    let recv_tau = recv("a", "x", tau(send("x", "b", Process::End)));
+
+    run_tests(vec![
+        ("tau.a<b>.0", &tau_send),
+        ("tau . a<b>", &tau_send),
+        ("tau + tau.0", &tau_sum),
+        ("a[x].tau.x<b>", &recv_tau),
+    ]);
+
+    // tau is a keyword, names only start with it
+    let taus = send("taus", "b", Process::End);
+    run_tests(vec![("taus<b>", &taus)]);
+    run_error_tests(vec!["tau<b>", "a<tau>", "a[tau]", "\\tau.0"]);
+}
+
+#[test]
+fn test_matching()
+{
+    let matching = Process::Match("x".to_string(), "y".to_string(), Box::new(send("a", "b", Process::End)));
+    let match_par = par(matching.clone(), send("c", "d", Process::End));
+
+    // A '[' after a channel name is a receive, where a process is expected it is a matching
+    let recv_match = recv("a", "x", Process::Match("x".to_string(), "b".to_string(), Box::new(send("x", "c", Process::End))));
+    let match_recv = Process::Match("x".to_string(), "y".to_string(), Box::new(recv("a", "b", Process::End)));
+
+    run_tests(vec![
+        ("[x=y]a<b>.0", &matching),
+        ("[ x = y ] a<b>", &matching),
+        ("[x=y]a<b> | c<d>", &match_par),
+        ("a[x].[x=b]x<c>", &recv_match),
+        ("[x=y]a[b]", &match_recv),
+    ]);
+    run_error_tests(vec!["[x=y]", "[x]a<b>", "[x=]a<b>", "[x=y a<b>", "a<b>[x=y]0"]);
+}
+
+#[test]
+fn test_replication()
+{
+    let repl = Process::Repl(Box::new(recv("a", "x", send("x", "b", Process::End))));
+    let repl_par = par(Process::Repl(Box::new(send("a", "b", Process::End))), send("c", "d", Process::End));
+    let repl_group = Process::Repl(Box::new(par(send("a", "b", Process::End), send("c", "d", Process::End))));
+    let repl_new = Process::Repl(Box::new(Process::New("x".to_string(), Box::new(send("a", "x", Process::End)))));
+
+    run_tests(vec![
+        ("!a[x].x<b>", &repl),
+        ("!a<b> | c<d>", &repl_par),
+        ("!(a<b> | c<d>)", &repl_group),
+        ("!\\x.a<x>", &repl_new),
+    ]);
+    run_error_tests(vec!["!", "a<b>.!"]);
+}
+
+#[test]
+fn test_whitespace()
+{
+    let long_names = send("abc", "de", recv("de", "f_1", Process::End));
+    let recv_send = recv("a", "c", send("c", "d", Process::End));
+    let new_x = Process::New("x".to_string(), Box::new(send("x", "b", Process::End)));
+
+    run_tests(vec![
+        ("abc<de>.de[f_1].0", &long_names),
+        ("  abc < de > . de [ f_1 ] . 0  ", &long_names),
+        ("a[c].c<d>.0", &recv_send),
+        ("\\ x . x<b>", &new_x),
+    ]);
+
+    // Names separated by whitespace do not merge
+    run_error_tests(vec!["ab c<de>", "abc<d e>", "a[b c].0", "\\x y.0", "0 0", "1a<b>"]);
+}
@@ -0,0 +1,235 @@
+use crate::calculus::parser::parse;
+use crate::calculus::reductions::{congruent, free_names, reduce, standard_form, substitute};
+use crate::calculus::syntax::{AtomicAction, Process};
+
+fn term(input: &str) -> Process
+{
+    parse(input).unwrap()
+}
+
+fn nil() -> Process
+{
+    Process::End
+}
+
+fn snd(a: &str, b: &str, cont: Process) -> Process
+{
+    Process::Prefix(AtomicAction::Send(a.to_string(), b.to_string()), Box::new(cont))
+}
+
+fn rcv(a: &str, x: &str, cont: Process) -> Process
+{
+    Process::Prefix(AtomicAction::Receive(a.to_string(), x.to_string()), Box::new(cont))
+}
+
+fn new(x: &str, body: Process) -> Process
+{
+    Process::New(x.to_string(), Box::new(body))
+}
+
+fn par(procs: Vec<Process>) -> Process
+{
+    procs.into_iter().reduce(|acc, p| Process::Par(Box::new(acc), Box::new(p))).unwrap_or(Process::End)
+}
+
+fn sum(procs: Vec<Process>) -> Process
+{
+    procs.into_iter().reduce(|acc, p| Process::Sum(Box::new(acc), Box::new(p))).unwrap_or(Process::End)
+}
+
+// The successors, each matched to one of the expected processes up to structural congruence
+fn assert_reduces_to(p: Process, expected: Vec<Process>)
+{
+    let succs = reduce(&p);
+    assert_eq!(succs.len(), expected.len(), "Successors of {:?}: {:?}", p, succs);
+    for exp in expected {
+        assert!(succs.iter().any(|s| congruent(s, &exp)), "{:?} is not a successor of {:?}: {:?}", exp, p, succs);
+    }
+}
+
+#[test]
+fn test_free_names()
+{
+    let p = new("x", par(vec![snd("x", "b", nil()), rcv("a", "c", snd("c", "x", nil()))]));
+    let mut names = free_names(&p).into_iter().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["a", "b"]);
+}
+
+#[test]
+fn test_substitution()
+{
+    let p = snd("a", "b", rcv("b", "c", snd("c", "b", nil())));
+    assert_eq!(substitute(&p, "b", "d"), snd("a", "d", rcv("d", "c", snd("c", "d", nil()))));
+
+    // Bound occurrences are left alone
+    let p = rcv("a", "b", snd("b", "a", nil()));
+    assert_eq!(substitute(&p, "b", "d"), p);
+    let p = new("b", snd("b", "a", nil()));
+    assert_eq!(substitute(&p, "b", "d"), p);
+
+    // The binders that would capture the new name are renamed
+    assert_eq!(substitute(&rcv("a", "d", snd("d", "b", nil())), "b", "d"), rcv("a", "d_1", snd("d_1", "d", nil())));
+    assert_eq!(substitute(&new("d", snd("d", "b", nil())), "b", "d"), new("d_1", snd("d_1", "d", nil())));
+}
+
+#[test]
+fn test_standard_form()
+{
+    // The second x is renamed, as the first one is already lifted
+    let (names, comps) = standard_form(&par(vec![new("x", snd("a", "x", nil())), new("x", snd("x", "b", nil())), nil()]));
+    assert_eq!(names, vec!["x", "x_1"]);
+    assert_eq!(comps, vec![snd("a", "x", nil()), snd("x_1", "b", nil())]);
+
+    // x is free in the other part
+    let (names, _) = standard_form(&par(vec![new("x", snd("a", "x", nil())), snd("x", "b", nil())]));
+    assert_eq!(names, vec!["x_1"]);
+}
+
+#[test]
+fn test_congruence()
+{
+    let (ab, cd, ef) = (snd("a", "b", nil()), snd("c", "d", nil()), snd("e", "f", nil()));
+
+    let congruent_pairs = vec![
+        (par(vec![ab.clone(), cd.clone()]), par(vec![cd.clone(), ab.clone()])),
+        (par(vec![par(vec![ab.clone(), cd.clone()]), ef.clone()]), par(vec![ab.clone(), par(vec![cd.clone(), ef.clone()])])),
+        (par(vec![ab.clone(), nil()]), ab.clone()),
+        (sum(vec![ab.clone(), cd.clone()]), sum(vec![cd.clone(), ab.clone()])),
+        (sum(vec![ab.clone(), nil()]), ab.clone()),
+        (new("x", nil()), nil()),
+        (new("x", new("y", snd("x", "y", nil()))), new("y", new("x", snd("x", "y", nil())))),
+        (new("x", new("y", snd("x", "y", nil()))), new("x", new("y", snd("y", "x", nil())))),
+        (new("x", snd("x", "b", nil())), new("y", snd("y", "b", nil()))),
+        (rcv("a", "x", snd("x", "b", nil())), rcv("a", "y", snd("y", "b", nil()))),
+        // Scope extrusion
+        (par(vec![new("x", snd("a", "x", nil())), rcv("a", "y", nil())]), new("x", par(vec![snd("a", "x", nil()), rcv("a", "y", nil())]))),
+        (par(vec![new("x", snd("x", "b", nil())), new("y", snd("y", "c", nil()))]),
+            new("y", new("x", par(vec![snd("y", "c", nil()), snd("x", "b", nil())])))),
+        // Under prefixes
+        (snd("a", "b", par(vec![cd.clone(), nil()])), snd("a", "b", cd.clone())),
+    ];
+    for (p1, p2) in congruent_pairs {
+        assert!(congruent(&p1, &p2), "{:?} and {:?} should be congruent", p1, p2);
+    }
+
+    let distinct_pairs = vec![
+        (ab.clone(), snd("b", "a", nil())),
+        (par(vec![ab.clone(), ab.clone()]), ab.clone()),
+        (sum(vec![ab.clone(), cd.clone()]), par(vec![ab.clone(), cd.clone()])),
+        // x is free on the right, so it can not be extruded
+        (par(vec![new("x", snd("a", "x", nil())), snd("x", "b", nil())]), new("x", par(vec![snd("a", "x", nil()), snd("x", "b", nil())]))),
+        (new("x", par(vec![snd("x", "a", nil()), snd("x", "b", nil())])), new("x", new("y", par(vec![snd("x", "a", nil()), snd("y", "b", nil())])))),
+        (rcv("a", "x", snd("x", "b", nil())), rcv("a", "x", snd("y", "b", nil()))),
+    ];
+    for (p1, p2) in distinct_pairs {
+        assert!(!congruent(&p1, &p2), "{:?} and {:?} should not be congruent", p1, p2);
+    }
+}
+
+#[test]
+fn test_communication()
+{
+    assert_reduces_to(par(vec![snd("a", "b", nil()), rcv("a", "c", snd("c", "d", nil()))]), vec![snd("b", "d", nil())]);
+    assert_reduces_to(par(vec![snd("a", "b", nil()), rcv("c", "d", nil())]), vec![]);
+
+    // A send and a receive of the same part can not meet
+    assert_reduces_to(snd("a", "b", rcv("a", "c", nil())), vec![]);
+
+    // Either receiver can take the message
+    let (recv_1, recv_2) = (rcv("a", "c", snd("c", "e", nil())), rcv("a", "d", snd("d", "f", nil())));
+    assert_reduces_to(par(vec![snd("a", "b", nil()), recv_1.clone(), recv_2.clone()]), vec![
+        par(vec![snd("b", "e", nil()), recv_2]),
+        par(vec![recv_1, snd("b", "f", nil())]),
+    ]);
+}
+
+#[test]
+fn test_sum()
+{
+    // The other summands are dropped with the choice
+    let p = par(vec![
+        sum(vec![snd("a", "b", nil()), snd("c", "d", nil())]),
+        sum(vec![rcv("a", "x", snd("x", "x", nil())), rcv("c", "y", nil())]),
+    ]);
+    assert_reduces_to(p, vec![snd("b", "b", nil()), nil()]);
+
+    let p = par(vec![sum(vec![snd("a", "b", nil()), snd("a", "c", nil())]), rcv("a", "x", snd("x", "e", nil()))]);
+    assert_reduces_to(p, vec![snd("b", "e", nil()), snd("c", "e", nil())]);
+}
+
+#[test]
+fn test_restriction_reductions()
+{
+    // The channel stays private after the communication
+    assert_reduces_to(new("x", par(vec![snd("x", "b", nil()), rcv("x", "c", snd("c", "d", nil()))])), vec![snd("b", "d", nil())]);
+    assert_reduces_to(new("x", par(vec![snd("x", "b", snd("b", "x", nil())), rcv("x", "c", snd("c", "d", nil()))])),
+        vec![new("x", par(vec![snd("b", "x", nil()), snd("b", "d", nil())]))]);
+
+    // Scope extrusion: the private x is sent to the receiver, which then uses it
+    let p = par(vec![new("x", snd("a", "x", snd("x", "b", nil()))), rcv("a", "y", rcv("y", "z", snd("z", "c", nil())))]);
+    assert_reduces_to(p.clone(), vec![new("x", par(vec![snd("x", "b", nil()), rcv("x", "z", snd("z", "c", nil()))]))]);
+    let succs = reduce(&p);
+    assert_reduces_to(succs[0].clone(), vec![snd("b", "c", nil())]);
+
+    // The receiver is under a restriction of the name it receives, the restriction is renamed so it does not capture it
+    let p = par(vec![snd("a", "x", nil()), new("x", rcv("a", "y", snd("y", "x", nil())))]);
+    let succs = reduce(&p);
+    assert_eq!(succs, vec![new("x_1", snd("x", "x_1", nil()))]);
+}
+
+#[test]
+fn test_tau()
+{
+    assert_reduces_to(term("tau.a<b>"), vec![term("a<b>")]);
+    assert_reduces_to(term("tau.a<b> + c<d>"), vec![term("a<b>")]);
+    assert_reduces_to(term("tau | tau.c<d>"), vec![term("tau.c<d>"), term("tau | c<d>")]);
+
+    // tau can be chosen instead of the communication
+    assert_reduces_to(term("(tau.e<f> + a<b>) | a[x].x<c>"), vec![term("e<f> | a[x].x<c>"), term("b<c>")]);
+}
+
+#[test]
+fn test_matching()
+{
+    assert_reduces_to(term("[a=a]a<b> | a[c].c<d>"), vec![term("b<d>")]);
+    assert_reduces_to(term("[a=e]a<b> | a[c].c<d>"), vec![]);
+    assert_reduces_to(term("[a=a]tau.a<b> + [a=e]tau.c<d>"), vec![term("a<b>")]);
+
+    // The matching is decided once the name is received
+    assert_reduces_to(term("a<b> | a[x].[x=b]tau.x<c>"), vec![term("tau.b<c>")]);
+    assert_reduces_to(term("a<e> | a[x].[x=b]tau.x<c>"), vec![term("[e=b]tau.e<c>")]);
+    assert!(reduce(&term("[e=b]tau.e<c>")).is_empty());
+
+    assert!(congruent(&term("[a=a]a<b>"), &term("a<b>")));
+    assert!(congruent(&term("a<b> + [c=c]d<e>"), &term("d<e> + a<b>")));
+    assert!(congruent(&term("a[x].[x=b]x<c>"), &term("a[y].[y=b]y<c>")));
+    assert!(!congruent(&term("[a=b]a<b>"), &term("0")));
+    assert!(!congruent(&term("[a=b]a<b>"), &term("[a=c]a<b>")));
+}
+
+#[test]
+fn test_replication()
+{
+    // The replicated receiver serves every sender and stays
+    assert_reduces_to(term("!a[x].x<c> | a<b> | a<d>"), vec![
+        term("!a[x].x<c> | b<c> | a<d>"),
+        term("!a[x].x<c> | a<b> | d<c>"),
+    ]);
+    assert_reduces_to(term("!tau.a<b>"), vec![term("!tau.a<b> | a<b>")]);
+    assert_reduces_to(term("!a<b>"), vec![]);
+
+    // Two copies of the same body communicate
+    assert_reduces_to(term("!(a<b> + a[x].x<c>)"), vec![term("!(a<b> + a[x].x<c>) | b<c>")]);
+
+    // Every copy gets its own private name
+    let p = term("!\\x.a<x> | a[y].a[z].y<z>");
+    assert_reduces_to(p.clone(), vec![term("\\x.(!\\x.a<x> | a[z].x<z>)")]);
+    let succs = reduce(&p);
+    assert_reduces_to(succs[0].clone(), vec![term("\\x.\\w.(!\\x.a<x> | x<w>)")]);
+
+    assert!(congruent(&term("!(a<b> | 0)"), &term("!a<b>")));
+    assert!(congruent(&term("!\\x.a<x>"), &term("!\\y.a<y>")));
+    assert!(!congruent(&term("!a<b>"), &term("!c<d>")));
+    assert!(!congruent(&term("!a<b>"), &term("a<b>")));
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::rollpi::{environment::launch::{run_processes_as_parties, LaunchConfig, StrategyKind}, errors::CliError, logger::trace::TraceTarget, syntax::Process};
+use crate::scenarios;
+
+pub const USAGE: &str = "\
+Usage:
+    pi_calculus list                      List the built-in scenarios
+    pi_calculus run <scenario> [options]  Run a built-in scenario
+    pi_calculus file <path> [options]     Run the scenario described in a file
+    pi_calculus help                      Show this message
+
+Options:
+    --strategy <kind>                 Strategy of every participant: order (default), determ or random
+    --strategy <participant>=<kind>   Strategy of one participant, can be repeated
+    --seed <n>                        Seed of the random strategies
+    --log-dir <dir>                   Where the logs go (default: logs)
+    --log-format <format>             text (default), jsonl or none
+    --timeout <seconds>               Stop the run after this long
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat
+{
+    Text,
+    JsonLines,
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOptions
+{
+    pub strategies: HashMap<String, StrategyKind>,
+    pub default_strategy: Option<StrategyKind>,
+    pub seed: Option<u64>,
+    pub log_dir: PathBuf,
+    pub log_format: LogFormat,
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RunOptions
+{
+    fn default() -> Self
+    {
+        RunOptions {
+            strategies: HashMap::new(),
+            default_strategy: None,
+            seed: None,
+            log_dir: PathBuf::from("logs"),
+            log_format: LogFormat::Text,
+            timeout: None,
+        }
+    }
+}
+
+impl RunOptions
+{
+    pub fn launch_config(&self) -> LaunchConfig
+    {
+        let trace_targets = match self.log_format {
+            LogFormat::Text => vec![TraceTarget::Text(self.log_dir.clone())],
+            LogFormat::JsonLines => vec![TraceTarget::JsonLines(self.log_dir.clone())],
+            LogFormat::None => vec![],
+        };
+
+        LaunchConfig {
+            strategies: self.strategies.clone(),
+            default_strategy: self.default_strategy,
+            seed: self.seed,
+            trace_targets: Some(trace_targets),
+            timeout: self.timeout,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command
+{
+    List,
+    Run(String, RunOptions),
+    File(PathBuf, RunOptions),
+    Help,
+}
+
+// The arguments without the name of the program
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, CliError>
+{
+    let mut args = args.into_iter();
+
+    match args.next().as_deref() {
+        None | Some("help") | Some("--help") | Some("-h") => Ok(Command::Help),
+        Some("list") => match args.next() {
+            None => Ok(Command::List),
+            Some(arg) => Err(CliError::UnknownOption(arg)),
+        },
+        Some("run") => {
+            let name = args.next().ok_or_else(|| CliError::MissingArgument("the name of the scenario".to_string()))?;
+            Ok(Command::Run(name, parse_options(args)?))
+        },
+        Some("file") => {
+            let path = args.next().ok_or_else(|| CliError::MissingArgument("the path of the scenario file".to_string()))?;
+            Ok(Command::File(PathBuf::from(path), parse_options(args)?))
+        },
+        Some(cmd) => Err(CliError::UnknownCommand(cmd.to_string())),
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<RunOptions, CliError>
+{
+    let mut options = RunOptions::default();
+
+    while let Some(opt) = args.next() {
+        let mut value = || args.next().ok_or_else(|| CliError::MissingArgument(format!("the value of {}", opt)));
+        let invalid = |value: &str, expected: &str| CliError::InvalidValue(opt.clone(), value.to_string(), expected.to_string());
+
+        match opt.as_str() {
+            "--strategy" => {
+                let value = value()?;
+                match value.split_once('=') {
+                    Some((id, kind)) => {
+                        let kind = kind.parse().map_err(|err: String| invalid(&value, &err))?;
+                        options.strategies.insert(id.to_string(), kind);
+                    },
+                    None => options.default_strategy = Some(value.parse().map_err(|err: String| invalid(&value, &err))?),
+                }
+            },
+            "--seed" => {
+                let value = value()?;
+                options.seed = Some(value.parse().map_err(|_| invalid(&value, "a non negative integer"))?);
+            },
+            "--log-dir" => options.log_dir = PathBuf::from(value()?),
+            "--log-format" => {
+                let value = value()?;
+                options.log_format = match value.as_str() {
+                    "text" => LogFormat::Text,
+                    "jsonl" => LogFormat::JsonLines,
+                    "none" => LogFormat::None,
+                    _ => return Err(invalid(&value, "text, jsonl or none")),
+                };
+            },
+            "--timeout" => {
+                let value = value()?;
+                let secs = value.parse::<f64>().ok().filter(|s| s.is_finite() && *s > 0.0)
+                    .ok_or_else(|| invalid(&value, "a positive number of seconds"))?;
+                options.timeout = Some(Duration::from_secs_f64(secs));
+            },
+            _ => return Err(CliError::UnknownOption(opt)),
+        }
+    }
+
+    Ok(options)
+}
+
+// Runs the command, the listings and the run summaries go to out
+pub fn execute(command: Command, out: &mut impl Write) -> Result<(), CliError>
+{
+    let write_err = |err: std::io::Error| CliError::Output(err.to_string());
+
+    match command {
+        Command::Help => write!(out, "{}", USAGE).map_err(write_err),
+        Command::List => {
+            for (name, description, _) in scenarios::builtin() {
+                writeln!(out, "{:<26}{}", name, description).map_err(write_err)?;
+            }
+            Ok(())
+        },
+        Command::Run(name, options) => {
+            create_log_dir(&options)?;
+            let scenario = scenarios::find_builtin(&name).ok_or(CliError::UnknownScenario(name.clone()))?;
+            run_and_summarize(&name, scenario(), &options, out)
+        },
+        Command::File(path, mut options) => {
+            create_log_dir(&options)?;
+            let scenario = scenarios::file::load_file(&path).map_err(CliError::Scenario)?;
+
+            // A strategy given on the command line for a participant wins over the one in the file
+            for (id, kind) in scenario.strategies {
+                options.strategies.entry(id).or_insert(kind);
+            }
+            run_and_summarize(&path.display().to_string(), scenario.conf, &options, out)
+        },
+    }
+}
+
+// Done before the run, so that a bad --log-dir is reported as such
+fn create_log_dir(options: &RunOptions) -> Result<(), CliError>
+{
+    if options.log_format == LogFormat::None {
+        return Ok(())
+    }
+
+    std::fs::create_dir_all(&options.log_dir)
+        .map_err(|err| CliError::LogDir(options.log_dir.display().to_string(), err.to_string()))
+}
+
+fn run_and_summarize(name: &str, conf: Vec<(String, Process)>, options: &RunOptions, out: &mut impl Write) -> Result<(), CliError>
+{
+    let ids = conf.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>();
+    let report = run_processes_as_parties(conf, options.launch_config()).map_err(CliError::Launch)?;
+
+    let summary = format!("Scenario {} with participants {}\n{}", name, ids.join(", "), report);
+    out.write_all(summary.as_bytes()).map_err(|err| CliError::Output(err.to_string()))
+}
@@ -1,2 +1,6 @@
 pub mod reductions;
-pub mod syntax;
\ No newline at end of file
+pub mod syntax;
+pub mod translation;
+
+#[cfg(test)]
+mod tests;
\ No newline at end of file
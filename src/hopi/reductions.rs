@@ -7,66 +7,173 @@ use super::syntax::Process;
 // ? Same idea but for ClosedNormProcess = (Vec<String>, Process) Where there are no free names either for channels or Processes
 
 
-// Takes in a process and returns a congruent process in its normal form
-#[allow(non_snake_case)]
+// Takes in a process and returns its normal form
+// The normal form is prenex: New x1. ... New xn. P where P has no restriction, the xi are all different
+//     and none of them is a free name of the process. Restrictions are renamed only when they would clash
+// Restrictions are also lifted out of the sent processes and the continuations of receives,
+//     so all the copies of a sent process share the lifted names: the result is not congruent to the process
+//     when a sent process with a restriction is received more than once, see lift_restrictions for a congruent form
 pub fn normalize_process(p: Process) -> Process
 {
     match p {
         Process::End => p,
         Process::PVar(_) => p,
         Process::New(c_name, proc) => {
-            Process::New(c_name, Box::new(normalize_process(*proc)))
-        },
-        Process::Par(A, B) => {
-            let nA = normalize_process(*A);
-            let nB = normalize_process(*B);
-            // if any of nA or nB is Process::New, then bring that on top level
-            if let Process::New(c_name, proc) = nA {
-                let norm = normalize_process(Process::Par(proc, Box::new(nB)));
-                let norm = change_chn_names(norm, &c_name);
-                return Process::New(c_name, Box::new(norm));
-            } else if let Process::New(c_name, proc) = nB {
-                let norm = normalize_process(Process::Par(Box::new(nA), proc));
-                let norm = change_chn_names(norm, &c_name);
-                return Process::New(c_name, Box::new(norm));
-            } else {
-                return Process::Par(Box::new(nA), Box::new(nB));
-            }
+            let norm = change_chn_names(normalize_process(*proc), &c_name);
+            Process::New(c_name, Box::new(norm))
         },
+        Process::Par(a, b) => prenex_par(normalize_process(*a), normalize_process(*b)),
         Process::Send(c_name, proc) => {
-            let norm = normalize_process(*proc);
-            let norm = change_chn_names(norm, &c_name);
-            if let Process::New(first_name, subproc) = norm {
-                let newSubProc = Process::Send(c_name, Box::new(*subproc));
-                let newProc = Process::New(first_name, Box::new(newSubProc));
-                return normalize_process(newProc)
-            }
-            return norm;
+            let norm = change_chn_names(normalize_process(*proc), &c_name);
+            let (names, body) = prenex_split(&norm);
+            prenex_join(names, Process::Send(c_name, Box::new(body)))
         },
         Process::Receive(c_name, p_name, proc) => {
-            let norm = normalize_process(*proc);
-            let norm = change_chn_names(norm, &c_name);
-            if let Process::New(first_name, subproc) = norm {
-                let newSubProc = Process::Receive(c_name, p_name, Box::new(*subproc));
-                let newProc = Process::New(first_name, Box::new(newSubProc));
-                return normalize_process(newProc)
-            }
-            return norm;
+            let norm = change_chn_names(normalize_process(*proc), &c_name);
+            let (names, body) = prenex_split(&norm);
+            prenex_join(names, Process::Receive(c_name, p_name, Box::new(body)))
+        },
+    }
+}
+
+// Same prenex form as normalize_process, but only the restrictions of the parallel composition are lifted,
+//     the ones under a send or a receive stay there, so every received copy of a sent process gets its own
+pub fn lift_restrictions(p: Process) -> Process
+{
+    match p {
+        Process::New(c_name, proc) => {
+            let lifted = change_chn_names(lift_restrictions(*proc), &c_name);
+            Process::New(c_name, Box::new(lifted))
+        },
+        Process::Par(a, b) => prenex_par(lift_restrictions(*a), lift_restrictions(*b)),
+        _ => p,
+    }
+}
+
+// The parallel composition of two processes in prenex form, in prenex form
+#[allow(non_snake_case)]
+fn prenex_par(nA: Process, nB: Process) -> Process
+{
+    // The restrictions of each side must not capture the names of the other side
+    let mut avoid_A = chn_free_names(&nB);
+    avoid_A.extend(prenex_split(&nB).0);
+    let nA = avoid_chn_names(nA, &avoid_A);
+
+    let mut avoid_B = chn_free_names(&nA);
+    avoid_B.extend(prenex_split(&nA).0);
+    let nB = avoid_chn_names(nB, &avoid_B);
+
+    let (mut names, bodyA) = prenex_split(&nA);
+    let (namesB, bodyB) = prenex_split(&nB);
+    names.extend(namesB);
+    prenex_join(names, Process::Par(Box::new(bodyA), Box::new(bodyB)))
+}
+
+// Renames the restrictions so that they all bind different names, none of them a free name of the process
+// The outermost restrictions keep their names
+pub fn rename_restrictions_apart(p: Process) -> Process
+{
+    let mut used = chn_free_names(&p);
+    let mut taken = all_chn_names(&p);
+    _rec_rename_apart(p, &mut used, &mut taken)
+}
+
+fn _rec_rename_apart(p: Process, used: &mut HashSet<String>, taken: &mut HashSet<String>) -> Process
+{
+    match p {
+        Process::End | Process::PVar(_) => p,
+        Process::New(c_name, proc) => {
+            let (c_name, proc) = if used.contains(&c_name) {
+                let fresh = fresh_name(&c_name, taken);
+                let proc = chn_substitution(&proc, &c_name, &fresh);
+                (fresh, proc)
+            } else {
+                (c_name, *proc)
+            };
+            used.insert(c_name.clone());
+            taken.insert(c_name.clone());
+            Process::New(c_name, Box::new(_rec_rename_apart(proc, used, taken)))
         },
+        Process::Par(a, b) => {
+            let a = _rec_rename_apart(*a, used, taken);
+            Process::Par(Box::new(a), Box::new(_rec_rename_apart(*b, used, taken)))
+        },
+        Process::Send(c_name, proc) => Process::Send(c_name, Box::new(_rec_rename_apart(*proc, used, taken))),
+        Process::Receive(c_name, p_var, proc) => Process::Receive(c_name, p_var, Box::new(_rec_rename_apart(*proc, used, taken))),
+    }
+}
+
+// The restricted names at the top of the process, outermost first, and what is under them
+fn prenex_split(p: &Process) -> (Vec<String>, Process)
+{
+    let mut names = vec![];
+    let mut body = p;
+    while let Process::New(c_name, proc) = body {
+        names.push(c_name.clone());
+        body = proc;
     }
+
+    (names, body.clone())
+}
+
+fn prenex_join(names: Vec<String>, body: Process) -> Process
+{
+    names.into_iter().rev().fold(body, |acc, c_name| Process::New(c_name, Box::new(acc)))
 }
 
 // Takes a process in normal form, a channel name c_name
 // Returns an equivalent normalized process which has all restricted channel names different to c_name
-fn change_chn_names(p: Process, _ch_name: &String) -> Process 
+fn change_chn_names(p: Process, ch_name: &str) -> Process 
+{
+    avoid_chn_names(p, &HashSet::from([ch_name.to_string()]))
+}
+
+// Same as change_chn_names, for all the names to avoid at once
+// The new names are different from the names to avoid and from all the channel names of the process
+fn avoid_chn_names(p: Process, avoid: &HashSet<String>) -> Process
+{
+    let (names, _) = prenex_split(&p);
+    if !names.iter().any(|c_name| avoid.contains(c_name)) {
+        return p
+    }
+
+    let mut taken = all_chn_names(&p);
+    taken.extend(avoid.iter().cloned());
+
+    let mut p = p;
+    for c_name in names.iter().filter(|c_name| avoid.contains(*c_name)) {
+        let fresh = fresh_name(c_name, &taken);
+        taken.insert(fresh.clone());
+        // The names are all different, so the only binder of c_name is the one in the prefix
+        p = _rec_chn_alpha_conversion(p, 0, c_name, fresh);
+    }
+
+    p
+}
+
+// A name made from the base that is not taken
+pub fn fresh_name(base: &str, taken: &HashSet<String>) -> String
+{
+    (1..).map(|i| format!("{}_{}", base, i))
+        .find(|c_name| !taken.contains(c_name))
+        .unwrap()
+}
+
+// All the channel names of the process, free or bound
+pub fn all_chn_names(p: &Process) -> HashSet<String>
 {
     match p {
-        Process::End => Process::End,
-        Process::PVar(_) => todo!(),
-        Process::New(_, _) => todo!(),
-        Process::Par(_, _) => todo!(),
-        Process::Send(_, _) => todo!(),
-        Process::Receive(_, _, _) => todo!(),
+        Process::End | Process::PVar(_) => HashSet::new(),
+        Process::New(c_name, proc) | Process::Send(c_name, proc) | Process::Receive(c_name, _, proc) => {
+            let mut names = all_chn_names(proc);
+            names.insert(c_name.clone());
+            names
+        },
+        Process::Par(a, b) => {
+            let mut names = all_chn_names(a);
+            names.extend(all_chn_names(b));
+            names
+        },
     }
 }
 
@@ -82,18 +189,31 @@ pub fn chn_alpha_conversion(p: Process, c_from: String, c_to: String) -> Result<
 }
 
 // Precondition: c_to NOT IN fn(p)
-// the boolean flag tells us if the c_from channel has been captured exactly once (so no nested captures are modified)
+// captures counts the binders of c_from around the current position, only the occurrences under exactly one are renamed
+//     (so no nested captures are modified)
+// A binder of c_to in the scope of the renamed binder is renamed first, so it does not capture the new occurrences
 #[allow(non_snake_case)]
 fn _rec_chn_alpha_conversion(p: Process, captures: i32, c_from: &String, c_to: String) -> Process {
+    let rename = |c_name: String| if captures == 1 && &c_name == c_from { c_to.clone() } else { c_name };
+
     match p {
         // Trivial case
         Process::End => Process::End,
         // A process variable name is not subject to this alpha conversion
         Process::PVar(_) => p,
-        // If the new channel name is c_from, change the boolean flag
+        // If the new channel name is c_from, count the capture, only the outermost binder is renamed
         Process::New(c_name, proc) => {
             if &c_name == c_from {
-                Process::New(c_to.clone(), Box::new(_rec_chn_alpha_conversion(*proc, captures + 1, c_from, c_to)))
+                let new_name = if captures == 0 { c_to.clone() } else { c_name };
+                Process::New(new_name, Box::new(_rec_chn_alpha_conversion(*proc, captures + 1, c_from, c_to)))
+            } else if captures == 1 && c_name == c_to {
+                let mut taken = all_chn_names(&proc);
+                taken.insert(c_to.clone());
+                taken.insert(c_from.clone());
+                let fresh = fresh_name(&c_name, &taken);
+                // The body is already under the binder, so its occurrences count as captured once
+                let proc = _rec_chn_alpha_conversion(*proc, 1, &c_name, fresh.clone());
+                Process::New(fresh, Box::new(_rec_chn_alpha_conversion(proc, captures, c_from, c_to)))
             } else {
                 Process::New(c_name, Box::new(_rec_chn_alpha_conversion(*proc, captures, c_from, c_to)))
             }
@@ -104,14 +224,152 @@ fn _rec_chn_alpha_conversion(p: Process, captures: i32, c_from: &String, c_to: S
             Process::Par(Box::new(pA), Box::new(pB))
         },
         Process::Send(c_name, proc) => {
-            if captures == 1 && &c_name == c_from {
-                Process::Send(c_to.clone(), Box::new(_rec_chn_alpha_conversion(*proc, captures, c_from, c_to)))
+            Process::Send(rename(c_name), Box::new(_rec_chn_alpha_conversion(*proc, captures, c_from, c_to)))
+        },
+        // The receive binds a process variable, only its channel can be renamed
+        Process::Receive(c_name, p_var, proc) => {
+            Process::Receive(rename(c_name), p_var, Box::new(_rec_chn_alpha_conversion(*proc, captures, c_from, c_to)))
+        },
+    }
+}
+
+// Every process the given one reduces to by one higher-order communication
+//     a<P> | a(X).Q  ->  Q{P/X}
+// The restrictions of the parallel composition are lifted first (lift_restrictions), the successors are lifted too:
+//     the restrictions stay at the top and the parts of the parallel composition keep their order,
+//     the received process taking the place of the receive
+// The restrictions of a sent process are only lifted once it is received, so that every copy gets its own channels
+pub fn step(p: &Process) -> Vec<Process>
+{
+    let (names, body) = prenex_split(&lift_restrictions(p.clone()));
+    let mut comps = vec![];
+    par_components(body, &mut comps);
+
+    let mut succs = vec![];
+    for (i, sender) in comps.iter().enumerate() {
+        let Process::Send(a, payload) = sender else { continue };
+        for (j, receiver) in comps.iter().enumerate() {
+            let Process::Receive(b, p_var, cont) = receiver else { continue };
+            if a != b {
+                continue;
             }
-            else {
-                Process::Send(c_name, Box::new(_rec_chn_alpha_conversion(*proc, captures, c_from, c_to)))
+
+            let mut next = comps.clone();
+            next[j] = pvar_substitution(cont, p_var, payload);
+            next.remove(i);
+
+            let succ = lift_restrictions(prenex_join(names.clone(), par_of(next)));
+            if !succs.contains(&succ) {
+                succs.push(succ);
             }
+        }
+    }
+
+    succs
+}
+
+// The parts of a parallel composition, without the ends
+pub fn par_components(p: Process, comps: &mut Vec<Process>)
+{
+    match p {
+        Process::End => (),
+        Process::Par(a, b) => {
+            par_components(*a, comps);
+            par_components(*b, comps);
+        },
+        other => comps.push(other),
+    }
+}
+
+pub fn par_of(comps: Vec<Process>) -> Process
+{
+    comps.into_iter()
+        .reduce(|acc, c| Process::Par(Box::new(acc), Box::new(c)))
+        .unwrap_or(Process::End)
+}
+
+// Q{P/X}: replaces the free occurrences of the process variable X in Q by P
+// The binders of Q that would capture a free name of P, a channel name or a process variable, are renamed first
+pub fn pvar_substitution(q: &Process, x: &str, p: &Process) -> Process
+{
+    match q {
+        Process::End => Process::End,
+        Process::PVar(y) => if y == x { p.clone() } else { q.clone() },
+        Process::New(c_name, proc) => {
+            if chn_free_names(p).contains(c_name) {
+                let mut taken = all_chn_names(proc);
+                taken.extend(chn_free_names(p));
+                let fresh = fresh_name(c_name, &taken);
+                let proc = chn_substitution(proc, c_name, &fresh);
+                Process::New(fresh, Box::new(pvar_substitution(&proc, x, p)))
+            } else {
+                Process::New(c_name.clone(), Box::new(pvar_substitution(proc, x, p)))
+            }
+        },
+        Process::Par(a, b) => Process::Par(Box::new(pvar_substitution(a, x, p)), Box::new(pvar_substitution(b, x, p))),
+        Process::Send(c_name, proc) => Process::Send(c_name.clone(), Box::new(pvar_substitution(proc, x, p))),
+        Process::Receive(c_name, y, proc) => {
+            if y == x {
+                q.clone()
+            } else if var_free_names(p).contains(y) {
+                let mut taken = all_var_names(proc);
+                taken.extend(var_free_names(p));
+                taken.insert(x.to_string());
+                let fresh = fresh_name(y, &taken);
+                let proc = pvar_substitution(proc, y, &Process::PVar(fresh.clone()));
+                Process::Receive(c_name.clone(), fresh, Box::new(pvar_substitution(&proc, x, p)))
+            } else {
+                Process::Receive(c_name.clone(), y.clone(), Box::new(pvar_substitution(proc, x, p)))
+            }
+        },
+    }
+}
+
+// P{to/from}: replaces the free occurrences of the channel name from in P by to
+// The restrictions of P that would capture to are renamed first
+pub fn chn_substitution(p: &Process, from: &str, to: &str) -> Process
+{
+    let rename = |c_name: &String| if c_name == from { to.to_string() } else { c_name.clone() };
+
+    match p {
+        Process::End | Process::PVar(_) => p.clone(),
+        Process::New(c_name, proc) => {
+            if c_name == from {
+                p.clone()
+            } else if c_name == to {
+                let mut taken = all_chn_names(proc);
+                taken.insert(from.to_string());
+                taken.insert(to.to_string());
+                let fresh = fresh_name(c_name, &taken);
+                let proc = chn_substitution(proc, c_name, &fresh);
+                Process::New(fresh, Box::new(chn_substitution(&proc, from, to)))
+            } else {
+                Process::New(c_name.clone(), Box::new(chn_substitution(proc, from, to)))
+            }
+        },
+        Process::Par(a, b) => Process::Par(Box::new(chn_substitution(a, from, to)), Box::new(chn_substitution(b, from, to))),
+        Process::Send(c_name, proc) => Process::Send(rename(c_name), Box::new(chn_substitution(proc, from, to))),
+        Process::Receive(c_name, p_var, proc) => Process::Receive(rename(c_name), p_var.clone(), Box::new(chn_substitution(proc, from, to))),
+    }
+}
+
+// All the process variables of the process, free or bound
+pub fn all_var_names(p: &Process) -> HashSet<String>
+{
+    match p {
+        Process::End => HashSet::new(),
+        Process::PVar(x) => HashSet::from([x.clone()]),
+        Process::New(_, proc) | Process::Send(_, proc) => all_var_names(proc),
+        Process::Receive(_, x, proc) => {
+            let mut names = all_var_names(proc);
+            names.insert(x.clone());
+            names
+        },
+        Process::Par(a, b) => {
+            let mut names = all_var_names(a);
+            names.extend(all_var_names(b));
+            names
         },
-        Process::Receive(_, _, _) => todo!(),
     }
 }
 
@@ -162,7 +420,7 @@ fn _var_free_names_env(p: &Process, ctx: &mut HashSet<String>) -> HashSet<String
 }
 
 // Returns a list of free channel names
-fn chn_free_names(p: &Process) -> HashSet<String>
+pub fn chn_free_names(p: &Process) -> HashSet<String>
 {
     return _chn_free_names_env(p, &mut HashSet::new())
 }
@@ -212,9 +470,9 @@ fn _chn_free_names_env(p: &Process, ctx: &mut HashSet<String>) -> HashSet<String
 }
 
 // returns a list of free tag names 
-// ? This should always be empty, at least if coming from an encoding of local types
+// hopi terms carry no tags, only their translation to roll-pi does, so the list is always empty
 pub fn tag_free_names(_p: &Process) -> Vec<String>
 {
-    todo!()
+    vec![]
 }
 
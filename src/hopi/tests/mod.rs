@@ -0,0 +1,40 @@
+use crate::hopi::syntax::Process;
+
+#[cfg(test)]
+mod normalisation;
+#[cfg(test)]
+mod reductions;
+#[cfg(test)]
+mod translation;
+
+// Shorthands for building terms
+
+pub fn end() -> Process
+{
+    Process::End
+}
+
+pub fn var(x: &str) -> Process
+{
+    Process::PVar(x.to_string())
+}
+
+pub fn new(c: &str, p: Process) -> Process
+{
+    Process::New(c.to_string(), Box::new(p))
+}
+
+pub fn par(p: Process, q: Process) -> Process
+{
+    Process::Par(Box::new(p), Box::new(q))
+}
+
+pub fn snd(c: &str, p: Process) -> Process
+{
+    Process::Send(c.to_string(), Box::new(p))
+}
+
+pub fn rcv(c: &str, x: &str, p: Process) -> Process
+{
+    Process::Receive(c.to_string(), x.to_string(), Box::new(p))
+}
\ No newline at end of file
@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+
+use crate::hopi::reductions::{chn_alpha_conversion, chn_free_names, lift_restrictions, normalize_process, rename_restrictions_apart, tag_free_names, var_free_names};
+use crate::hopi::syntax::Process;
+use crate::rollpi::environment::components::strategies::SimpleRandom::splitmix64;
+
+use super::{end, new, par, rcv, snd, var};
+
+// New x1. ... New xn. P with no restriction in P and all the xi different
+fn is_prenex(p: &Process) -> bool
+{
+    fn has_new(p: &Process) -> bool
+    {
+        match p {
+            Process::End | Process::PVar(_) => false,
+            Process::New(_, _) => true,
+            Process::Par(a, b) => has_new(a) || has_new(b),
+            Process::Send(_, q) | Process::Receive(_, _, q) => has_new(q),
+        }
+    }
+
+    let mut names = HashSet::new();
+    let mut body = p;
+    while let Process::New(c, q) = body {
+        if !names.insert(c.clone()) {
+            return false
+        }
+        body = q;
+    }
+
+    !has_new(body)
+}
+
+// A random term over few names, so that restrictions often shadow and clash with each other
+fn random_process(rng: &mut u64, depth: usize) -> Process
+{
+    let chans = ["a", "b", "c"];
+    let vars = ["X", "Y"];
+    let mut pick = |n: usize| (splitmix64(rng) % n as u64) as usize;
+
+    let kind = if depth == 0 { pick(2) } else { pick(6) };
+    match kind {
+        0 => end(),
+        1 => var(vars[pick(vars.len())]),
+        2 => {
+            let c = chans[pick(chans.len())];
+            new(c, random_process(rng, depth - 1))
+        },
+        3 => par(random_process(rng, depth - 1), random_process(rng, depth - 1)),
+        4 => {
+            let c = chans[pick(chans.len())];
+            snd(c, random_process(rng, depth - 1))
+        },
+        _ => {
+            let (c, x) = (chans[pick(chans.len())], vars[pick(vars.len())]);
+            rcv(c, x, random_process(rng, depth - 1))
+        },
+    }
+}
+
+#[test]
+fn test_normalize_examples()
+{
+    // Already in normal form
+    let p = new("a", par(snd("a", end()), rcv("a", "X", var("X"))));
+    assert_eq!(normalize_process(p.clone()), p);
+
+    // Lifted out of a parallel composition, a sent process and a receive
+    let p = par(snd("b", new("a", snd("a", end()))), rcv("b", "X", new("c", par(var("X"), snd("c", end())))));
+    assert_eq!(normalize_process(p), new("a", new("c", par(snd("b", snd("a", end())), rcv("b", "X", par(var("X"), snd("c", end())))))));
+
+    // The restriction on the left is renamed as a is free on the right
+    let p = par(new("a", snd("a", end())), snd("a", end()));
+    assert_eq!(normalize_process(p), new("a_1", par(snd("a_1", end()), snd("a", end()))));
+
+    // Two restrictions of the same name
+    let p = new("a", par(snd("a", end()), new("a", snd("a", end()))));
+    assert_eq!(normalize_process(p), new("a", new("a_1", par(snd("a", end()), snd("a_1", end())))));
+
+    // The restriction can not capture the channel of the send it is lifted out of
+    let p = snd("a", new("a", snd("a", end())));
+    assert_eq!(normalize_process(p), new("a_1", snd("a", snd("a_1", end()))));
+}
+
+#[test]
+fn test_lift_restrictions()
+{
+    // The restrictions under the send and the receive stay where they are
+    let p = par(snd("b", new("a", snd("a", end()))), rcv("b", "X", new("c", par(var("X"), snd("c", end())))));
+    assert_eq!(lift_restrictions(p.clone()), p);
+
+    let p = par(new("a", snd("a", end())), par(snd("a", end()), new("c", snd("b", new("c", snd("c", end()))))));
+    assert_eq!(lift_restrictions(p), new("a_1", new("c", par(snd("a_1", end()), par(snd("a", end()), snd("b", new("c", snd("c", end()))))))));
+
+    // Renaming apart gives the inner c a name of its own, the outer one keeps its name
+    let p = par(new("c", snd("c", end())), snd("a", new("c", snd("c", end()))));
+    assert_eq!(rename_restrictions_apart(p), par(new("c", snd("c", end())), snd("a", new("c_1", snd("c_1", end())))));
+}
+
+#[test]
+fn test_alpha_conversion()
+{
+    // Only the outermost binder and its occurrences are renamed, the receive channel included
+    let p = new("a", par(rcv("a", "X", var("X")), new("a", snd("a", end()))));
+    assert_eq!(chn_alpha_conversion(p, "a".to_string(), "b".to_string()),
+        Ok(new("b", par(rcv("b", "X", var("X")), new("a", snd("a", end()))))));
+
+    // A binder of the new name in the scope is renamed so it does not capture
+    let p = new("a", new("b", par(snd("a", end()), snd("b", end()))));
+    assert_eq!(chn_alpha_conversion(p, "a".to_string(), "b".to_string()),
+        Ok(new("b", new("b_1", par(snd("b", end()), snd("b_1", end()))))));
+
+    assert!(chn_alpha_conversion(new("a", snd("b", end())), "a".to_string(), "b".to_string()).is_err());
+}
+
+#[test]
+fn test_tag_free_names()
+{
+    assert!(tag_free_names(&new("a", snd("a", end()))).is_empty());
+}
+
+#[test]
+fn test_normalize_properties()
+{
+    let mut rng = 7;
+    for _ in 0..500 {
+        let p = random_process(&mut rng, 5);
+        let norm = normalize_process(p.clone());
+
+        assert!(is_prenex(&norm), "{:?} normalised to {:?}", p, norm);
+        assert_eq!(chn_free_names(&norm), chn_free_names(&p), "{:?} normalised to {:?}", p, norm);
+        assert_eq!(var_free_names(&norm), var_free_names(&p), "{:?} normalised to {:?}", p, norm);
+        assert_eq!(normalize_process(norm.clone()), norm, "{:?} is not stable", norm);
+
+        let lifted = lift_restrictions(p.clone());
+        assert_eq!(chn_free_names(&lifted), chn_free_names(&p), "{:?} lifted to {:?}", p, lifted);
+        assert_eq!(lift_restrictions(lifted.clone()), lifted, "{:?} is not stable", lifted);
+    }
+}
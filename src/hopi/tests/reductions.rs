@@ -0,0 +1,86 @@
+use crate::hopi::reductions::{chn_substitution, pvar_substitution, step};
+
+use super::{end, new, par, rcv, snd, var};
+
+#[test]
+fn test_step()
+{
+    // The received process runs in place of X
+    let p = par(snd("a", snd("b", end())), rcv("a", "X", par(var("X"), var("X"))));
+    assert_eq!(step(&p), vec![par(snd("b", end()), snd("b", end()))]);
+
+    // Nothing to receive
+    assert!(step(&par(snd("a", end()), rcv("b", "X", var("X")))).is_empty());
+    assert!(step(&rcv("a", "X", snd("a", var("X")))).is_empty());
+
+    // Either message can be received, the other one stays
+    let p = par(par(snd("a", snd("b", end())), snd("a", snd("c", end()))), rcv("a", "X", var("X")));
+    assert_eq!(step(&p), vec![
+        par(snd("a", snd("c", end())), snd("b", end())),
+        par(snd("a", snd("b", end())), snd("c", end())),
+    ]);
+}
+
+#[test]
+fn test_step_restrictions()
+{
+    // The terms are normalised first, the private channel is lifted and the sent process takes it along
+    let p = par(new("c", par(snd("a", snd("c", end())), rcv("c", "Y", var("Y")))), rcv("a", "X", var("X")));
+    let succs = step(&p);
+    assert_eq!(succs, vec![new("c", par(rcv("c", "Y", var("Y")), snd("c", end())))]);
+    assert_eq!(step(&succs[0]), vec![new("c", end())]);
+
+    // The c of the receiver is another channel, so the private c is renamed when lifted
+    let p = par(new("c", snd("a", snd("c", end()))), rcv("a", "X", par(var("X"), rcv("c", "Y", var("Y")))));
+    let succs = step(&p);
+    assert_eq!(succs, vec![new("c_1", par(snd("c_1", end()), rcv("c", "Y", var("Y"))))]);
+    assert!(step(&succs[0]).is_empty());
+
+    // The receiver has its own c, which is renamed when lifted
+    let p = par(snd("a", snd("c", end())), rcv("a", "X", new("c", par(var("X"), rcv("c", "Y", var("Y"))))));
+    assert_eq!(step(&p), vec![new("c_1", par(snd("c", end()), rcv("c_1", "Y", var("Y"))))]);
+}
+
+#[test]
+fn test_step_duplicated_payload()
+{
+    // Every copy of the sent process gets its own private channel
+    let private = |c: &str| par(snd(c, end()), rcv(c, "Y", var("Y")));
+    let p = par(snd("a", new("c", private("c"))), rcv("a", "X", par(var("X"), var("X"))));
+    let succs = step(&p);
+    assert_eq!(succs, vec![new("c_1", new("c", par(private("c_1"), private("c"))))]);
+
+    // So each copy can only talk to itself
+    assert_eq!(step(&succs[0]).len(), 2);
+}
+
+#[test]
+fn test_pvar_substitution()
+{
+    assert_eq!(pvar_substitution(&par(var("X"), var("Y")), "X", &snd("a", end())), par(snd("a", end()), var("Y")));
+
+    // X is bound again by the receive
+    let q = rcv("b", "X", var("X"));
+    assert_eq!(pvar_substitution(&q, "X", &snd("a", end())), q);
+
+    // The restriction of c would capture the free c of the substituted process
+    let q = new("c", par(var("X"), snd("c", end())));
+    assert_eq!(pvar_substitution(&q, "X", &snd("c", end())), new("c_1", par(snd("c", end()), snd("c_1", end()))));
+
+    // The receive of Y would capture the free Y of the substituted process
+    let q = rcv("b", "Y", par(var("X"), var("Y")));
+    assert_eq!(pvar_substitution(&q, "X", &var("Y")), rcv("b", "Y_1", par(var("Y"), var("Y_1"))));
+}
+
+#[test]
+fn test_chn_substitution()
+{
+    let p = par(snd("a", end()), rcv("a", "X", snd("b", var("X"))));
+    assert_eq!(chn_substitution(&p, "a", "c"), par(snd("c", end()), rcv("c", "X", snd("b", var("X")))));
+
+    // Bound occurrences are left alone, a restriction of the new name is renamed
+    let p = new("a", snd("a", end()));
+    assert_eq!(chn_substitution(&p, "a", "c"), p);
+    let p = new("c", par(snd("a", end()), snd("c", end())));
+    assert_eq!(chn_substitution(&p, "a", "c"), new("c_1", par(snd("c", end()), snd("c_1", end()))));
+}
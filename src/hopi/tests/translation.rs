@@ -0,0 +1,118 @@
+use crate::hopi::reductions::{par_components, step};
+use crate::hopi::syntax::Process;
+use crate::hopi::translation::{forget, forget_run, to_rollpi, to_rollpi_conf};
+use crate::rollpi::environment::launch::{run_processes_as_parties, LaunchConfig};
+use crate::rollpi::errors::TranslationError;
+use crate::rollpi::logger::trace::{MemorySink, TraceTarget};
+use crate::rollpi::parser::parse;
+use crate::rollpi::syntax::{check_initial_conf, check_initial_conf_list};
+
+use super::{end, new, par, rcv, snd, var};
+
+// The parts of the parallel composition under the top level restrictions, in a fixed order
+fn parts(p: Process) -> Vec<Process>
+{
+    let mut body = p;
+    while let Process::New(_, proc) = body {
+        body = *proc;
+    }
+
+    let mut comps = vec![];
+    par_components(body, &mut comps);
+    comps.sort_by_key(|c| format!("{:?}", c));
+    comps
+}
+
+// Runs the hopi term on the roll-pi runtime and forgets the run
+fn run_forgotten(p: &Process) -> Process
+{
+    let config = LaunchConfig {
+        trace_targets: Some(vec![TraceTarget::Memory(MemorySink::default())]),
+        ..Default::default()
+    };
+    let report = run_processes_as_parties(to_rollpi_conf(p).unwrap(), config).unwrap();
+    forget_run(&report).unwrap()
+}
+
+// The hopi term reached by the first successor of every step, for terms that can only go one way
+fn run_hopi(p: &Process) -> Process
+{
+    let mut p = p.clone();
+    while let Some(next) = step(&p).into_iter().next() {
+        p = next;
+    }
+    p
+}
+
+#[test]
+fn test_to_rollpi()
+{
+    let p = par(snd("a", snd("b", end())), rcv("a", "X", par(var("X"), rcv("b", "Y", var("Y")))));
+    assert_eq!(to_rollpi(&p), parse("a<b<0>> | a(X, k1).(X | b(Y, k2).Y)").unwrap());
+
+    // The inner X would bind twice on the same path
+    let p = rcv("a", "X", rcv("b", "X", par(var("X"), snd("c", end()))));
+    let translated = to_rollpi(&p);
+    assert_eq!(translated, parse("a(X, k1).b(X_1, k2).(X_1 | c<0>)").unwrap());
+    assert!(check_initial_conf(&translated));
+}
+
+#[test]
+fn test_to_rollpi_conf()
+{
+    // The private c is renamed as the receiver has a free c, then opened
+    let p = par(new("c", snd("a", snd("c", end()))), rcv("a", "X", par(var("X"), rcv("c", "Y", var("Y")))));
+    let conf = to_rollpi_conf(&p).unwrap();
+    assert_eq!(conf, vec![
+        ("P1".to_string(), parse("a<c_1<0>>").unwrap()),
+        ("P2".to_string(), parse("a(X, k1).(X | c(Y, k2).Y)").unwrap()),
+    ]);
+    assert!(check_initial_conf_list(&conf.into_iter().map(|(_, p)| p).collect()));
+
+    // The restriction stays in the sent process, renamed apart from the c of the receiver
+    let p = par(snd("a", new("c", snd("c", end()))), rcv("a", "X", par(var("X"), par(var("X"), snd("c", end())))));
+    let conf = to_rollpi_conf(&p).unwrap();
+    assert_eq!(conf, vec![
+        ("P1".to_string(), parse("a<new c_1.c_1<0>>").unwrap()),
+        ("P2".to_string(), parse("a(X, k1).(X | X | c<0>)").unwrap()),
+    ]);
+    assert!(check_initial_conf_list(&conf.into_iter().map(|(_, p)| p).collect()));
+
+    assert_eq!(to_rollpi_conf(&par(snd("a", var("Y")), rcv("a", "X", var("X")))), Err(TranslationError::FreeVariable("Y".to_string())));
+}
+
+#[test]
+fn test_forget()
+{
+    let p = parse("a<b<0>> | new c.a(X, k).(X | roll k | roll @k0)").unwrap();
+    assert_eq!(forget(&p), Ok(par(snd("a", snd("b", end())), new("c", rcv("a", "X", par(var("X"), par(end(), end())))))));
+
+    // Forgetting is a left inverse of the translation
+    let p = par(new("c", snd("a", snd("c", end()))), rcv("a", "X", rcv("b", "Y", par(var("X"), var("Y")))));
+    assert_eq!(forget(&to_rollpi(&p)), Ok(p));
+
+    assert_eq!(forget(&parse("a[1]<0>").unwrap()), Err(TranslationError::Data("a".to_string())));
+    assert_eq!(forget(&parse("b<a[x](X, k).X>").unwrap()), Err(TranslationError::Data("a".to_string())));
+}
+
+#[test]
+fn test_run_against_hopi()
+{
+    // The received process sends on c, which nobody receives
+    let p = par(snd("a", snd("b", end())), par(rcv("a", "X", var("X")), rcv("b", "Y", snd("c", var("Y")))));
+    assert_eq!(parts(run_forgotten(&p)), vec![snd("c", end())]);
+    assert_eq!(parts(run_forgotten(&p)), parts(run_hopi(&p)));
+
+    // A private channel sent away and used by the receiver, the receive on d is left waiting
+    let p = par(
+        new("c", par(snd("a", snd("c", snd("e", end()))), rcv("c", "Y", var("Y")))),
+        par(rcv("a", "X", var("X")), rcv("d", "Z", var("Z"))),
+    );
+    assert_eq!(parts(run_forgotten(&p)), parts(run_hopi(&p)));
+    assert_eq!(parts(run_hopi(&p)), vec![rcv("d", "Z", var("Z")), snd("e", end())]);
+
+    // The sent process is received twice, each copy talks on its own private channel
+    let p = par(snd("a", new("c", par(snd("c", snd("b", end())), rcv("c", "Y", var("Y"))))), rcv("a", "X", par(var("X"), var("X"))));
+    assert_eq!(parts(run_forgotten(&p)), parts(run_hopi(&p)));
+    assert_eq!(parts(run_hopi(&p)), vec![snd("b", end()), snd("b", end())]);
+}
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+
+use crate::rollpi::environment::runner::RunReport;
+use crate::rollpi::errors::TranslationError;
+use crate::rollpi::syntax::{self as rollpi, ChName, ProcVar, TagVar};
+
+use super::reductions::{all_var_names, fresh_name, lift_restrictions, par_components, par_of, pvar_substitution, rename_restrictions_apart, var_free_names};
+use super::syntax::Process;
+
+// Translation of hopi terms into roll-pi processes, and the forgetful map back
+//
+//   [a<P>]     = a<[P]>
+//   [a(X).P]   = a(X, k).[P]        with k a fresh tag variable
+//   [new a.P]  = new a.[P],  [P | Q] = [P] | [Q],  [X] = X,  [0] = 0
+//
+// Forgetting drops the tag variables and erases the rolls:
+//   a(X, k).P  ->  a(X).P,  roll k  ->  0,  roll @k  ->  0
+// Data has no counterpart in hopi, forgetting a send or receive that carries data is an error
+
+// The roll-pi process of a hopi term
+// A receive that binds a process variable already bound around it gets a fresh one,
+//     as roll-pi expects the process and tag variables of a path to be unique
+pub fn to_rollpi(p: &Process) -> rollpi::Process
+{
+    let mut taken = all_var_names(p);
+    let mut tags = 0;
+    _to_rollpi(p, &HashSet::new(), &mut taken, &mut tags)
+}
+
+fn _to_rollpi(p: &Process, scope: &HashSet<String>, taken: &mut HashSet<String>, tags: &mut usize) -> rollpi::Process
+{
+    match p {
+        Process::End => rollpi::Process::End,
+        Process::PVar(x) => rollpi::Process::PVar(ProcVar(x.clone())),
+        Process::New(c_name, proc) =>
+            rollpi::Process::New(ChName(c_name.clone()), Box::new(_to_rollpi(proc, scope, taken, tags))),
+        Process::Par(a, b) =>
+            rollpi::Process::Par(Box::new(_to_rollpi(a, scope, taken, tags)), Box::new(_to_rollpi(b, scope, taken, tags))),
+        Process::Send(c_name, proc) =>
+            rollpi::Process::Send(ChName(c_name.clone()), vec![], Box::new(_to_rollpi(proc, scope, taken, tags))),
+        Process::Receive(c_name, p_var, proc) => {
+            let (p_var, proc) = if scope.contains(p_var) {
+                let fresh = fresh_name(p_var, taken);
+                taken.insert(fresh.clone());
+                let renamed = pvar_substitution(proc, p_var, &Process::PVar(fresh.clone()));
+                (fresh, renamed)
+            } else {
+                (p_var.clone(), (**proc).clone())
+            };
+
+            *tags += 1;
+            let t_var = TagVar(format!("k{}", tags));
+
+            let mut scope = scope.clone();
+            scope.insert(p_var.clone());
+            let cont = _to_rollpi(&proc, &scope, taken, tags);
+            rollpi::Process::Recv(ChName(c_name.clone()), vec![], ProcVar(p_var), t_var, Box::new(cont))
+        },
+    }
+}
+
+// A configuration running the hopi term, one participant P1, P2, .. per part of its parallel composition
+// The restrictions are renamed apart and the ones of the parallel composition are lifted and opened:
+//     their names are not used anywhere else, so every participant can keep them
+// As in step, the restrictions of the sent processes stay in them, every received copy opens its own
+pub fn to_rollpi_conf(p: &Process) -> Result<Vec<(String, rollpi::Process)>, TranslationError>
+{
+    let mut free = var_free_names(p).into_iter().collect::<Vec<_>>();
+    free.sort();
+    if let Some(x) = free.into_iter().next() {
+        return Err(TranslationError::FreeVariable(x))
+    }
+
+    let mut body = lift_restrictions(rename_restrictions_apart(p.clone()));
+    while let Process::New(_, proc) = body {
+        body = *proc;
+    }
+
+    let mut comps = vec![];
+    par_components(body, &mut comps);
+
+    Ok(comps.iter().enumerate()
+        .map(|(i, comp)| (format!("P{}", i + 1), to_rollpi(comp)))
+        .collect())
+}
+
+// The hopi term of a roll-pi process, with the tags and the rolls forgotten
+pub fn forget(p: &rollpi::Process) -> Result<Process, TranslationError>
+{
+    match p {
+        rollpi::Process::End | rollpi::Process::RollV(_) | rollpi::Process::RollK(_) => Ok(Process::End),
+        rollpi::Process::PVar(ProcVar(x)) => Ok(Process::PVar(x.clone())),
+        rollpi::Process::New(ChName(c_name), proc) => Ok(Process::New(c_name.clone(), Box::new(forget(proc)?))),
+        rollpi::Process::Par(a, b) => Ok(Process::Par(Box::new(forget(a)?), Box::new(forget(b)?))),
+        rollpi::Process::Send(ChName(c_name), data, proc) => {
+            if !data.is_empty() {
+                return Err(TranslationError::Data(c_name.clone()))
+            }
+            Ok(Process::Send(c_name.clone(), Box::new(forget(proc)?)))
+        },
+        rollpi::Process::Recv(ChName(c_name), d_vars, ProcVar(p_var), _t_var, proc) => {
+            if !d_vars.is_empty() {
+                return Err(TranslationError::Data(c_name.clone()))
+            }
+            Ok(Process::Receive(c_name.clone(), p_var.clone(), Box::new(forget(proc)?)))
+        },
+    }
+}
+
+// The hopi term the run ended in: the live processes of the participants, in the order of the report,
+//     then the messages left in the channels as sends
+// The memories are left out, and so are the dead processes, ends and rolls that would be forgotten to 0,
+//     so the run is seen without its rollbacks
+pub fn forget_run(report: &RunReport) -> Result<Process, TranslationError>
+{
+    let mut comps = vec![];
+    for party in &report.parties {
+        for tagged in &party.live_state {
+            comps.push(forget(&tagged.proc.clone().to_process())?);
+        }
+    }
+
+    for (c_name, msg) in &report.pending {
+        if !msg.data.is_empty() {
+            return Err(TranslationError::Data(c_name.clone()))
+        }
+        comps.push(Process::Send(c_name.clone(), Box::new(forget(&msg.process)?)));
+    }
+
+    Ok(par_of(comps.into_iter().filter(|c| *c != Process::End).collect()))
+}
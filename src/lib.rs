@@ -1,4 +1,5 @@
 pub mod calculus;
+pub mod cli;
 pub mod hopi;
 pub mod rollpi;
 pub mod scenarios;
\ No newline at end of file
@@ -1,43 +1,22 @@
-use pi_calculus::rollpi::{syntax::*, environment::{generator, runner::{self, RunningContext}}};
-use pi_calculus::scenarios;
+use std::process::ExitCode;
 
-fn run_processes_as_parties(conf: Vec<(String, Process)>) 
-{
-    let parties = conf.iter().map(|(id, _p)| id.clone()).collect::<Vec<_>>();
-    let procs = conf.into_iter().map(|(_id, p)| p).collect::<Vec<_>>();
-
-    if check_initial_conf_list(&procs) == false {
-        panic!("The processes do not respect the checks! (pvar, tvar uniques and closed and rolls bounded)")
-    }
-
-    let conf = parties.into_iter().zip(procs.into_iter()).collect::<Vec<_>>();
-
-    // Generate the contexts for running the parties and their associated processes
-    let mut generator = generator::Generator::default();
-    
-    conf.iter().for_each(|(id, p)| {
-        let new_tag = ProcTag::PTKey(TagKey(id.clone()));
-        let state = p.clone().to_tagged_process(new_tag).to_prime_state();
-        generator.take_participant_conf(state, Some(id.clone()), None);
-    });
-
-    let (parties, hist) = generator.generate_participants();
+use pi_calculus::cli;
 
-    let runner = runner::Runner::new(RunningContext {
-        parties,
-        hist,
-    });
-
-    runner.run();
-}
-
-fn main() 
+fn main() -> ExitCode
 {
-    let chosen_scenario = 
-        scenarios::loc_types::simple_rec_lt;
-        // scenarios::roll_pi::basic_roll_pi_test;
-        // scenarios::roll_pi::basic_roll_pi_rollback;
-        // scenarios::roll_pi::complex_roll_pi_rollback;
-
-    run_processes_as_parties(chosen_scenario());
+    let command = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, cli::USAGE);
+            return ExitCode::FAILURE
+        },
+    };
+
+    match cli::execute(command, &mut std::io::stdout()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        },
+    }
 }
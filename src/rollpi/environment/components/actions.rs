@@ -1,4 +1,4 @@
-use crate::rollpi::{syntax::{PrimeState, ChName, ProcTag}, environment::{entities::participant::PartyContext, types::{PartyComm, MemoryPiece}}};
+use crate::rollpi::{syntax::{PrimeState, ChName, DataBinder, Expr, ProcTag}, environment::{entities::participant::PartyContext, types::{PartyComm, MemoryPiece}}, logger::trace::TraceEvent};
 
 use super::strategies::SimpleDeterministic::{TaggedActionContext, ActionContext};
 
@@ -7,13 +7,12 @@ pub trait ActionInterpreter : Send
     fn interpret_action(&self, context: &mut PartyContext, ctx: TaggedActionContext)
         -> PrimeState;
 
-    fn probe_recv_channel(&self, context: &PartyContext, ChName(id): &ChName)
+    // Only a message whose values fit the data variables can be taken, the other ones are not touched
+    fn probe_recv_channel(&self, context: &PartyContext, ChName(id): &ChName, d_vars: &[DataBinder])
         -> Option<PartyComm>
     {
-        let recv_channel = context.get_comm_ctx().chan_msg_ctx(&id).recv_channel;
-        let data = recv_channel.try_recv().ok();
-
-        data
+        context.get_comm_ctx().channel_pool.get_recvs(id, d_vars).iter()
+            .find_map(|recv| recv.try_recv().ok())
     }
 }
 
@@ -37,7 +36,7 @@ impl ActionInterpreter for SimpleActionInterpreter
 
         match act_ctx {
             (proc_tag, ActionContext::RollK(target_roll_key)) => {
-                ctx.get_logger().log(format!("INT: ROLLK {:?} with target {:?} \n", proc_tag, target_roll_key));
+                ctx.get_logger().event(TraceEvent::RollRequest { tag: proc_tag.clone(), target: target_roll_key.clone() });
 
                 let send_roll_ch = &ctx.get_comm_ctx().rollback_ctx.roll_tag_channel;
                 // TODO: ? See for crash handling
@@ -45,29 +44,40 @@ impl ActionInterpreter for SimpleActionInterpreter
 
                 vec![]
             },
-            (send_tag, ActionContext::Send(ChName(ch_name), proc)) => {
-                ctx.get_logger().log(format!("INT: SEND {:?} to channel {:?} \n", send_tag, ch_name));
+            (send_tag, ActionContext::Send(ChName(ch_name), data, proc)) => {
+                // The generator does not take processes with unbound data variables, so the values are known by now
+                let values = data.iter()
+                    .map(|e| e.eval().expect("Data sent before its variables were received"))
+                    .collect::<Vec<_>>();
+
+                ctx.get_logger().event(TraceEvent::Send { channel: ChName(ch_name.clone()), tag: send_tag.clone(), data: values.clone(), payload: proc.clone() });
 
-                let send_channel = ctx.get_comm_ctx().chan_msg_ctx(&ch_name).send_channel;
+                let send_channel = ctx.get_comm_ctx().channel_pool.get_send(&ch_name, &values);
                 send_channel.send(PartyComm { 
-                    sender_id: ctx.get_id().clone(), process: proc.clone(), tag: send_tag.clone() 
+                    sender_id: ctx.get_id().clone(), data: values, process: proc.clone(), tag: send_tag.clone() 
                 }).unwrap();
                 
                 vec![]
             },
-            (recv_tag, ActionContext::RecvCont(in_data, ch_name, p_var, t_var, next_proc)) => {
-                ctx.get_logger().log(format!("INT: RECV {:?} from channel {:?} \n", in_data.tag, ch_name));
-                
+            (recv_tag, ActionContext::RecvCont(in_data, ch_name, d_vars, p_var, t_var, next_proc)) => {
                 let new_tag = ctx.get_tag_ctx().create_new_tag();
 
+                ctx.get_logger().event(TraceEvent::Recv {
+                    channel: ch_name.clone(),
+                    sender: in_data.sender_id.clone(),
+                    sender_tag: in_data.tag.clone(),
+                    receiver_tag: recv_tag.clone(),
+                    new_tag: new_tag.clone(),
+                });
+
                 let send_ch = &ctx.get_comm_ctx().history_ctx.hist_tag_channel;
                 let rez = send_ch.send(MemoryPiece::new(
                     (in_data.sender_id, ctx.get_id().clone()),
                     (   in_data.tag,
-                        (ch_name.clone(), in_data.process.clone())
+                        (ch_name.clone(), in_data.data.iter().cloned().map(Expr::Val).collect(), in_data.process.clone())
                     ),
                     (   recv_tag.clone(),
-                        (ch_name.clone(), p_var.clone(), t_var.clone(), next_proc.clone())
+                        (ch_name.clone(), d_vars.clone(), p_var.clone(), t_var.clone(), next_proc.clone())
                     ),
                     new_tag.clone(),
                 ));
@@ -79,19 +89,22 @@ impl ActionInterpreter for SimpleActionInterpreter
 
                 let recv_ch = &ctx.get_comm_ctx().history_ctx.hist_conf_channel;
                 match recv_ch.recv() {
-                    Err(_err) => todo!(),
-                    Ok(rec_tag_key) => {
-                        assert_eq!(rec_tag_key, new_tag);
-                        
-                        next_proc.clone()
-                            .substitution_on_trigger(p_var.clone(), &in_data.process, t_var.clone(), &new_tag)
-                            .to_tagged_process(ProcTag::PTKey(new_tag))
-                            .to_prime_state()
-                    }
+                    Ok(rec_tag_key) => assert_eq!(rec_tag_key, new_tag),
+                    // Only happens when the run is stopped, the memory is then not recorded
+                    Err(_err) => ctx.get_logger().log(format!("The history stopped before storing the memory {}\n", new_tag.0)),
                 }
+
+                // Restrictions reaching the top level get channels no other participant knows
+                let pool = ctx.get_comm_ctx().channel_pool.clone();
+                let binds = d_vars.iter().map(|DataBinder(var, _)| var.clone()).zip(in_data.data.iter().cloned()).collect::<Vec<_>>();
+                next_proc.clone()
+                    .substitute_data(&binds)
+                    .substitution_on_trigger(p_var.clone(), &in_data.process, t_var.clone(), &new_tag)
+                    .to_tagged_process(ProcTag::PTKey(new_tag))
+                    .to_prime_state_with(&mut |ch_name| pool.fresh_channel(ch_name))
             },
             (proc_tag, ActionContext::End) => {
-                ctx.get_logger().log(format!("INT: END {:?} \n", proc_tag));
+                ctx.get_logger().event(TraceEvent::End { tag: proc_tag.clone() });
 
                 vec![]
             }
@@ -0,0 +1,139 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::rollpi::{environment::entities::participant::{PartyContext, ParticipantState}, local_types::{ChoicePoint, Label, Party}, syntax::{PrimProcess, ProcTag, TagKey}};
+
+use super::strategies::SimpleRandom::splitmix64;
+
+// Decides which label a participant sends when its local type offers a choice
+// Returning None, or a label that is not offered, leaves the choice to the strategy
+pub trait ChoiceOracle : Send
+{
+    fn choose(&mut self, point: &ChoicePoint) -> Option<Label>;
+}
+
+impl<F> ChoiceOracle for F
+where F: FnMut(&ChoicePoint) -> Option<Label> + Send
+{
+    fn choose(&mut self, point: &ChoicePoint) -> Option<Label>
+    {
+        self(point)
+    }
+}
+
+// Picks the labels in the given order, once they are used up the strategy chooses
+pub struct ScriptedChoices
+{
+    script: VecDeque<Label>,
+}
+
+impl ScriptedChoices
+{
+    pub fn new(script: Vec<&str>) -> Self
+    {
+        ScriptedChoices { script: script.into_iter().map(|l| l.to_string()).collect() }
+    }
+}
+
+impl ChoiceOracle for ScriptedChoices
+{
+    fn choose(&mut self, _point: &ChoicePoint) -> Option<Label>
+    {
+        self.script.pop_front()
+    }
+}
+
+// Picks one of the offered labels at random, the same seed gives the same labels
+pub struct RandomChoices
+{
+    rng: u64,
+}
+
+impl RandomChoices
+{
+    pub fn new(seed: u64) -> Self
+    {
+        RandomChoices { rng: seed }
+    }
+}
+
+impl ChoiceOracle for RandomChoices
+{
+    fn choose(&mut self, point: &ChoicePoint) -> Option<Label>
+    {
+        if point.labels.is_empty() {
+            return None
+        }
+
+        let pos = (splitmix64(&mut self.rng) % point.labels.len() as u64) as usize;
+        Some(point.labels[pos].clone())
+    }
+}
+
+// The choice oracle of a strategy, the choice points of the local type it runs and the choices already made
+// A choice is kept for the process the branches were split from, so the oracle is asked once per choice point,
+//     and a rolled back choice is replayed with the same label
+#[derive(Default)]
+pub struct Choices
+{
+    oracle: Option<RefCell<Box<dyn ChoiceOracle>>>,
+    points: Vec<ChoicePoint>,
+    made: RefCell<HashMap<(TagKey, Party), Label>>,
+}
+
+impl Choices
+{
+    pub fn set_oracle(&mut self, points: Vec<ChoicePoint>, oracle: Box<dyn ChoiceOracle>)
+    {
+        self.points = points;
+        self.oracle = Some(RefCell::new(oracle));
+    }
+
+    // Positions in the live state of the offers that were not chosen, the strategy must not send them yet
+    // The receiver runs the first offer it gets, so they are held back until the chosen one is sent,
+    //     then they are sent and dropped by the receiver
+    pub fn excluded(&self, pctx: &mut PartyContext, state: &ParticipantState) -> HashSet<usize>
+    {
+        let Some(oracle) = &self.oracle else { return HashSet::new() };
+
+        let mut excluded = HashSet::new();
+        for point in &self.points {
+            // The offers left of every choice, by the process they were split from
+            let mut offers: HashMap<&TagKey, Vec<(usize, &Label)>> = HashMap::new();
+            for (i, x) in state.live_state.iter().enumerate().filter(|(_, x)| !state.frozen_tags.contains(&x.tag)) {
+                if let PrimProcess::Send(ch, _, proc) = &x.proc {
+                    if let Some(pos) = point.offers.iter().position(|offer| *ch == point.channel && offer == proc) {
+                        offers.entry(origin(&x.tag)).or_default().push((i, &point.labels[pos]));
+                    }
+                }
+            }
+
+            for (tag, left) in offers {
+                let key = (tag.clone(), point.to.clone());
+                if !self.made.borrow().contains_key(&key) {
+                    if let Some(label) = oracle.borrow_mut().choose(point).filter(|l| point.labels.contains(l)) {
+                        pctx.get_logger().log(format!("Choice of {} towards {} among {:?}: {}\n", point.party, point.to, point.labels, label));
+                        self.made.borrow_mut().insert(key.clone(), label);
+                    }
+                }
+
+                if let Some(label) = self.made.borrow().get(&key) {
+                    if left.iter().any(|(_, l)| *l == label) {
+                        excluded.extend(left.iter().filter(|(_, l)| *l != label).map(|(i, _)| *i));
+                    }
+                }
+            }
+        }
+
+        excluded
+    }
+}
+
+// The parallel pieces of a process are tagged with the key of the process they were split from
+fn origin(tag: &ProcTag) -> &TagKey
+{
+    match tag {
+        ProcTag::PTKey(key) => key,
+        ProcTag::PTSplit(_, _, og_key) => og_key,
+    }
+}
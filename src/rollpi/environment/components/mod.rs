@@ -1,3 +1,4 @@
 pub mod picker;
 pub mod actions;
-pub mod strategies;
\ No newline at end of file
+pub mod strategies;
+pub mod choice;
\ No newline at end of file
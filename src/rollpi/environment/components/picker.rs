@@ -1,6 +1,6 @@
-use crate::rollpi::{syntax::PrimeState, environment::entities::participant::{PartyContext, ParticipantState}};
+use crate::rollpi::{local_types::ChoicePoint, syntax::PrimeState, environment::entities::participant::{PartyContext, ParticipantState}};
 
-use super::strategies::SimpleOrder::SimpleOrderStrat;
+use super::{choice::ChoiceOracle, strategies::SimpleOrder::SimpleOrderStrat};
 
 pub struct PrimProcTransf(pub usize, pub PrimeState);
 
@@ -9,6 +9,10 @@ pub struct PrimProcTransf(pub usize, pub PrimeState);
 pub trait Strategy : Send
 {
     fn run_strategy<'a>(&'a self, pctx: &mut PartyContext, state: &'a ParticipantState) -> Option<PrimProcTransf>;
+
+    // The oracle picks the label at the given choice points of the local type of the participant,
+    //     the offers of the branches that were not chosen are sent after the chosen one, and dropped by the receiver
+    fn set_choice_oracle(&mut self, points: Vec<ChoicePoint>, oracle: Box<dyn ChoiceOracle>);
 }
 
 impl Default for Box<dyn Strategy>
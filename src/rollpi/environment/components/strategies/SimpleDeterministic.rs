@@ -1,4 +1,4 @@
-use crate::rollpi::{environment::{components::{picker::{Strategy, PrimProcTransf}, actions::ActionInterpreter}, entities::participant::{PartyContext, ParticipantState}, types::PartyComm}, syntax::{PrimProcess, TaggedPrimProc, ProcVar, TagVar, Process, TagKey, ChName, ProcTag}};
+use crate::rollpi::{local_types::ChoicePoint, environment::{components::{picker::{Strategy, PrimProcTransf}, actions::ActionInterpreter, choice::{ChoiceOracle, Choices}}, entities::participant::{PartyContext, ParticipantState}, types::PartyComm}, syntax::{PrimProcess, TaggedPrimProc, ProcVar, TagVar, Process, TagKey, ChName, ProcTag, Expr, DataBinder}};
 
 // #[derive(Debug)]
 pub type TaggedActionContext<'a> = (&'a ProcTag, ActionContext<'a>);
@@ -9,17 +9,18 @@ pub enum ActionContext<'a>
     End,
     // Represents the prime process which reverts to the given TagKey   
     RollK(&'a TagKey),
-    // Represents the prime process - Send on channel ChName, payload Process, and the whole process with given ProcTag 
-    Send(&'a ChName, &'a Process),
+    // Represents the prime process - Send on channel ChName, payload data and Process, and the whole process with given ProcTag 
+    Send(&'a ChName, &'a Vec<Expr>, &'a Process),
     // Represents the prime process - tagged with tag - 
-    //    Receive on channel ChName, PVar and TVar replaced in the Process with what comes from PartyComm 
-    RecvCont(PartyComm, ChName, &'a ProcVar, &'a TagVar, &'a Process),
+    //    Receive on channel ChName, data variables, PVar and TVar replaced in the Process with what comes from PartyComm 
+    RecvCont(PartyComm, ChName, &'a Vec<DataBinder>, &'a ProcVar, &'a TagVar, &'a Process),
 }
 
 
 #[derive(Default)]
 pub struct SimpleDetermStrat {
     interpreter: Box<dyn ActionInterpreter>,
+    choices: Choices,
 }
 
 impl Strategy for SimpleDetermStrat
@@ -28,19 +29,21 @@ impl Strategy for SimpleDetermStrat
     {
         let ParticipantState { live_state, dead_state, frozen_tags } = state;
 
+        let excluded = self.choices.excluded(pctx, state);
+
         let non_frozen_states = || {
             live_state
                 .iter()
                 .enumerate()
-                .filter(|(_, x)| !frozen_tags.contains(&x.tag))
+                .filter(|(i, x)| !frozen_tags.contains(&x.tag) && !excluded.contains(i))
         };
 
         let pos = None;
 
         let pos = pos.or_else(|| non_frozen_states().find_map(|(i, x)| match x {
-            TaggedPrimProc{ proc: PrimProcess::Send(ch_name, send_proc), tag} => {
+            TaggedPrimProc{ proc: PrimProcess::Send(ch_name, data, send_proc), tag} => {
                 // println!("Chose send process {:?} with tag {:?} to channel {:?}", send_proc, tag, ch_name);
-                Some((i, (tag, ActionContext::Send(ch_name, send_proc))))
+                Some((i, (tag, ActionContext::Send(ch_name, data, send_proc))))
             },
             _ => None,
         }));
@@ -56,12 +59,12 @@ impl Strategy for SimpleDetermStrat
         let pos = pos.or_else(|| {
             non_frozen_states().find_map(|(i, x)| {
                 match x {
-                    TaggedPrimProc { proc: PrimProcess::Recv(ch_name, p_var, t_var, next_proc), tag } => {
+                    TaggedPrimProc { proc: PrimProcess::Recv(ch_name, d_vars, p_var, t_var, next_proc), tag } => {
                         // println!("Trying to receive from channel {:?}", ch_name);
-                        self.interpreter.probe_recv_channel(&pctx, ch_name)
+                        self.interpreter.probe_recv_channel(&pctx, ch_name, d_vars)
                             .map(|comm| {
                                 // println!("Chose receiving process {:?} with tag {:?} from channel {:?}", comm.process, comm.tag, ch_name);
-                                (i, (tag, ActionContext::RecvCont(comm, ch_name.clone(), p_var, t_var, next_proc)))
+                                (i, (tag, ActionContext::RecvCont(comm, ch_name.clone(), d_vars, p_var, t_var, next_proc)))
                             })
                     },
                     _ => None
@@ -85,4 +88,9 @@ impl Strategy for SimpleDetermStrat
         })
 
     }
+
+    fn set_choice_oracle(&mut self, points: Vec<ChoicePoint>, oracle: Box<dyn ChoiceOracle>)
+    {
+        self.choices.set_oracle(points, oracle);
+    }
 }
\ No newline at end of file
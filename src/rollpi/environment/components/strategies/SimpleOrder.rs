@@ -1,10 +1,13 @@
-use crate::rollpi::{environment::{components::{actions::ActionInterpreter, picker::{Strategy, PrimProcTransf}}, entities::participant::{PartyContext, ParticipantState}}, syntax::{TaggedPrimProc, PrimProcess, ChName, PrimeState}, logger::file_log::FileLogger};
+use std::cell::Cell;
+
+use crate::rollpi::{local_types::ChoicePoint, environment::{components::{actions::ActionInterpreter, choice::{ChoiceOracle, Choices}, picker::{Strategy, PrimProcTransf}}, entities::participant::{PartyContext, ParticipantState}}, syntax::{TaggedPrimProc, PrimProcess, PrimeState, SessionId}, logger::trace::Tracer, printer::PrettyConfig};
 
 use super::SimpleDeterministic::ActionContext;
 
 #[derive(Default)]
 pub struct SimpleOrderStrat {
     interpreter: Box<dyn ActionInterpreter>,
+    choices: Choices,
 }
 
 // The idea for this ordering of evaluation is:
@@ -15,33 +18,43 @@ pub struct SimpleOrderStrat {
 //  4. When sending recursive variables, prioritise rec_norm
 //  5. Then sending recursive variables, prioritise rec_comb
 //  6. Important, when doing a send with whatever variable, put the resulting processes at the end of the list
+// The channels are told apart by their name in the encoding, without the prefix of the session
+// The sends of 4. and 5. only happen once all the other participants are idle, so the communications
+//     in flight are done before the recursion is unfolded again
 impl Strategy for SimpleOrderStrat
 {
     fn run_strategy<'a>(&'a self, pctx: &mut PartyContext, state: &'a ParticipantState) -> Option<PrimProcTransf>
     {
         let ParticipantState { live_state, dead_state, frozen_tags } = state;
 
+        let excluded = self.choices.excluded(pctx, state);
+
         let non_frozen_states = || {
             live_state
                 .iter()
                 .enumerate()
-                .filter(|(_, x)| !frozen_tags.contains(&x.tag))
+                .filter(|(i, x)| !frozen_tags.contains(&x.tag) && !excluded.contains(i))
         };
 
-        log_state(state, pctx.get_logger());
+        let pretty_cfg = *pctx.get_pretty_cfg();
+        log_state(state, pctx.get_logger(), &pretty_cfg);
 
-        check_for_non_rec_comm(&state.live_state, pctx.get_id().clone(), pctx.get_logger());
+        let session = pctx.get_comm_ctx().session.clone();
+        check_for_non_rec_comm(&state.live_state, &session, pctx.get_id().clone(), pctx.get_logger());
+
+        let settled = pctx.is_settled();
+        let held_back = Cell::new(false);
         
         let pos = None;
 
         // Try recv processes - order: first comm_* -> rec_norm_* -> rec_comb_*
         let pos = pos.or_else(|| {non_frozen_states().find_map(|(i, x)| match x {
-            TaggedPrimProc { proc: PrimProcess::Recv(ch_name, p_var, t_var, next_proc), tag } => {
+            TaggedPrimProc { proc: PrimProcess::Recv(ch_name, d_vars, p_var, t_var, next_proc), tag } => {
                 // println!("Trying to receive from channel {:?}", ch_name);
-                self.interpreter.probe_recv_channel(&pctx, ch_name)
+                self.interpreter.probe_recv_channel(&pctx, ch_name, d_vars)
                     .map(|comm| {
                         // println!("Chose receiving process {:?} with tag {:?} from channel {:?}", comm.process, comm.tag, ch_name);
-                        (i, (tag, ActionContext::RecvCont(comm, ch_name.clone(), p_var, t_var, next_proc, )))
+                        (i, (tag, ActionContext::RecvCont(comm, ch_name.clone(), d_vars, p_var, t_var, next_proc, )))
                     })
             },
             _ => None
@@ -58,9 +71,9 @@ impl Strategy for SimpleOrderStrat
 
         // Try sends -- Channel communication
         let pos = pos.or_else(|| {non_frozen_states().find_map(|(i, x)| match x {
-            TaggedPrimProc{ proc: PrimProcess::Send(ch_name, send_proc), tag} => {
-                if ch_name.0.starts_with("comm") {
-                    Some((i, (tag, ActionContext::Send(ch_name, send_proc))))
+            TaggedPrimProc{ proc: PrimProcess::Send(ch_name, data, send_proc), tag} => {
+                if session.role(ch_name).starts_with("comm") {
+                    Some((i, (tag, ActionContext::Send(ch_name, data, send_proc))))
                 }
                 else {
                     None
@@ -71,10 +84,10 @@ impl Strategy for SimpleOrderStrat
 
         // Try sends -- The Recv side of recursion variable
         let pos = pos.or_else(|| {non_frozen_states().find_map(|(i, x)| match x {
-            TaggedPrimProc{ proc: PrimProcess::Send(ch_name, send_proc), tag} => {
-                if ch_name.0.starts_with("rec_norm") {
-                    std::thread::sleep(std::time::Duration::from_secs(2));
-                    Some((i, (tag,ActionContext::Send(ch_name, send_proc))))
+            TaggedPrimProc{ proc: PrimProcess::Send(ch_name, data, send_proc), tag} => {
+                if session.role(ch_name).starts_with("rec_norm") {
+                    held_back.set(!settled);
+                    settled.then_some((i, (tag,ActionContext::Send(ch_name, data, send_proc))))
                 }
                 else {
                     None
@@ -85,9 +98,9 @@ impl Strategy for SimpleOrderStrat
 
         // Try sends -- The rest
         let pos = pos.or_else(|| {non_frozen_states().find_map(|(i, x)| match x {
-            TaggedPrimProc{ proc: PrimProcess::Send(ch_name, send_proc), tag} => {
-                std::thread::sleep(std::time::Duration::from_secs(2));
-                Some((i, (tag, ActionContext::Send(ch_name, send_proc))))
+            TaggedPrimProc{ proc: PrimProcess::Send(ch_name, data, send_proc), tag} => {
+                held_back.set(!settled);
+                settled.then_some((i, (tag, ActionContext::Send(ch_name, data, send_proc))))
             },
             _ => None,
         })});
@@ -101,59 +114,45 @@ impl Strategy for SimpleOrderStrat
             _ => None,
         })});  
         
+        if pos.is_none() && held_back.get() {
+            pctx.defer();
+        }
+
         pos.map(|(el_pos, ac)| {
             PrimProcTransf(el_pos, 
                            self.interpreter.interpret_action(pctx, ac))
         })
     }
+
+    fn set_choice_oracle(&mut self, points: Vec<ChoicePoint>, oracle: Box<dyn ChoiceOracle>)
+    {
+        self.choices.set_oracle(points, oracle);
+    }
 }
 
-fn log_state(state: &ParticipantState, logger: &mut FileLogger)
+fn log_state(state: &ParticipantState, logger: &mut Tracer, pretty_cfg: &PrettyConfig)
 {
-    state.live_state.iter().for_each(|x| {
-        let TaggedPrimProc { tag: _, proc } = x;
-        let tip = match proc {
-            PrimProcess::End => "End".to_string(),
-            PrimProcess::RollK(_) => "Roll".to_string(),
-            PrimProcess::Send(ChName(ch_name), _) => format!("Send {}", ch_name),
-            PrimProcess::Recv(ChName(ch_name), _, _, _) => format!("Recv {}", ch_name),
-        };
-
-        logger.log(format!("- {} ", tip));
-    });
-
-    logger.log(format!(" xxx "));
-
-    state.dead_state.iter().for_each(|x| {
-        let TaggedPrimProc { tag: _, proc } = x;
-        let tip = match proc {
-            PrimProcess::End => "End".to_string(),
-            PrimProcess::RollK(_) => "Roll".to_string(),
-            PrimProcess::Send(ChName(ch_name), _) => format!("Send {}", ch_name),
-            PrimProcess::Recv(ChName(ch_name), _, _, _) => format!("Recv {}", ch_name),
-        };
-
-        logger.log(format!("{} - ", tip));
-    });
-
+    logger.log(format!("Live:\n{}\n", pretty_cfg.state(&state.live_state)));
+    logger.log(format!("Dead:\n{}\n", pretty_cfg.state(&state.dead_state)));
     logger.log(format!(" ||| \n"));
 }
 
-fn check_for_non_rec_comm(state: &PrimeState, id: String, logger: &mut FileLogger)
+fn check_for_non_rec_comm(state: &PrimeState, session: &SessionId, id: String, logger: &mut Tracer)
 {
     let mut has_non_rec_gen = false;
 
     for TaggedPrimProc { tag: _, proc } in state {
         match proc {
-            PrimProcess::Send(ChName(ch_name), _) => {
-                if !ch_name.starts_with("rec") {
+            PrimProcess::Send(ch_name, _, _) => {
+                if !session.role(ch_name).starts_with("rec") {
                     has_non_rec_gen = true;
                 }
             },
-            PrimProcess::Recv(ChName(ch_name), _, _, _) => {
-                if !ch_name.starts_with("rec") {
+            PrimProcess::Recv(ch_name, ..) => {
+                let role = session.role(ch_name);
+                if !role.starts_with("rec") {
                     has_non_rec_gen = true;
-                } else if !ch_name.starts_with("rec_norm") {
+                } else if !role.starts_with("rec_norm") {
                     has_non_rec_gen = true;
                 }
             },            
@@ -1,2 +1,190 @@
-#[allow(dead_code)]
-pub struct SimpleRandStrat {  }
\ No newline at end of file
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::rollpi::{local_types::ChoicePoint, environment::{components::{actions::ActionInterpreter, choice::{ChoiceOracle, Choices}, picker::{Strategy, PrimProcTransf}}, entities::participant::{PartyContext, ParticipantState}}, syntax::{TaggedPrimProc, PrimProcess}};
+
+use super::SimpleDeterministic::ActionContext;
+
+// Relative chances of picking each kind of enabled prime process
+// A kind with weight 0 is only picked when nothing else is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RandWeights
+{
+    pub send: u32,
+    pub recv: u32,
+    pub roll: u32,
+    pub end: u32,
+}
+
+impl Default for RandWeights
+{
+    // Every enabled process is equally likely
+    fn default() -> Self
+    {
+        RandWeights { send: 1, recv: 1, roll: 1, end: 1 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionKind
+{
+    Send,
+    Recv,
+    Roll,
+    End,
+}
+
+impl RandWeights
+{
+    pub fn weight(&self, kind: ActionKind) -> u32
+    {
+        match kind {
+            ActionKind::Send => self.send,
+            ActionKind::Recv => self.recv,
+            ActionKind::Roll => self.roll,
+            ActionKind::End => self.end,
+        }
+    }
+}
+
+// SplitMix64, small and good enough for scheduling choices
+pub fn splitmix64(state: &mut u64) -> u64
+{
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+// Picks at random among the enabled non-frozen prime processes
+// A receive is enabled when a message is waiting on its channel
+// The same seed gives the same sequence of choices for the participant, the interleaving
+//     with the other threads can still differ between runs
+pub struct SimpleRandStrat {
+    interpreter: Box<dyn ActionInterpreter>,
+    choices: Choices,
+    weights: RandWeights,
+    seed: u64,
+    rng: Cell<u64>,
+    seed_logged: Cell<bool>,
+}
+
+impl Default for SimpleRandStrat
+{
+    fn default() -> Self
+    {
+        SimpleRandStrat::new(None, RandWeights::default())
+    }
+}
+
+impl SimpleRandStrat
+{
+    // Without a seed, one is made from the current time, it is logged for replaying the run
+    pub fn new(seed: Option<u64>, weights: RandWeights) -> Self
+    {
+        let seed = seed.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or_default()
+        });
+
+        SimpleRandStrat {
+            interpreter: Box::default(),
+            choices: Choices::default(),
+            weights,
+            seed,
+            rng: Cell::new(seed),
+            seed_logged: Cell::new(false),
+        }
+    }
+
+    pub fn seed(&self) -> u64
+    {
+        self.seed
+    }
+
+    fn next_u64(&self) -> u64
+    {
+        let mut state = self.rng.get();
+        let next = splitmix64(&mut state);
+        self.rng.set(state);
+        next
+    }
+
+    // Position in the candidates of the picked one, following the weights of their kinds
+    pub fn pick(&self, candidates: &[ActionKind]) -> Option<usize>
+    {
+        if candidates.is_empty() {
+            return None
+        }
+
+        let total = candidates.iter().map(|k| self.weights.weight(*k) as u64).sum::<u64>();
+        if total == 0 {
+            return Some((self.next_u64() % candidates.len() as u64) as usize)
+        }
+
+        let mut roll = self.next_u64() % total;
+        candidates.iter().position(|k| {
+            let w = self.weights.weight(*k) as u64;
+            if roll < w { true } else { roll -= w; false }
+        })
+    }
+}
+
+impl Strategy for SimpleRandStrat
+{
+    fn run_strategy<'a>(&'a self, pctx: &mut PartyContext, state: &'a ParticipantState) -> Option<PrimProcTransf>
+    {
+        let ParticipantState { live_state, frozen_tags, .. } = state;
+
+        if !self.seed_logged.replace(true) {
+            let id = pctx.get_id().clone();
+            pctx.get_logger().log(format!("Random strategy seed for {}: {}\n", id, self.seed));
+        }
+
+        let excluded = self.choices.excluded(pctx, state);
+
+        // Receives only count if a message is already waiting, without taking it out of the channel
+        let mut candidates = live_state.iter()
+            .enumerate()
+            .filter(|(i, x)| !frozen_tags.contains(&x.tag) && !excluded.contains(i))
+            .filter_map(|(i, x)| match &x.proc {
+                PrimProcess::Send(..) => Some((i, ActionKind::Send)),
+                PrimProcess::RollK(_) => Some((i, ActionKind::Roll)),
+                PrimProcess::End => Some((i, ActionKind::End)),
+                PrimProcess::Recv(ch_name, d_vars, ..) => {
+                    let recv_channels = pctx.get_comm_ctx().channel_pool.get_recvs(&ch_name.0, d_vars);
+                    recv_channels.iter().any(|recv| !recv.is_empty()).then_some((i, ActionKind::Recv))
+                },
+            })
+            .collect::<Vec<_>>();
+
+        // Another participant can take the message first, then the receive is dropped and we pick again
+        while let Some(c) = self.pick(&candidates.iter().map(|(_, k)| *k).collect::<Vec<_>>()) {
+            let (el_pos, _) = candidates.remove(c);
+
+            let action = match &live_state[el_pos] {
+                TaggedPrimProc { proc: PrimProcess::Send(ch_name, data, send_proc), tag } =>
+                    Some((tag, ActionContext::Send(ch_name, data, send_proc))),
+                TaggedPrimProc { proc: PrimProcess::RollK(tag_key), tag } =>
+                    Some((tag, ActionContext::RollK(tag_key))),
+                TaggedPrimProc { proc: PrimProcess::End, tag } =>
+                    Some((tag, ActionContext::End)),
+                TaggedPrimProc { proc: PrimProcess::Recv(ch_name, d_vars, p_var, t_var, next_proc), tag } =>
+                    self.interpreter.probe_recv_channel(pctx, ch_name, d_vars)
+                        .map(|comm| (tag, ActionContext::RecvCont(comm, ch_name.clone(), d_vars, p_var, t_var, next_proc))),
+            };
+
+            if let Some(ac) = action {
+                return Some(PrimProcTransf(el_pos, self.interpreter.interpret_action(pctx, ac)))
+            }
+        }
+
+        None
+    }
+
+    fn set_choice_oracle(&mut self, points: Vec<ChoicePoint>, oracle: Box<dyn ChoiceOracle>)
+    {
+        self.choices.set_oracle(points, oracle);
+    }
+}
@@ -1,10 +1,11 @@
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
-use crossbeam::channel::{Receiver, Sender};
+use crossbeam::channel::{never, Receiver, Select, Sender, TryRecvError};
 
-use crate::rollpi::{environment::types::MemoryPiece, syntax::{TagKey, ProcTag, TaggedProc, Process}, logger::file_log::FileLogger};
+use crate::rollpi::{environment::types::MemoryPiece, syntax::{SessionId, TagKey, ProcTag, TaggedProc, Process}, logger::trace::{Tracer, TraceEvent}, printer::PrettyConfig};
 
-use super::participant::Runnable;
+use super::{participant::Runnable, quiescence::QuiescenceMonitor};
 
 
 // Central place for holding process memories,
@@ -27,10 +28,19 @@ pub struct HistoryParticipant
 
     // frozen tags
     frozen_tags: HashSet<ProcTag>,
+
+    // participants whose thread is gone
+    crashed: HashSet<String>,
+
+    // tags of the memories, in the order they were stored
+    memory_order: Vec<ProcTag>,
 }
 
 pub struct HistoryContext
 {
+    // Every session has its own history, which only knows the participants of the session
+    pub session: SessionId,
+
     // Channels for receiving tag creations
     pub hist_tag_recv: HashMap<String, Receiver<MemoryPiece>>,
     // Channels for sending notifications of tag creations
@@ -44,16 +54,117 @@ pub struct HistoryContext
 
     pub diss_tag_recv: Receiver<ProcTag>,
 
+    // Rollback requests coming from outside the processes, never ready unless a handle was asked for
+    pub ext_roll_recv: Receiver<RollbackRequest>,
+
     pub ress_tag_send: HashMap<String, Sender<RessurectMsg>>,
 
-    pub logger: FileLogger,
+    // Channels for telling the participants that the session was aborted, with the name of the crashed one
+    pub abort_send: HashMap<String, Sender<String>>,
+
+    pub crash_policy: CrashPolicy,
+
+    pub logger: Tracer,
+
+    pub pretty_cfg: PrettyConfig,
+
+    pub monitor: Arc<QuiescenceMonitor>,
+}
+
+// What happens to the session when a participant crashes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CrashPolicy
+{
+    // Every communication the crashed participant took part in is rolled back,
+    //     together with its causal descendants, the others keep running
+    #[default]
+    Rollback,
+    // Every other participant is told to stop
+    Abort,
+}
+
+// Snapshot of the causal information kept by the history at the end of a run
+#[derive(Debug, Clone, Default)]
+pub struct HistoryGraph
+{
+    pub tag_owner: HashMap<ProcTag, String>,
+    pub join_links: HashMap<ProcTag, ProcTag>,
+    // The memories still in the history, (sender, receiver) for the tag they created
+    pub memories: HashMap<ProcTag, (TaggedProc, TaggedProc)>,
+    pub branch_links: HashMap<ProcTag, Vec<ProcTag>>,
+    pub frozen_tags: HashSet<ProcTag>,
+    pub crashed: HashSet<String>,
+}
+
+impl HistoryGraph
+{
+    // Renders the graph in the Graphviz DOT format
+    // Nodes are tags labelled with their owner, solid edges are join links from the sender and receiver
+    //     to the tag their communication created, dashed edges are branch links to the parallel fragments
+    // Frozen tags are filled, so the part of the session a roll undoes stands out
+    pub fn to_dot(&self) -> String
+    {
+        let mut nodes = self.tag_owner.keys()
+            .chain(self.join_links.keys())
+            .chain(self.join_links.values())
+            .chain(self.memories.keys())
+            .chain(self.branch_links.keys())
+            .chain(self.branch_links.values().flatten())
+            .chain(self.frozen_tags.iter())
+            .map(|tag| tag.to_string())
+            .collect::<Vec<_>>();
+        nodes.sort();
+        nodes.dedup();
+
+        let owners = self.tag_owner.iter().map(|(tag, owner)| (tag.to_string(), owner)).collect::<HashMap<_, _>>();
+        let frozen = self.frozen_tags.iter().map(|tag| tag.to_string()).collect::<HashSet<_>>();
+
+        let mut lines = vec!["digraph history {".to_string(), "    node [shape=box];".to_string()];
+
+        for node in &nodes {
+            let label = match owners.get(node) {
+                Some(owner) => format!("{}\\n{}", node, owner),
+                None => node.clone(),
+            };
+            let style = if frozen.contains(node) { ", style=filled, fillcolor=lightblue" } else { "" };
+            lines.push(format!("    {} [label={}{}];", dot_id(node), dot_id(&label), style));
+        }
+
+        let mut join_edges = self.join_links.iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect::<Vec<_>>();
+        join_edges.sort();
+        for (from, to) in join_edges {
+            lines.push(format!("    {} -> {} [style=solid];", dot_id(&from), dot_id(&to)));
+        }
+
+        let mut branch_edges = self.branch_links.iter()
+            .flat_map(|(from, tos)| tos.iter().map(move |to| (from.to_string(), to.to_string())))
+            .collect::<Vec<_>>();
+        branch_edges.sort();
+        branch_edges.dedup();
+        for (from, to) in branch_edges {
+            lines.push(format!("    {} -> {} [style=dashed];", dot_id(&from), dot_id(&to)));
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
+// Quoted DOT identifier, the label line breaks are already escaped
+fn dot_id(s: &str) -> String
+{
+    format!("\"{}\"", s.replace('"', "\\\""))
 }
 
 impl HistoryContext
 {
-    pub fn new(arg_diss_tag_recv: Receiver<ProcTag>) -> Self
+    pub fn new(session: SessionId, arg_diss_tag_recv: Receiver<ProcTag>, pretty_cfg: PrettyConfig, monitor: Arc<QuiescenceMonitor>, logger: Tracer) -> Self
     {
         Self {
+            session,
+
             hist_tag_recv: HashMap::default(),
             hist_not_send: HashMap::default(),
 
@@ -62,13 +173,36 @@ impl HistoryContext
 
             diss_tag_recv: arg_diss_tag_recv,
 
+            ext_roll_recv: never(),
+
             ress_tag_send: HashMap::default(),
 
-            logger: FileLogger::new("Hist".to_string()),
+            abort_send: HashMap::default(),
+
+            crash_policy: CrashPolicy::default(),
+
+            logger,
+
+            pretty_cfg,
+
+            monitor,
         }
     }
 }
 
+// Name of the history when reporting to the quiescence monitor, prefixed by the session if there is one
+pub const HIST_ID: &str = "Hist";
+
+// A rollback asked for from outside the processes, see RollbackHandle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RollbackRequest
+{
+    // Roll back the communication that created the tag, as a roll on it would
+    Tag(TagKey),
+    // Roll back the last n communications the participant took part in, which are still in the history
+    Last(String, usize),
+}
+
 #[derive(Debug)]
 pub struct RessurectMsg
 {
@@ -87,9 +221,35 @@ impl HistoryParticipant
             rev_join_links: HashMap::new(),
             branch_links: HashMap::new(),
             frozen_tags: HashSet::new(),
+            crashed: HashSet::new(),
+            memory_order: Vec::new(),
         }
     }
 
+    pub fn session(&self) -> &SessionId
+    {
+        &self.ctx.session
+    }
+
+    pub fn get_monitor(&self) -> &Arc<QuiescenceMonitor>
+    {
+        &self.ctx.monitor
+    }
+
+    // Ids of the participants of the session
+    pub fn participants(&self) -> Vec<String>
+    {
+        let mut ids = self.ctx.roll_frz_send.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    // From now on rollbacks can also be requested on the given channel
+    pub fn take_rollback_requests(&mut self, recv: Receiver<RollbackRequest>)
+    {
+        self.ctx.ext_roll_recv = recv;
+    }
+
     fn _generate_links(br_links: &mut HashMap<ProcTag, Vec<ProcTag>>, 
                        join_links: &mut HashMap<ProcTag, ProcTag>,  
                        rev_join_links: &mut HashMap<ProcTag, (TaggedProc, TaggedProc)>,
@@ -122,39 +282,57 @@ impl HistoryParticipant
     }
 
     // Tries to get a tag message from all the participants and process/respond
-    fn run_tag_cycle(self: &mut Self)
+    // Returns the participants whose tag channel got disconnected, and if any message was processed
+    fn run_tag_cycle(self: &mut Self) -> (Vec<String>, bool)
     {
-        let (hctx, br_links, join_links, rev_join_links) = (&self.ctx, &mut self.branch_links, &mut self.join_links, &mut self.rev_join_links);
+        let mut processed = false;
+        let mut disconnected = vec![];
+        let (hctx, br_links, join_links, rev_join_links, memory_order) = (&mut self.ctx, &mut self.branch_links, &mut self.join_links, &mut self.rev_join_links, &mut self.memory_order);
         // Poll for receiving tagging messages
-        for (_name, recv) in &hctx.hist_tag_recv {
-            while let Ok(MemoryPiece{
-                ids: (id_send, id_recv), 
-                sender,
-                receiver, 
-                new_mem_tag, ..}) = recv.try_recv() 
+        for (name, recv) in &hctx.hist_tag_recv {
+            loop
             {
+                let mem_piece = match recv.try_recv() {
+                    Ok(mem_piece) => mem_piece,
+                    Err(TryRecvError::Empty) => break,
+                    // Only the participant holds the sender, so its thread is gone
+                    Err(TryRecvError::Disconnected) => {
+                        disconnected.push(name.clone());
+                        break
+                    },
+                };
+                processed = true;
+                hctx.logger.log(format!("Storing memory {}\n", hctx.pretty_cfg.memory(&mem_piece.sender, &mem_piece.receiver, &mem_piece.new_mem_tag)));
+
+                let MemoryPiece{
+                    ids: (id_send, id_recv), 
+                    sender,
+                    receiver, 
+                    new_mem_tag, ..} = mem_piece;
                 let new_tag = ProcTag::PTKey(new_mem_tag.clone());
-                
-                // self.ctx.logger.log(format!(""))
 
                 // Update tag owner data structure
                 self.tag_owner.insert(new_tag.clone(), id_recv.clone());
                 self.tag_owner.insert(sender.tag.clone(), id_send.clone());
                 self.tag_owner.insert(receiver.tag.clone(), id_recv.clone());
 
+                memory_order.push(new_tag);
+
                 // update causal dependency links
                 HistoryParticipant::_generate_links(br_links, join_links, rev_join_links, 
                     sender, receiver, &ProcTag::PTKey(new_mem_tag.clone()),); 
                 
                 if let Some(x) = hctx.hist_not_send.get(&id_recv) {
-                    // TODO: ? Check what to do in case of crash
+                    // The receiver can not crash while waiting for the confirmation
                     let _ = x.send(new_mem_tag);
                 }
             }
         }
+
+        (disconnected, processed)
     }
 
-    fn _send_freeze_sgn_dfs(logger: &mut FileLogger,
+    fn _send_freeze_sgn_dfs(logger: &mut Tracer,
                             join_links: &HashMap<ProcTag, ProcTag>, 
                             branch_links: &HashMap<ProcTag, Vec<ProcTag>>, 
                             roll_frz_send_map: &HashMap<String, Sender<ProcTag>>, 
@@ -171,8 +349,8 @@ impl HistoryParticipant
         let signal_ch = roll_frz_send_map.get(owner).unwrap();
         
 
-        logger.log(format!("Sending freeze signal to {:?}\n", p));
-        // TODO: ? Check what to do in case of crash
+        logger.event(TraceEvent::FreezeSignal { tag: p.clone(), owner: owner.clone() });
+        // Fails if the owner crashed, its tags get dissapeared by the history instead
         let _ = signal_ch.send(p.clone());
 
         frozen_tags.insert(p.clone());
@@ -192,8 +370,9 @@ impl HistoryParticipant
 
     // TODO: Make DFS run through join links & branch links
     // Receives a rollback request from a participant and sends a freeze signal to all relevant participants
-    fn run_rollback_cycle(self: &mut Self)
+    fn run_rollback_cycle(self: &mut Self) -> bool
     {
+        let mut processed = false;
         let (join_links, 
              branch_links, 
              frozen_tags, 
@@ -204,70 +383,230 @@ impl HistoryParticipant
         
         for (_name, recv) in roll_tag_recv {
             while let Ok(proc_tag) = recv.try_recv() {
-                logger.log(format!("Start rollback on tag: {:?}\n", proc_tag));
+                processed = true;
+                logger.log(format!("Start rollback on tag: {}\n", proc_tag));
 
                 HistoryParticipant::_send_freeze_sgn_dfs(logger, join_links, branch_links, roll_frz_send, frozen_tags, tag_owner, &proc_tag);
             }
         }
+
+        processed
+    }
+
+    // Handles the rollback requests from outside the processes, in the same way as the ones from the participants
+    // Requests on tags or participants this history does not know about are logged and dropped
+    fn run_external_rollback_cycle(self: &mut Self) -> bool
+    {
+        let mut processed = false;
+
+        while let Ok(request) = self.ctx.ext_roll_recv.try_recv() {
+            processed = true;
+            let tags = match request {
+                RollbackRequest::Tag(key) => {
+                    let tag = ProcTag::PTKey(key);
+                    if self.rev_join_links.contains_key(&tag) {
+                        vec![tag]
+                    } else {
+                        self.ctx.logger.log(format!("Ignoring external rollback on tag {}, it has no memory\n", tag));
+                        vec![]
+                    }
+                },
+                RollbackRequest::Last(id, n) => {
+                    let involved = self.memory_order.iter()
+                        .filter(|tag| self.rev_join_links.get(tag).is_some_and(|(sender, receiver)| {
+                            [&sender.tag, &receiver.tag].into_iter().any(|t| self.tag_owner.get(t) == Some(&id))
+                        }))
+                        .collect::<Vec<_>>();
+                    involved[involved.len().saturating_sub(n)..].iter().map(|tag| (*tag).clone()).collect()
+                },
+            };
+
+            for tag in tags {
+                self.ctx.logger.log(format!("Start external rollback on tag: {}\n", tag));
+                HistoryParticipant::_send_freeze_sgn_dfs(&mut self.ctx.logger, &self.join_links, &self.branch_links,
+                    &self.ctx.roll_frz_send, &mut self.frozen_tags, &self.tag_owner, &tag);
+            }
+        }
+
+        processed
     }
 
     // TODO: Check data structures are correctly updated
-    fn run_dissapear_cycle(self: &mut Self)
+    fn run_dissapear_cycle(self: &mut Self) -> bool
     {
+        let mut processed = false;
+
         while let Ok(diss_tag) = self.ctx.diss_tag_recv.try_recv() {
-            match self.rev_join_links.remove(&diss_tag)
-            {
-                Some((sender, receiver)) => {
-                    // Check that the sender is of enum variant Send
-                    assert!(matches!(sender.proc, Process::Send(..)));
-                    assert!(matches!(receiver.proc, Process::Recv(..)));
+            processed = true;
+            self.dissapear_tag(diss_tag);
+        } 
 
-                    let sender_id = self.tag_owner.get(&sender.tag).unwrap();
-                    let receiver_id = self.tag_owner.get(&receiver.tag).unwrap();
+        processed
+    }
 
-                    let sender_ch = self.ctx.ress_tag_send.get(sender_id).unwrap();
-                    let receiver_ch = self.ctx.ress_tag_send.get(receiver_id).unwrap();
+    // Blocks until a participant sends a memory, a rollback request or a dissapear notification,
+    //     or the monitor asks to look again. A crashed participant disconnects its channels, which also wakes it up
+    fn wait_for_event(&self, waker: &Receiver<()>)
+    {
+        let mut sel = Select::new();
+        for recv in self.ctx.hist_tag_recv.values() {
+            sel.recv(recv);
+        }
+        for recv in self.ctx.roll_tag_recv.values() {
+            sel.recv(recv);
+        }
+        sel.recv(&self.ctx.diss_tag_recv);
+        sel.recv(&self.ctx.ext_roll_recv);
+        sel.recv(waker);
 
-                    let sender_msg = RessurectMsg {
-                        dissapeared_tag: diss_tag.clone(),
-                        ress_tagged_proc: sender.clone(),
-                    };
+        sel.ready();
+    }
 
-                    let recver_msg = RessurectMsg {
-                        dissapeared_tag: diss_tag.clone(),
-                        ress_tagged_proc: receiver.clone(),
-                    };
+    // Copy of the causal information the history has right now
+    pub fn snapshot(&self) -> HistoryGraph
+    {
+        HistoryGraph {
+            tag_owner: self.tag_owner.clone(),
+            join_links: self.join_links.clone(),
+            memories: self.rev_join_links.clone(),
+            branch_links: self.branch_links.clone(),
+            frozen_tags: self.frozen_tags.clone(),
+            crashed: self.crashed.clone(),
+        }
+    }
 
-                    self.ctx.logger.log(format!("Sending ressurect message {:?}\n", sender_msg));
-                    self.ctx.logger.log(format!("Sending ressurect message {:?}\n", recver_msg));
+    // Sends the sender and receiver of the memory of the tag back to their owners
+    fn dissapear_tag(self: &mut Self, diss_tag: ProcTag)
+    {
+        self.memory_order.retain(|tag| *tag != diss_tag);
+        match self.rev_join_links.remove(&diss_tag)
+        {
+            Some((sender, receiver)) => {
+                // Check that the sender is of enum variant Send
+                assert!(matches!(sender.proc, Process::Send(..)));
+                assert!(matches!(receiver.proc, Process::Recv(..)));
+
+                let sender_id = self.tag_owner.get(&sender.tag).unwrap();
+                let receiver_id = self.tag_owner.get(&receiver.tag).unwrap();
+
+                let sender_ch = self.ctx.ress_tag_send.get(sender_id).unwrap();
+                let receiver_ch = self.ctx.ress_tag_send.get(receiver_id).unwrap();
+
+                let sender_msg = RessurectMsg {
+                    dissapeared_tag: diss_tag.clone(),
+                    ress_tagged_proc: sender.clone(),
+                };
+
+                let recver_msg = RessurectMsg {
+                    dissapeared_tag: diss_tag.clone(),
+                    ress_tagged_proc: receiver.clone(),
+                };
+
+                self.ctx.logger.event(TraceEvent::Resurrect { disappeared: diss_tag.clone(), tag: sender.tag.clone(), owner: sender_id.clone() });
+                self.ctx.logger.event(TraceEvent::Resurrect { disappeared: diss_tag.clone(), tag: receiver.tag.clone(), owner: receiver_id.clone() });
+
+                // Fails if the owner crashed, then there is nothing left to ressurect
+                let _ = sender_ch.send(sender_msg);
+
+                let _ = receiver_ch.send(recver_msg);
+            },
+            None => {
+                println!("Received a dissapear tag that no longer is in the history's records")
+            },
+        }
+    }
 
-                    // TODO: ? Check if can ignore the result
-                    let _ = sender_ch.send(sender_msg);
+    // The crashed participants can not dissapear their frozen processes anymore,
+    //     so the history does it for the frozen memories they own
+    fn dissapear_crashed_tags(self: &mut Self)
+    {
+        let mut crashed_tags = self.frozen_tags.iter()
+            .filter(|tag| self.rev_join_links.contains_key(tag))
+            .filter(|tag| self.tag_owner.get(tag).is_some_and(|owner| self.crashed.contains(owner)))
+            .cloned()
+            .collect::<Vec<_>>();
+        crashed_tags.sort_by_key(|tag| tag.to_string());
+
+        for tag in crashed_tags {
+            self.ctx.logger.event(TraceEvent::Disappear { tag: tag.clone() });
+            self.dissapear_tag(tag);
+        }
+    }
 
-                    // TODO: ? Check if can ignore the result
-                    let _ = receiver_ch.send(recver_msg);
+    fn run_crash_cycle(self: &mut Self, crashed: Vec<String>)
+    {
+        for id in crashed {
+            self.ctx.logger.log(format!("Participant {} crashed\n", id));
+
+            self.ctx.hist_tag_recv.remove(&id);
+            self.ctx.roll_tag_recv.remove(&id);
+            self.ctx.monitor.forget(&id);
+            self.crashed.insert(id.clone());
+
+            match self.ctx.crash_policy {
+                CrashPolicy::Rollback => {
+                    // Roll back to before the first communication of the crashed participant
+                    let mut keys = self.rev_join_links.iter()
+                        .filter(|(_, (sender, receiver))| {
+                            [&sender.tag, &receiver.tag].into_iter().any(|tag| self.tag_owner.get(tag) == Some(&id))
+                        })
+                        .map(|(key, _)| key.clone())
+                        .collect::<Vec<_>>();
+                    keys.sort_by_key(|tag| tag.to_string());
+
+                    for key in keys {
+                        self.ctx.logger.log(format!("Start rollback on tag: {}\n", key));
+                        HistoryParticipant::_send_freeze_sgn_dfs(&mut self.ctx.logger, &self.join_links, &self.branch_links,
+                            &self.ctx.roll_frz_send, &mut self.frozen_tags, &self.tag_owner, &key);
+                    }
                 },
-                None => {
-                    println!("Received a dissapear tag that no longer is in the history's records")
+                CrashPolicy::Abort => {
+                    for (party, abort_ch) in &self.ctx.abort_send {
+                        if !self.crashed.contains(party) {
+                            self.ctx.logger.log(format!("Aborting the session of {}\n", party));
+                            let _ = abort_ch.send(id.clone());
+                        }
+                    }
                 },
             }
-            
-        } 
+        }
+
+        self.dissapear_crashed_tags();
     }
 
 }
 
 impl Runnable for HistoryParticipant
 {
-    fn run(mut self: Self)
+    type Output = HistoryGraph;
+
+    fn run(mut self: Self) -> HistoryGraph
     {
-        loop {
-            self.run_tag_cycle();
+        let monitor = self.ctx.monitor.clone();
+        let hist_id = self.ctx.session.party_id(HIST_ID);
+        let waker = monitor.waker(&hist_id);
+
+        while !monitor.is_done() {
+            while waker.try_recv().is_ok() {}
+            let seen_epoch = monitor.epoch();
 
-            self.run_rollback_cycle();
-            
-            self.run_dissapear_cycle();
+            let (crashed, tagged) = self.run_tag_cycle();
+            let rolled = self.run_rollback_cycle() | self.run_external_rollback_cycle();
+            let dissapeared = self.run_dissapear_cycle();
+            let any_crash = !crashed.is_empty();
+
+            if any_crash || rolled {
+                self.run_crash_cycle(crashed);
+            }
+
+            if tagged || rolled || dissapeared || any_crash {
+                monitor.report_active(&hist_id);
+            } else if !monitor.report_idle(&hist_id, seen_epoch, false) {
+                self.wait_for_event(&waker);
+            }
         }
+
+        self.snapshot()
     }
 }
 
@@ -1,3 +1,4 @@
 pub mod history;
 pub mod participant;
-pub mod tag_creator;
\ No newline at end of file
+pub mod tag_creator;
+pub mod quiescence;
\ No newline at end of file
@@ -1,11 +1,14 @@
-use core::time;
 use std::collections::{HashMap, HashSet};
-use crate::rollpi::{environment::{components::picker::{Strategy, PrimProcTransf}, types::{MemoryPiece, PartyComm}}, syntax::{TagKey, PrimeState, ProcTag, TaggedPrimProc, PrimProcess, TaggedProc, Process}, logger::file_log::FileLogger};
+use std::sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}};
+use crate::rollpi::{environment::{components::picker::{Strategy, PrimProcTransf}, types::{MemoryPiece, PartyComm}}, syntax::{ChName, SessionId, TagKey, PrimeState, ProcTag, TaggedPrimProc, PrimProcess, TaggedProc, Process, DataBinder, Sort, Value}, logger::trace::{Tracer, TraceEvent}, printer::PrettyConfig};
 
 
 pub trait Runnable : Send
 {
-    fn run(self: Self);
+    // What is left of the entity once the run is over
+    type Output : Send;
+
+    fn run(self: Self) -> Self::Output;
 }
 
 pub trait ContextGetter 
@@ -23,6 +26,10 @@ pub struct Participant
     party_context: PartyContext,
     
     strategy: Box<dyn Strategy>,
+
+    // Injected crash: the thread stops after taking this many actions
+    crash_after: Option<usize>,
+    steps_taken: usize,
 }
 
 pub struct ParticipantState
@@ -33,12 +40,39 @@ pub struct ParticipantState
     pub frozen_tags: HashSet<ProcTag>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartyStatus
+{
+    // Ran until the whole system was quiescent
+    Finished,
+    // Stopped at its injected crash point
+    Crashed,
+    // Told by the history that the session was aborted after a crash
+    Aborted,
+}
+
+// Final state of a participant at the end of a run
+#[derive(Debug, Clone)]
+pub struct PartyReport
+{
+    pub id: String,
+    pub status: PartyStatus,
+    pub live_state: PrimeState,
+    pub dead_state: PrimeState,
+}
+
 pub struct PartyContext
 {
     id: String,
     comm_ctx: PartyCommCtx,
     tag_ctx: TagCreator,
-    logger: FileLogger,
+    logger: Tracer,
+    pretty_cfg: PrettyConfig,
+
+    // All the other entities were idle when the participant started looking for an action
+    settled: bool,
+    // The strategy held back an action until the system is settled
+    deferred: bool,
 }
 
 impl PartyContext
@@ -58,25 +92,40 @@ impl PartyContext
         &mut self.tag_ctx
     }
 
-    pub fn get_logger(&mut self) -> &mut FileLogger
+    pub fn get_logger(&mut self) -> &mut Tracer
     {
         &mut self.logger
     }
+
+    pub fn get_pretty_cfg(&self) -> &PrettyConfig
+    {
+        &self.pretty_cfg
+    }
+
+    pub fn is_settled(&self) -> bool
+    {
+        self.settled
+    }
+
+    // Called by a strategy that does not take an action yet, the participant is woken up
+    //     to look again once all the other entities are idle
+    pub fn defer(&mut self)
+    {
+        self.deferred = true;
+    }
 }
 
 pub struct PartyCommCtx
 {
+    // The session the channels of the pool belong to
+    pub session: SessionId,
     pub channel_pool: PartyChPool,
     pub history_ctx: HistTagContext,
     pub rollback_ctx: RollbackContext,
     pub dissapear_ctx: DissapearContext,
     pub ressurect_ctx: RessurectContext,
-}
-
-pub struct ChMsgContext<'a>
-{
-    pub send_channel: &'a Sender<PartyComm>,
-    pub recv_channel: &'a Receiver<PartyComm>,
+    pub abort_ctx: AbortContext,
+    pub monitor: Arc<QuiescenceMonitor>,
 }
 
 pub struct HistTagContext
@@ -102,12 +151,25 @@ pub struct RessurectContext
     pub ress_recv_channel: Receiver<RessurectMsg>,
 }
 
+pub struct AbortContext
+{
+    // Receives the id of the crashed participant that caused the abort
+    pub abort_recv_channel: Receiver<String>,
+}
+
+
+// Both ends of the queue of every (channel, sorts) pair
+type ChQueues = HashMap<(String, Vec<Sort>), (Sender<PartyComm>, Receiver<PartyComm>)>;
 
+// The channels are shared between all the participants, new ones are allocated
+//     when a restriction is opened or when a name is first used
+// Every channel has one queue per sorts of the values sent on it, so a receive only looks
+//     at the messages it can take and the others stay in order
 #[derive(Default, Clone)]
 pub struct PartyChPool
 {
-    senders: HashMap<String, Sender<PartyComm>>,
-    receivers: HashMap<String, Receiver<PartyComm>>,
+    channels: Arc<Mutex<ChQueues>>,
+    next_fresh: Arc<AtomicUsize>,
 }
 
 impl PartyChPool
@@ -115,42 +177,71 @@ impl PartyChPool
     pub fn new(it: impl Iterator<Item = String>) 
         -> Self
     {
-        let mut receivers = HashMap::new();
-        let mut senders = HashMap::new();
+        let pool = Self::default();
 
         for id in it {
-            println!("Creating channel-pair for {}", id);
-            let (tx, rx) = unbounded::<PartyComm>();
-            receivers.insert(id.clone(), rx);
-            senders.insert(id, tx);
+            pool.queue(&id, &[]);
         }
 
-        Self {
-            senders,
-            receivers,
-        }
+        pool
     }
 
-    pub fn get_recv(&self, id: &str) -> &Receiver<PartyComm>
+    fn queue(&self, id: &str, sorts: &[Sort]) -> (Sender<PartyComm>, Receiver<PartyComm>)
     {
-        self.receivers.get(id).unwrap()
+        self.channels.lock().unwrap()
+            .entry((id.to_string(), sorts.to_vec()))
+            .or_insert_with(unbounded::<PartyComm>)
+            .clone()
     }
 
-    pub fn get_send(&self, id: &str) -> &Sender<PartyComm>
+    // The queues of the channel holding values the data variables can take, ordered by sorts
+    // A queue that does not exist yet has no messages, the monitor wakes the receivers up once it gets one
+    pub fn get_recvs(&self, id: &str, d_vars: &[DataBinder]) -> Vec<Receiver<PartyComm>>
     {
-        self.senders.get(id).unwrap()
+        let mut queues = self.channels.lock().unwrap().iter()
+            .filter(|((ch_name, sorts), _)| {
+                ch_name == id && sorts.len() == d_vars.len() && d_vars.iter().zip(sorts).all(|(b, s)| b.accepts_sort(s))
+            })
+            .map(|((_, sorts), (_, recv))| (sorts.clone(), recv.clone()))
+            .collect::<Vec<_>>();
+        queues.sort_by(|(a, _), (b, _)| a.cmp(b));
+        queues.into_iter().map(|(_, recv)| recv).collect()
     }
-}
 
-impl PartyCommCtx
-{
-    pub fn chan_msg_ctx(&self, ch: &str) -> ChMsgContext
+    pub fn get_send(&self, id: &str, values: &[Value]) -> Sender<PartyComm>
     {
-        ChMsgContext {
-            send_channel: self.channel_pool.get_send(ch),
-            recv_channel: self.channel_pool.get_recv(ch),
+        self.queue(id, &values.iter().map(|v| v.sort()).collect::<Vec<_>>()).0
+    }
+
+    // Allocates a channel that no participant knows yet, named _ch_<n>_<base>
+    // Every name of the session has a queue, so the names that already have one are skipped
+    pub fn fresh_channel(&self, ChName(base): &ChName) -> ChName
+    {
+        let mut channels = self.channels.lock().unwrap();
+        loop {
+            let name = format!("_ch_{}_{}", self.next_fresh.fetch_add(1, Ordering::SeqCst), base);
+            if !channels.keys().any(|(ch_name, _)| *ch_name == name) {
+                channels.insert((name.clone(), vec![]), unbounded::<PartyComm>());
+                return ChName(name)
+            }
         }
     }
+
+    // Whether both handles refer to the channels of the same session
+    pub fn same_pool(&self, other: &PartyChPool) -> bool
+    {
+        Arc::ptr_eq(&self.channels, &other.channels)
+    }
+
+    // Takes out the messages that were sent but never received, sorted by channel name
+    pub fn drain_pending(&self) -> Vec<(String, PartyComm)>
+    {
+        let mut pending = self.channels.lock().unwrap().iter()
+            .flat_map(|((ch_name, _), (_, recv))| recv.try_iter().map(|msg| (ch_name.clone(), msg)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        pending.sort_by(|(a, _), (b, _)| a.cmp(b));
+        pending
+    }
 }
 
 impl Participant 
@@ -160,6 +251,8 @@ impl Participant
         state: PrimeState,
         strategy: Box<dyn Strategy>,
         comm_context: PartyCommCtx,
+        pretty_cfg: PrettyConfig,
+        tracer: Tracer,
     ) -> Self
     {
         Self {
@@ -173,12 +266,45 @@ impl Participant
                 id: id.clone(),
                 comm_ctx: comm_context,
                 tag_ctx: TagCreator::new(id.clone()),
-                logger: FileLogger::new(format!("{}", id)),
+                logger: tracer,
+                pretty_cfg,
+                settled: false,
+                deferred: false,
             },
+            crash_after: None,
+            steps_taken: 0,
         }
     }
 
-    fn evolve_state(&mut self)
+    pub fn get_id(&self) -> &String
+    {
+        &self.party_context.id
+    }
+
+    // The participant stops, as if killed, once it took the given number of actions
+    pub fn set_crash_after(&mut self, steps: usize)
+    {
+        self.crash_after = Some(steps);
+    }
+
+    // The tag keys of the initial configuration of the session, the participant never creates one of them
+    pub fn reserve_tag_keys(&mut self, keys: Arc<HashSet<TagKey>>)
+    {
+        self.party_context.tag_ctx.reserve(keys);
+    }
+
+    pub fn get_channel_pool(&self) -> &PartyChPool
+    {
+        &self.party_context.comm_ctx.channel_pool
+    }
+
+    pub fn get_monitor(&self) -> &Arc<QuiescenceMonitor>
+    {
+        &self.party_context.comm_ctx.monitor
+    }
+
+    // Returns true if an action was taken
+    fn evolve_state(&mut self) -> bool
     {
         let (ctx, state, strat) = (&mut self.party_context, &mut self.state, &*self.strategy);
         let action = strat.run_strategy(ctx, state);
@@ -194,11 +320,9 @@ impl Participant
                 };
 
                 pr_state.extend(proc.into_iter());
+                true
             },
-            None => {
-                println!("I am done .... {}", ctx.get_id());
-                std::thread::sleep(time::Duration::from_secs(1));
-            }
+            None => false,
         }
     }   
 
@@ -208,28 +332,34 @@ impl Participant
         //       like remembering causal links
     }
 
-    fn freeze_logic(self: &mut Self)
+    fn freeze_logic(self: &mut Self) -> bool
     {
         let (state, ctx) = (&mut self.state, &mut self.party_context);
         let (comm_ctx, logger) = (&mut ctx.comm_ctx, &mut ctx.logger);
         let ParticipantState{live_state, dead_state, frozen_tags} = state;
+        let mut froze_any = false;
 
         while let Ok(tag) = comm_ctx.rollback_ctx.freeze_not_channel.try_recv() {
             Participant::_mark_live_proc_as_frozen(&tag, live_state);
             frozen_tags.insert(tag.clone());
+            froze_any = true;
 
-            logger.log(format!("Freezing process with tag {:?}\n", tag));
+            logger.event(TraceEvent::FreezeSignal { tag: tag.clone(), owner: ctx.id.clone() });
         }
+
+        froze_any
     }
     
     // For all live processes that are frozen, delete them and send dissapear notif
     // For all the split processes, roll back to the united one, and dissapear that one 
     //     (by dissapearing all the fragments and sending dissapear notif for the original one)
-    fn dissapear_logic(self: &mut Self)
+    // Returns true if some process dissapeared
+    fn dissapear_logic(self: &mut Self) -> bool
     {
         let (state, ctx) = (&mut self.state, &mut self.party_context);
         let (comm_ctx, logger) = (&ctx.comm_ctx, &mut ctx.logger);
         let ParticipantState{live_state, dead_state, frozen_tags} = state;
+        let procs_before = live_state.len() + dead_state.len();
         let mut key_fragments = HashMap::new();
         
         // Part 1: Identify, send dissapear notif and eliminate from the frozen processes set the simple frozen tags
@@ -247,7 +377,7 @@ impl Participant
                             let _ = diss_ch.send(tag.clone());
                             assert!(frozen_tags.remove(tag));
     
-                            logger.log(format!("Dissapearing process with tag {:?}", tag));
+                            logger.event(TraceEvent::Disappear { tag: tag.clone() });
     
                             false
                         } else {
@@ -296,7 +426,7 @@ impl Participant
             // TODO: ? Decide if ignore or not send error
             let _ = diss_ch.send(ProcTag::PTKey(k.clone()));
 
-            logger.log(format!("Dissapearing process with tag {:?}", k));
+            logger.event(TraceEvent::Disappear { tag: ProcTag::PTKey(k.clone()) });
         });
 
         // Eliminate from the frozen processes set
@@ -308,12 +438,14 @@ impl Participant
             }
         });
 
+        live_state.len() + dead_state.len() != procs_before
     }
 
-    fn ressurect_logic(self: &mut Self)
+    fn ressurect_logic(self: &mut Self) -> bool
     {
         let (state, ctx) = (&mut self.state, &self.party_context);
         let ress_ch = &ctx.get_comm_ctx().ressurect_ctx.ress_recv_channel;
+        let mut ressurected_any = false;
 
         while let Ok(RessurectMsg { dissapeared_tag: _, ress_tagged_proc }) = ress_ch.try_recv() {
             // TODO: Inspect if this should be here, normaly remoing from frozen happens in dissapear logic
@@ -323,12 +455,64 @@ impl Participant
             
             // assert that proc is either Send or Recv
             let is_send_recv = match proc {
-                Process::Send(_, _) | Process::Recv(_, _, _, _) => true,
+                Process::Send(..) | Process::Recv(..) => true,
                 _ => false,
             };
             assert!(is_send_recv);
 
             state.live_state.append(&mut vec![TaggedPrimProc { tag, proc: proc.to_prime_process() }]);
+            ressurected_any = true;
+        }
+
+        ressurected_any
+    }
+
+    // Returns the crashed participant, if the history aborted the session
+    fn abort_logic(self: &mut Self) -> Option<String>
+    {
+        let ctx = &mut self.party_context;
+        let crashed = ctx.comm_ctx.abort_ctx.abort_recv_channel.try_recv().ok()?;
+        ctx.logger.log(format!("Session aborted because {} crashed\n", crashed));
+
+        Some(crashed)
+    }
+
+    // Blocks until a message arrives for one of the live receives, a freeze, ressurect or abort signal
+    //     comes from the history, or the monitor asks to look again
+    fn wait_for_event(&self, waker: &Receiver<()>)
+    {
+        let comm_ctx = &self.party_context.comm_ctx;
+        let ParticipantState { live_state, frozen_tags, .. } = &self.state;
+
+        let recv_channels = live_state.iter()
+            .filter(|x| !frozen_tags.contains(&x.tag))
+            .filter_map(|x| match &x.proc {
+                PrimProcess::Recv(ChName(ch), d_vars, ..) => Some(comm_ctx.channel_pool.get_recvs(ch, d_vars)),
+                _ => None,
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut sel = Select::new();
+        for recv in &recv_channels {
+            sel.recv(recv);
+        }
+        sel.recv(&comm_ctx.rollback_ctx.freeze_not_channel);
+        sel.recv(&comm_ctx.ressurect_ctx.ress_recv_channel);
+        sel.recv(&comm_ctx.abort_ctx.abort_recv_channel);
+        sel.recv(waker);
+
+        // Only waits for something to be ready, the messages are taken by the next round
+        sel.ready();
+    }
+
+    fn report(self: Self, status: PartyStatus) -> PartyReport
+    {
+        PartyReport {
+            id: self.party_context.id,
+            status,
+            live_state: self.state.live_state,
+            dead_state: self.state.dead_state,
         }
     }
 
@@ -336,20 +520,55 @@ impl Participant
 
 impl Runnable for Participant
 {
-    fn run(mut self: Self)
+    type Output = PartyReport;
+
+    // Runs until the monitor detects that no entity can take any action anymore
+    fn run(mut self: Self) -> PartyReport
     {
-        loop {
-            self.evolve_state();
+        let monitor = self.party_context.comm_ctx.monitor.clone();
+        let id = self.party_context.id.clone();
+        let waker = monitor.waker(&id);
+
+        while !monitor.is_done() {
+            // Nothing is sent to anyone, the history finds out when the channels get disconnected
+            if self.crash_after.is_some_and(|steps| self.steps_taken >= steps) {
+                self.party_context.logger.log(format!("Crashing {} after {} steps\n", id, self.steps_taken));
+                return self.report(PartyStatus::Crashed)
+            }
 
-            self.freeze_logic();
+            if self.abort_logic().is_some() {
+                monitor.forget(&id);
+                return self.report(PartyStatus::Aborted)
+            }
 
-            self.dissapear_logic();
+            // Any earlier signal is covered by reading the epoch now
+            while waker.try_recv().is_ok() {}
+            let seen_epoch = monitor.epoch();
+            self.party_context.settled = monitor.others_idle(&id, seen_epoch);
+            self.party_context.deferred = false;
 
-            self.ressurect_logic();
+            // All the steps run every time, none of them is skipped if an earlier one did something
+            let evolved = self.evolve_state();
+            if evolved {
+                self.steps_taken += 1;
+            }
+            let froze = self.freeze_logic();
+            let dissapeared = self.dissapear_logic();
+            let ressurected = self.ressurect_logic();
+
+            if evolved || froze || dissapeared || ressurected {
+                monitor.report_active(&id);
+            } else if !monitor.report_idle(&id, seen_epoch, self.party_context.deferred) {
+                self.wait_for_event(&waker);
+            }
         }
+
+        self.party_context.logger.log(format!("Run is over for {}\n", id));
+
+        self.report(PartyStatus::Finished)
     }
 }
 
-use crossbeam::channel::{Sender, Receiver, unbounded};
+use crossbeam::channel::{Sender, Receiver, Select, unbounded};
 
-use super::{tag_creator::TagCreator, history::RessurectMsg};
+use super::{tag_creator::TagCreator, history::RessurectMsg, quiescence::QuiescenceMonitor};
@@ -0,0 +1,220 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use crossbeam::channel::{bounded, Receiver, RecvTimeoutError, Sender};
+
+// Shared between the participants and the history to detect that the whole system is quiescent
+// Every action bumps an epoch counter, an entity that finds nothing to do reports the epoch it saw
+//     before looking. The run is over when all entities reported being idle at the current epoch,
+//     since then no action happened while any of them was looking
+// An idle entity blocks on its own channels together with a waker, which the monitor signals
+//     after every action so the entity looks again and reports the new epoch
+// While the run is held from the outside, a quiescent system keeps waiting instead of being done
+pub struct QuiescenceMonitor
+{
+    state: Mutex<MonitorState>,
+    // Disconnected once the run is over
+    done_recv: Receiver<()>,
+    // Signalled when the system gets quiescent or the run is over, for the ones waiting from the outside
+    quiet: Condvar,
+}
+
+struct MonitorState
+{
+    epoch: usize,
+    idle_at: HashMap<String, Option<usize>>,
+    // Idle entities that hold back an action until all the others are idle
+    deferred: HashSet<String>,
+    wakers: HashMap<String, Sender<()>>,
+    done: bool,
+    // Stopped from the outside before the system was quiescent
+    stopped: bool,
+    done_send: Option<Sender<()>>,
+    // Number of holders that keep the run going
+    holds: usize,
+}
+
+impl MonitorState
+{
+    fn wake_all(&self)
+    {
+        // A waker that is already signalled does not need a second signal
+        for waker in self.wakers.values() {
+            let _ = waker.try_send(());
+        }
+    }
+
+    fn finish(&mut self)
+    {
+        self.done = true;
+        self.done_send = None;
+        self.wake_all();
+    }
+
+    fn all_idle(&self) -> bool
+    {
+        self.idle_at.values().all(|seen| *seen == Some(self.epoch))
+    }
+
+    fn is_quiescent(&self) -> bool
+    {
+        self.all_idle() && self.deferred.is_empty()
+    }
+}
+
+impl QuiescenceMonitor
+{
+    pub fn new(ids: impl Iterator<Item = String>) -> Self
+    {
+        let (done_send, done_recv) = bounded(0);
+        QuiescenceMonitor {
+            state: Mutex::new(MonitorState {
+                epoch: 0,
+                idle_at: ids.map(|id| (id, None)).collect(),
+                deferred: HashSet::new(),
+                wakers: HashMap::new(),
+                done: false,
+                stopped: false,
+                done_send: Some(done_send),
+                holds: 0,
+            }),
+            done_recv,
+            quiet: Condvar::new(),
+        }
+    }
+
+    // The receiver gets a message whenever the entity should look again for something to do
+    pub fn waker(&self, id: &str) -> Receiver<()>
+    {
+        let (send, recv) = bounded(1);
+        self.state.lock().unwrap().wakers.insert(id.to_string(), send);
+        recv
+    }
+
+    pub fn epoch(&self) -> usize
+    {
+        self.state.lock().unwrap().epoch
+    }
+
+    pub fn is_done(&self) -> bool
+    {
+        self.state.lock().unwrap().done
+    }
+
+    // Blocks until the run is over or the timeout passes, returns true if the run is over
+    pub fn wait_done(&self, timeout: Duration) -> bool
+    {
+        matches!(self.done_recv.recv_timeout(timeout), Err(RecvTimeoutError::Disconnected))
+    }
+
+    // Ends the run even though some entity can still act, they all stop at their next round
+    pub fn stop(&self)
+    {
+        let mut state = self.state.lock().unwrap();
+        if !state.done {
+            state.stopped = true;
+            state.finish();
+        }
+        self.quiet.notify_all();
+    }
+
+    // Keeps the run going even when the system is quiescent, until the matching release
+    pub fn hold(&self)
+    {
+        self.state.lock().unwrap().holds += 1;
+    }
+
+    pub fn release(&self)
+    {
+        let mut state = self.state.lock().unwrap();
+        state.holds -= 1;
+        // The entities are all blocked already, nobody else would notice the end
+        if state.holds == 0 && !state.done && state.is_quiescent() {
+            state.finish();
+        }
+        self.quiet.notify_all();
+    }
+
+    // Something happened outside of the entities that they have to look at
+    pub fn bump(&self)
+    {
+        let mut state = self.state.lock().unwrap();
+        state.epoch += 1;
+        state.wake_all();
+    }
+
+    // Blocks until the system is quiescent, or the run is over, or the timeout passes
+    // Returns true unless the timeout passed
+    pub fn wait_quiescent(&self, timeout: Duration) -> bool
+    {
+        let state = self.state.lock().unwrap();
+        let (_state, res) = self.quiet.wait_timeout_while(state, timeout, |s| !s.done && !s.is_quiescent()).unwrap();
+        !res.timed_out()
+    }
+
+    pub fn was_stopped(&self) -> bool
+    {
+        self.state.lock().unwrap().stopped
+    }
+
+    // Whether all the other entities found nothing to do at the given epoch, which is still the current one
+    pub fn others_idle(&self, id: &str, seen_epoch: usize) -> bool
+    {
+        let state = self.state.lock().unwrap();
+        state.epoch == seen_epoch
+            && state.idle_at.iter().all(|(other, seen)| other == id || *seen == Some(seen_epoch))
+    }
+
+    // Called after an action is taken, once its effects are visible to the other entities
+    pub fn report_active(&self, id: &str)
+    {
+        let mut state = self.state.lock().unwrap();
+        state.epoch += 1;
+        state.idle_at.insert(id.to_string(), None);
+        state.deferred.remove(id);
+        state.wake_all();
+    }
+
+    // Called for an entity that stopped running before the end, it is not waited for anymore
+    pub fn forget(&self, id: &str)
+    {
+        let mut state = self.state.lock().unwrap();
+        state.epoch += 1;
+        state.idle_at.remove(id);
+        state.deferred.remove(id);
+        state.wakers.remove(id);
+        state.wake_all();
+    }
+
+    // Called when nothing could be done, with the epoch read before looking for something to do
+    // A deferring entity still has an action it waits to take until all the others are idle,
+    //     when that happens it is woken up instead of the run being over
+    // Returns true if the whole system is now quiescent
+    pub fn report_idle(&self, id: &str, seen_epoch: usize, deferring: bool) -> bool
+    {
+        let mut state = self.state.lock().unwrap();
+        state.idle_at.insert(id.to_string(), Some(seen_epoch));
+        if deferring {
+            state.deferred.insert(id.to_string());
+        } else {
+            state.deferred.remove(id);
+        }
+
+        if state.all_idle() {
+            if state.deferred.is_empty() {
+                if state.holds == 0 {
+                    state.finish();
+                }
+                self.quiet.notify_all();
+            } else {
+                for id in &state.deferred {
+                    if let Some(waker) = state.wakers.get(id) {
+                        let _ = waker.try_send(());
+                    }
+                }
+            }
+        }
+        state.done
+    }
+}
@@ -1,9 +1,14 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
 use crate::rollpi::syntax::TagKey;
 
 pub struct TagCreator
 {
     tag_count: usize,
     tag_prefix: String,
+    // Keys already in use when the run started, the parser takes names like the created ones as well
+    reserved: Arc<HashSet<TagKey>>,
 }
 
 impl TagCreator 
@@ -13,13 +18,23 @@ impl TagCreator
         TagCreator {
             tag_count: 0,
             tag_prefix,
+            reserved: Arc::default(),
         }
     }
 
+    pub fn reserve(&mut self, keys: Arc<HashSet<TagKey>>)
+    {
+        self.reserved = keys;
+    }
+
     pub fn create_new_tag(self: &mut Self) -> TagKey
     {
-        let tag = TagKey(format!("_tag_{}_{}", self.tag_prefix, self.tag_count));
-        self.tag_count += 1;
-        tag
+        loop {
+            let tag = TagKey(format!("_tag_{}_{}", self.tag_prefix, self.tag_count));
+            self.tag_count += 1;
+            if !self.reserved.contains(&tag) {
+                return tag
+            }
+        }
     }
 }
\ No newline at end of file
@@ -1,15 +1,28 @@
 use std::collections::{HashSet, HashMap};
+use std::sync::Arc;
 
 use crossbeam::channel::unbounded;
 
-use crate::rollpi::syntax::{PrimeState, all_chn_names_proc, TagKey, ProcTag};
+use crate::rollpi::{syntax::{PrimeState, Process, SessionId, all_chn_names_proc, all_tag_keys_state, check_all_dvar_closed, TagKey, ProcTag}, printer::PrettyConfig, logger::trace::{TraceClock, TraceTarget, Tracer}, errors::LaunchError};
 
-use super::{components::picker::Strategy, entities::{participant::{Participant, PartyCommCtx, PartyChPool, HistTagContext, RollbackContext, DissapearContext, RessurectContext}, history::{HistoryContext, HistoryParticipant, RessurectMsg}}, types::MemoryPiece};
+pub use super::entities::history::CrashPolicy;
+
+use super::{components::picker::Strategy, entities::{participant::{Participant, PartyCommCtx, PartyChPool, HistTagContext, RollbackContext, DissapearContext, RessurectContext, AbortContext}, history::{HistoryContext, HistoryParticipant, RessurectMsg, HIST_ID}, quiescence::QuiescenceMonitor}, types::MemoryPiece};
+
+// The party of the session, its process and its strategy, if not the default one
+pub type SessionParty = (String, Process, Option<Box<dyn Strategy>>);
 
 #[derive(Default)]
 pub struct Generator
 {
     participants: HashMap<String, (Box<dyn Strategy>, PrimeState)>,
+    // Participants missing from here run in the default session
+    sessions: HashMap<String, SessionId>,
+    pretty_cfg: PrettyConfig,
+    crash_points: HashMap<String, usize>,
+    crash_policy: CrashPolicy,
+    // None means the default human readable logs
+    trace_targets: Option<Vec<TraceTarget>>,
 }
 
 impl Generator
@@ -29,18 +42,24 @@ impl Generator
 
     // Add a participant to be generated. 
     // Returns true if the given id is not already used, false if automatically generated
+    // A process sending data variables that no receive before it binds is not added,
+    //     the participant would have no value to send
     pub fn take_participant_conf(
         self: &mut Self,
         proc: PrimeState,
         id: Option<String>,
         strategy: Option<Box<dyn Strategy>>,
-    ) -> bool
+    ) -> Result<bool, LaunchError>
     {
+        if !proc.iter().all(|tagged| check_all_dvar_closed(&tagged.proc.clone().to_process())) {
+            return Err(LaunchError::InvalidConfiguration)
+        }
+
         // try to add the id given from the argument if not in the hashset
         if let Some(id) = id {
             if !self.participants.contains_key(&id) {
                 self.create_participant(id, proc, strategy);
-                return true;    
+                return Ok(true);
             }
         }
 
@@ -55,75 +74,175 @@ impl Generator
         };
 
         self.create_participant(id, proc, strategy);
-        return false;
+        return Ok(false);
     }
 
-    pub fn generate_participants(self: Self) -> (Vec<Participant>, HistoryParticipant)
+    // Add one instance of a protocol, given as (party, process, strategy) for each of its parties.
+    // The participants get the ids <session>.<party> and share channels and the history
+    //     only with the other participants of the same session.
+    // Returns false, without adding anything, if one of the ids is already used
+    //     or one of the processes sends data variables that are not bound
+    pub fn take_session_conf(
+        self: &mut Self,
+        session: SessionId,
+        conf: Vec<SessionParty>,
+    ) -> bool
     {
-        // Create channels and create copy for each of the participants
-        let channels = self.participants.iter()
-            .map(|(_id, (_, proc))| {
-                proc.iter().map(|tag_proc| {
-                    all_chn_names_proc(&tag_proc.proc.clone().to_process())
-                })
-                .flatten().collect::<HashSet<_>>()
-            })
-            .flatten().collect::<HashSet<_>>();
-
-        let part_ch_pool = PartyChPool::new(channels.into_iter());
-        
-        let (diss_send, diss_recv) = unbounded::<ProcTag>();
-        let mut memory_context = HistoryContext::new(diss_recv);
-        
-
-        let mut create_party_context = |id: &String| {
-            let (h_tag_send, h_tag_recv) = unbounded::<MemoryPiece>();
-            let (h_not_send, h_not_recv) = unbounded::<TagKey>();
-
-            let (r_tag_send, r_tag_recv) = unbounded::<ProcTag>();
-            let (r_frz_send, r_frz_recv) = unbounded::<ProcTag>();
-
-            let (s_tag_send, s_tag_recv) = unbounded::<RessurectMsg>();
-
-            memory_context.hist_tag_recv.insert(id.clone(), h_tag_recv);
-            memory_context.hist_not_send.insert(id.clone(), h_not_send);
-            memory_context.roll_tag_recv.insert(id.clone(), r_tag_recv);
-            memory_context.roll_frz_send.insert(id.clone(), r_frz_send);
-            memory_context.ress_tag_send.insert(id.clone(), s_tag_send);
-
-            PartyCommCtx {
-                channel_pool: part_ch_pool.clone(),
-                history_ctx: HistTagContext {
-                    hist_tag_channel: h_tag_send,
-                    hist_conf_channel: h_not_recv,
-                },
-                rollback_ctx: RollbackContext {
-                    roll_tag_channel: r_tag_send,
-                    freeze_not_channel: r_frz_recv,
-                },
-                dissapear_ctx: DissapearContext {
-                    diss_send_channel: diss_send.clone(),
-                },
-                ressurect_ctx: RessurectContext {
-                    ress_recv_channel: s_tag_recv,
-                },
-            }
+        let ids = conf.iter().map(|(party, _, _)| session.party_id(party)).collect::<Vec<_>>();
+        if ids.iter().any(|id| self.participants.contains_key(id)) || ids.iter().collect::<HashSet<_>>().len() != ids.len()
+            || !conf.iter().all(|(_, proc, _)| check_all_dvar_closed(proc)) {
+            return false;
+        }
+
+        for (id, (_, proc, strategy)) in ids.into_iter().zip(conf) {
+            let state = proc.to_tagged_process(ProcTag::PTKey(TagKey(id.clone()))).to_prime_state();
+            self.create_participant(id.clone(), state, strategy);
+            self.sessions.insert(id, session.clone());
+        }
+
+        true
+    }
+
+    // Layout used by all participants and the history when logging processes
+    pub fn set_pretty_config(self: &mut Self, pretty_cfg: PrettyConfig)
+    {
+        self.pretty_cfg = pretty_cfg;
+    }
+
+    // The participant with the given id gets killed after taking the given number of actions
+    pub fn inject_crash(self: &mut Self, id: &str, after_steps: usize)
+    {
+        self.crash_points.insert(id.to_string(), after_steps);
+    }
+
+    pub fn set_crash_policy(self: &mut Self, policy: CrashPolicy)
+    {
+        self.crash_policy = policy;
+    }
+
+    // Where the participants and the history record their events, an empty list turns tracing off
+    pub fn set_trace_targets(self: &mut Self, targets: Vec<TraceTarget>)
+    {
+        self.trace_targets = Some(targets);
+    }
+
+    // One history is created for every session, all of them together with the participants
+    //     report to the same quiescence monitor
+    pub fn generate_participants(self: Self) -> Result<(Vec<Participant>, Vec<HistoryParticipant>), LaunchError>
+    {
+        let sessions = self.sessions;
+        let session_of = |id: &String| sessions.get(id).cloned().unwrap_or_default();
+
+        let mut by_session = HashMap::<SessionId, Vec<(String, (Box<dyn Strategy>, PrimeState))>>::new();
+        for (id, party) in self.participants {
+            by_session.entry(session_of(&id)).or_default().push((id, party));
+        }
+
+        // The run ends when all the participants and the histories are idle
+        let monitor = Arc::new(QuiescenceMonitor::new(
+            by_session.iter().flat_map(|(session, parties)| {
+                parties.iter().map(|(id, _)| id.clone()).chain(std::iter::once(session.party_id(HIST_ID)))
+            }).collect::<Vec<_>>().into_iter()
+        ));
+
+        // Every participant gets its own sinks, the sequence numbers are shared by the whole run
+        let clock = Arc::new(TraceClock::default());
+        let targets = self.trace_targets.unwrap_or_else(|| vec![TraceTarget::default()]);
+        let create_tracer = |id: &str| {
+            let sinks = targets.iter().map(|t| t.open(id)).collect::<Result<_, _>>()
+                .map_err(|e| LaunchError::Trace(id.to_string(), e.to_string()))?;
+            Ok::<_, LaunchError>(Tracer::new(id.to_string(), clock.clone(), sinks))
         };
 
-        let parties = self.participants
-            .into_iter()
-            .map(|(id, (strat, proc)) | {
+        let mut all_parties = vec![];
+        let mut hists = vec![];
+
+        for (session, participants) in by_session {
+            // Create channels and create copy for each of the participants of the session
+            let channels = participants.iter()
+                .flat_map(|(_id, (_, proc))| {
+                    proc.iter().flat_map(|tag_proc| {
+                        all_chn_names_proc(&tag_proc.proc.clone().to_process())
+                    })
+                    .collect::<HashSet<_>>()
+                })
+                .collect::<HashSet<_>>();
+
+            let part_ch_pool = PartyChPool::new(channels.into_iter());
+
+            // The tags created by the participants do not take the keys of the session
+            let reserved_keys = Arc::new(participants.iter()
+                .flat_map(|(_id, (_, proc))| all_tag_keys_state(proc))
+                .collect::<HashSet<_>>());
+
+            let (diss_send, diss_recv) = unbounded::<ProcTag>();
+            let hist_id = session.party_id(HIST_ID);
+            let mut memory_context = HistoryContext::new(session.clone(), diss_recv, self.pretty_cfg, monitor.clone(), create_tracer(&hist_id)?);
+            memory_context.crash_policy = self.crash_policy;
+
+            let mut create_party_context = |id: &String| {
+                let (h_tag_send, h_tag_recv) = unbounded::<MemoryPiece>();
+                let (h_not_send, h_not_recv) = unbounded::<TagKey>();
+
+                let (r_tag_send, r_tag_recv) = unbounded::<ProcTag>();
+                let (r_frz_send, r_frz_recv) = unbounded::<ProcTag>();
+
+                let (s_tag_send, s_tag_recv) = unbounded::<RessurectMsg>();
+
+                let (a_send, a_recv) = unbounded::<String>();
+
+                memory_context.hist_tag_recv.insert(id.clone(), h_tag_recv);
+                memory_context.hist_not_send.insert(id.clone(), h_not_send);
+                memory_context.roll_tag_recv.insert(id.clone(), r_tag_recv);
+                memory_context.roll_frz_send.insert(id.clone(), r_frz_send);
+                memory_context.ress_tag_send.insert(id.clone(), s_tag_send);
+                memory_context.abort_send.insert(id.clone(), a_send);
+
+                PartyCommCtx {
+                    session: session.clone(),
+                    channel_pool: part_ch_pool.clone(),
+                    history_ctx: HistTagContext {
+                        hist_tag_channel: h_tag_send,
+                        hist_conf_channel: h_not_recv,
+                    },
+                    rollback_ctx: RollbackContext {
+                        roll_tag_channel: r_tag_send,
+                        freeze_not_channel: r_frz_recv,
+                    },
+                    dissapear_ctx: DissapearContext {
+                        diss_send_channel: diss_send.clone(),
+                    },
+                    ressurect_ctx: RessurectContext {
+                        ress_recv_channel: s_tag_recv,
+                    },
+                    abort_ctx: AbortContext {
+                        abort_recv_channel: a_recv,
+                    },
+                    monitor: monitor.clone(),
+                }
+            };
+
+            for (id, (strat, proc)) in participants {
                 let c_ctx = create_party_context(&id);
-                Participant::new(
+                let tracer = create_tracer(&id)?;
+                let mut party = Participant::new(
                     id,
                     proc,
                     strat,
                     c_ctx,
-                )
-            }).collect();
-        
-        let hist = HistoryParticipant::new(memory_context);
+                    self.pretty_cfg,
+                    tracer,
+                );
+                if let Some(steps) = self.crash_points.get(party.get_id()) {
+                    party.set_crash_after(*steps);
+                }
+                party.reserve_tag_keys(reserved_keys.clone());
+                all_parties.push(party);
+            }
+
+            hists.push(HistoryParticipant::new(memory_context));
+        }
 
-        return (parties, hist)
+        Ok((all_parties, hists))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::rollpi::{errors::LaunchError, logger::trace::TraceTarget, syntax::{check_initial_conf_list, Process, ProcTag, TagKey}};
+
+use super::{components::{picker::Strategy, strategies::{SimpleDeterministic::SimpleDetermStrat, SimpleOrder::SimpleOrderStrat, SimpleRandom::{RandWeights, SimpleRandStrat}}}, generator::Generator, runner::{RunReport, Runner, RunningContext}};
+
+// The strategies that can be picked by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyKind
+{
+    Order,
+    Determ,
+    Random,
+}
+
+impl StrategyKind
+{
+    pub const ALL: [StrategyKind; 3] = [StrategyKind::Order, StrategyKind::Determ, StrategyKind::Random];
+
+    pub fn build(&self, seed: Option<u64>) -> Box<dyn Strategy>
+    {
+        match self {
+            StrategyKind::Order => Box::new(SimpleOrderStrat::default()),
+            StrategyKind::Determ => Box::new(SimpleDetermStrat::default()),
+            StrategyKind::Random => Box::new(SimpleRandStrat::new(seed, RandWeights::default())),
+        }
+    }
+}
+
+impl Display for StrategyKind
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            StrategyKind::Order => write!(f, "order"),
+            StrategyKind::Determ => write!(f, "determ"),
+            StrategyKind::Random => write!(f, "random"),
+        }
+    }
+}
+
+impl FromStr for StrategyKind
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        StrategyKind::ALL.into_iter()
+            .find(|kind| kind.to_string() == s)
+            .ok_or_else(|| format!("unknown strategy '{}', expected one of order, determ, random", s))
+    }
+}
+
+// How to run a configuration, everything left out keeps the default of the generator
+#[derive(Default, Clone)]
+pub struct LaunchConfig
+{
+    // Strategy of the participants, by id, the others use default_strategy
+    pub strategies: HashMap<String, StrategyKind>,
+    pub default_strategy: Option<StrategyKind>,
+    // The random participants get the seed plus their position in the configuration,
+    //     so they do not all make the same choices
+    pub seed: Option<u64>,
+    pub trace_targets: Option<Vec<TraceTarget>>,
+    pub timeout: Option<Duration>,
+}
+
+// Runs every process as a participant with the same id, after checking the initial configuration
+pub fn run_processes_as_parties(conf: Vec<(String, Process)>, config: LaunchConfig) -> Result<RunReport, LaunchError>
+{
+    let procs = conf.iter().map(|(_id, p)| p.clone()).collect::<Vec<_>>();
+    if !check_initial_conf_list(&procs) {
+        return Err(LaunchError::InvalidConfiguration)
+    }
+
+    if let Some(id) = config.strategies.keys().find(|id| !conf.iter().any(|(party, _)| party == *id)) {
+        return Err(LaunchError::UnknownParticipant(id.clone()))
+    }
+
+    // Generate the contexts for running the parties and their associated processes
+    let mut generator = Generator::default();
+    for (i, (id, p)) in conf.into_iter().enumerate() {
+        let state = p.to_tagged_process(ProcTag::PTKey(TagKey(id.clone()))).to_prime_state();
+        let kind = config.strategies.get(&id).or(config.default_strategy.as_ref());
+        let strategy = kind.map(|k| k.build(config.seed.map(|s| s.wrapping_add(i as u64))));
+        generator.take_participant_conf(state, Some(id), strategy)?;
+    }
+
+    if let Some(targets) = config.trace_targets {
+        generator.set_trace_targets(targets);
+    }
+
+    let (parties, hist) = generator.generate_participants()?;
+    let mut runner = Runner::new(RunningContext { parties, hist });
+    if let Some(timeout) = config.timeout {
+        runner.set_timeout(timeout);
+    }
+
+    Ok(runner.run())
+}
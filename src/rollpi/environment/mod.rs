@@ -1,5 +1,6 @@
 pub mod generator;
 pub mod runner;
+pub mod launch;
 mod entities;
-mod components;
+pub mod components;
 mod types;
\ No newline at end of file
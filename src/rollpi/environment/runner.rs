@@ -1,16 +1,118 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
-use super::entities::{participant::{Participant, Runnable}, history::HistoryParticipant};
+use crossbeam::channel::{unbounded, Sender};
+
+use crate::rollpi::{errors::RollbackError, printer::StateFmt, syntax::{SessionId, TagKey}};
+
+use super::{entities::{participant::{Participant, PartyChPool, Runnable}, history::{HistoryParticipant, RollbackRequest}, quiescence::QuiescenceMonitor}, types::PartyComm};
+
+pub use super::entities::{participant::{PartyReport, PartyStatus}, history::HistoryGraph};
 
 pub struct RunningContext
 {
     pub parties: Vec<Participant>,
-    pub hist: HistoryParticipant,
+    // One history for every session
+    pub hist: Vec<HistoryParticipant>,
 }
 
 pub struct Runner
 {
     context: RunningContext,
+    timeout: Option<Duration>,
+    rollback: Option<RollbackHandle>,
+}
+
+// Lets rollbacks be injected from outside the processes while the run goes on,
+//     they take the same path in the history as the rollbacks asked for by a roll
+// The run does not end on its own while a handle, or one of its clones, is alive,
+//     so that a quiescent system can still be rolled back. A timeout still stops it
+#[derive(Clone)]
+pub struct RollbackHandle
+{
+    // The participants of every session, with the requests channel of its history
+    histories: Vec<(Vec<String>, Sender<RollbackRequest>)>,
+    monitor: Arc<QuiescenceMonitor>,
+    _hold: Arc<RunHold>,
+}
+
+// Releases the run once the last clone of a handle is gone
+struct RunHold(Arc<QuiescenceMonitor>);
+
+impl Drop for RunHold
+{
+    fn drop(&mut self)
+    {
+        self.0.release();
+    }
+}
+
+impl RollbackHandle
+{
+    // Rolls back the communication that created the tag, together with everything that depends on it
+    // A tag that no history has a memory for is ignored
+    pub fn roll_back_tag(&self, tag: TagKey) -> Result<(), RollbackError>
+    {
+        let sent = self.histories.iter()
+            .filter(|(_, requests)| requests.send(RollbackRequest::Tag(tag.clone())).is_ok())
+            .count();
+        self.notify(sent > 0)
+    }
+
+    // Rolls back the last n communications, still in the history, that the participant took part in
+    pub fn roll_back_last(&self, id: &str, n: usize) -> Result<(), RollbackError>
+    {
+        let (_, requests) = self.histories.iter()
+            .find(|(ids, _)| ids.iter().any(|other| other == id))
+            .ok_or_else(|| RollbackError::UnknownParticipant(id.to_string()))?;
+        let sent = requests.send(RollbackRequest::Last(id.to_string(), n)).is_ok();
+        self.notify(sent)
+    }
+
+    // Blocks until no participant nor history has anything left to do, or the timeout passes
+    // Returns false if the timeout passed first
+    pub fn wait_quiescent(&self, timeout: Duration) -> bool
+    {
+        self.monitor.wait_quiescent(timeout)
+    }
+
+    fn notify(&self, sent: bool) -> Result<(), RollbackError>
+    {
+        if !sent || self.monitor.is_done() {
+            return Err(RollbackError::RunOver)
+        }
+
+        // The system is not quiescent anymore until the history looked at the request
+        self.monitor.bump();
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome
+{
+    // Every process ran to completion and every message was received
+    Terminated,
+    // Some process is stuck forever, or some message was never received
+    Deadlocked,
+    // A participant crashed and the others were told to stop
+    Aborted,
+    // The run was stopped after the timeout while some participant could still act
+    TimedOut,
+}
+
+pub struct RunReport
+{
+    // Sorted by participant id
+    pub parties: Vec<PartyReport>,
+    // The history graph of every session, the default session being SessionId::default()
+    pub histories: BTreeMap<SessionId, HistoryGraph>,
+    // Messages left in the channels, with the name of their channel
+    pub pending: Vec<(String, PartyComm)>,
+    pub outcome: RunOutcome,
 }
 
 impl Runner
@@ -19,30 +121,142 @@ impl Runner
     {
         Runner {
             context,
+            timeout: None,
+            rollback: None,
         }
     }
 
-    pub fn run(self: Self)
+    // A handle for rolling back from the outside, all the calls give clones of the same handle
+    pub fn rollback_handle(self: &mut Self) -> RollbackHandle
     {
+        if let Some(handle) = &self.rollback {
+            return handle.clone()
+        }
+
+        let monitor = self.context.hist.first().map(|h| h.get_monitor().clone())
+            .or_else(|| self.context.parties.first().map(|p| p.get_monitor().clone()))
+            .unwrap_or_else(|| Arc::new(QuiescenceMonitor::new(std::iter::empty())));
+        monitor.hold();
+
+        let histories = self.context.hist.iter_mut().map(|h| {
+            let (send, recv) = unbounded();
+            h.take_rollback_requests(recv);
+            (h.participants(), send)
+        }).collect();
+
+        let handle = RollbackHandle { histories, monitor: monitor.clone(), _hold: Arc::new(RunHold(monitor)) };
+        self.rollback = Some(handle.clone());
+        handle
+    }
+
+    // Stops the run after the given time, for configurations that never get quiescent
+    pub fn set_timeout(self: &mut Self, timeout: Duration)
+    {
+        self.timeout = Some(timeout);
+    }
+
+    // Runs until no participant has an enabled action and the history has no message left to handle
+    pub fn run(self: Self) -> RunReport
+    {
+        // Only the handles given out keep the run going
+        let Runner { context, timeout, rollback } = self;
+        drop(rollback);
+
+        // The participants of a session share the same channels
+        let mut channel_pools: Vec<PartyChPool> = vec![];
+        for p in &context.parties {
+            let pool = p.get_channel_pool();
+            if !channel_pools.iter().any(|other| other.same_pool(pool)) {
+                channel_pools.push(pool.clone());
+            }
+        }
+
+        let monitor = context.parties.first().map(|p| p.get_monitor().clone());
+
         let mut handles = vec![];
 
         // start the participants on different threads
-        for p in self.context.parties {
+        for p in context.parties {
             let h = thread::spawn(move || {
-                p.run();
+                p.run()
             });
 
             handles.push(h);
         };
 
-        let hist_h = thread::spawn(move || {
-            self.context.hist.run();
-        });
-        handles.push(hist_h);
+        let hist_hs = context.hist.into_iter().map(|h| {
+            let session = h.session().clone();
+            (session, thread::spawn(move || h.run()))
+        }).collect::<Vec<_>>();
+
+        if let (Some(monitor), Some(timeout)) = (&monitor, timeout) {
+            if !monitor.wait_done(timeout) {
+                monitor.stop();
+            }
+        }
 
         // wait for all threads to finish
-        for h in handles {
-            h.join().unwrap();
+        let mut parties = handles.into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>();
+        parties.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let histories = hist_hs.into_iter()
+            .map(|(session, h)| (session, h.join().unwrap()))
+            .collect();
+
+        let mut pending = channel_pools.iter().flat_map(|pool| pool.drain_pending()).collect::<Vec<_>>();
+        pending.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        // Whatever a crashed participant was running is lost, it does not count for deadlocks
+        let all_done = parties.iter()
+            .filter(|p| p.status != PartyStatus::Crashed)
+            .all(|p| p.live_state.is_empty()) && pending.is_empty();
+
+        let outcome = if monitor.is_some_and(|m| m.was_stopped()) {
+            RunOutcome::TimedOut
+        } else if parties.iter().any(|p| p.status == PartyStatus::Aborted) {
+            RunOutcome::Aborted
+        } else if all_done {
+            RunOutcome::Terminated
+        } else {
+            RunOutcome::Deadlocked
+        };
+
+        RunReport {
+            parties,
+            histories,
+            pending,
+            outcome,
         }
     }
-}
\ No newline at end of file
+}
+
+impl Display for RunReport
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self.outcome {
+            RunOutcome::Terminated => writeln!(f, "Run terminated")?,
+            RunOutcome::Deadlocked => writeln!(f, "Run deadlocked")?,
+            RunOutcome::Aborted => writeln!(f, "Run aborted")?,
+            RunOutcome::TimedOut => writeln!(f, "Run timed out")?,
+        }
+
+        for PartyReport { id, status, live_state, dead_state } in &self.parties {
+            match status {
+                PartyStatus::Finished => (),
+                PartyStatus::Crashed => writeln!(f, "{} crashed", id)?,
+                PartyStatus::Aborted => writeln!(f, "{} aborted", id)?,
+            }
+            writeln!(f, "{} live:\n{}", id, StateFmt(live_state))?;
+            writeln!(f, "{} dead:\n{}", id, StateFmt(dead_state))?;
+        }
+
+        for (ch_name, PartyComm { sender_id, process, .. }) in &self.pending {
+            writeln!(f, "Pending on {} from {}: {}", ch_name, sender_id, process)?;
+        }
+
+        Ok(())
+    }
+}
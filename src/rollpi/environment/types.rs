@@ -2,12 +2,15 @@
 
 
 
-use crate::rollpi::syntax::{Process, ProcTag, ChName, ProcVar, TagVar, TagKey, TaggedProc};
+use std::fmt::Display;
+
+use crate::rollpi::{syntax::{Process, ProcTag, ChName, ProcVar, TagVar, TagKey, TaggedProc, Value, Expr, DataBinder}, printer::PrettyConfig};
 
 #[derive(Debug)]
 pub struct PartyComm
 {
     pub sender_id: String,
+    pub data: Vec<Value>,
     pub process: Process,
     pub tag: ProcTag,
 }
@@ -30,19 +33,19 @@ pub struct MemoryPiece
 impl MemoryPiece
 {
     pub fn new( ids: (String, String), 
-                sender: (ProcTag, (ChName, Process)), 
-                receiver: (ProcTag, (ChName, ProcVar, TagVar, Process)),
+                sender: (ProcTag, (ChName, Vec<Expr>, Process)), 
+                receiver: (ProcTag, (ChName, Vec<DataBinder>, ProcVar, TagVar, Process)),
                 new_mem_tag: TagKey) -> Self
     {
         MemoryPiece { 
             ids, 
             sender: TaggedProc { 
                 tag: sender.0, 
-                proc: Process::Send(sender.1.0, Box::new(sender.1.1)),
+                proc: Process::Send(sender.1.0, sender.1.1, Box::new(sender.1.2)),
             },
             receiver: TaggedProc { 
                 tag: receiver.0, 
-                proc: Process::Recv(receiver.1.0, receiver.1.1, receiver.1.2, Box::new(receiver.1.3)),
+                proc: Process::Recv(receiver.1.0, receiver.1.1, receiver.1.2, receiver.1.3, Box::new(receiver.1.4)),
             },
             new_mem_tag, 
             _secret: () 
@@ -50,3 +53,11 @@ impl MemoryPiece
     }
 }
 
+// Prints the memory in the roll-pi notation [μ;k]
+impl Display for MemoryPiece
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", PrettyConfig::default().memory(&self.sender, &self.receiver, &self.new_mem_tag))
+    }
+}
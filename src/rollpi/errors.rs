@@ -0,0 +1,331 @@
+use std::fmt::Display;
+
+// Line and column (both starting from 1) of a character in the parsed input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position
+{
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError
+{
+    // A character that can not start any token
+    UnexpectedChar(Position, char),
+    // (position, found token, what was expected instead)
+    UnexpectedToken(Position, String, String),
+    // The input ended while still expecting something
+    UnexpectedEnd(Position, String),
+    // (position, literal) An integer that does not fit in 64 bits
+    IntOutOfRange(Position, String),
+}
+
+impl ParseError
+{
+    pub fn position(&self) -> Position
+    {
+        match self {
+            ParseError::UnexpectedChar(pos, _) => *pos,
+            ParseError::UnexpectedToken(pos, _, _) => *pos,
+            ParseError::UnexpectedEnd(pos, _) => *pos,
+            ParseError::IntOutOfRange(pos, _) => *pos,
+        }
+    }
+
+    // The same error at another position, for input that was cut out of a bigger text
+    pub fn map_position(self, f: impl Fn(Position) -> Position) -> ParseError
+    {
+        match self {
+            ParseError::UnexpectedChar(pos, c) => ParseError::UnexpectedChar(f(pos), c),
+            ParseError::UnexpectedToken(pos, found, expected) => ParseError::UnexpectedToken(f(pos), found, expected),
+            ParseError::UnexpectedEnd(pos, expected) => ParseError::UnexpectedEnd(f(pos), expected),
+            ParseError::IntOutOfRange(pos, literal) => ParseError::IntOutOfRange(f(pos), literal),
+        }
+    }
+}
+
+impl Display for Position
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl Display for ParseError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            ParseError::UnexpectedChar(pos, c) =>
+                write!(f, "Parse Error at {}: unexpected character '{}'", pos, c),
+            ParseError::UnexpectedToken(pos, found, expected) =>
+                write!(f, "Parse Error at {}: unexpected token '{}' - expected {}", pos, found, expected),
+            ParseError::UnexpectedEnd(pos, expected) =>
+                write!(f, "Parse Error at {}: unexpected end of input - expected {}", pos, expected),
+            ParseError::IntOutOfRange(pos, literal) =>
+                write!(f, "Parse Error at {}: the integer {} does not fit in 64 bits", pos, literal),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectionError
+{
+    // (role, labels leading to the message, party) A message sent by a party to itself
+    SelfCommunication(String, Vec<String>, String),
+    // (role, labels leading to the message) A message with no branches to choose from
+    EmptyChoice(String, Vec<String>),
+    // (role, labels leading to the message, label) A label used for more than one branch of the same message
+    DuplicateLabel(String, Vec<String>, String),
+    // (role, labels leading to the message, label) The role does not take part in the choice,
+    //     but its behaviour in the given branch differs from the one in the previous branches
+    NotMergeable(String, Vec<String>, String),
+}
+
+fn display_path(path: &[String]) -> String
+{
+    if path.is_empty() {
+        "the top level".to_string()
+    } else {
+        format!("branch {}", path.join("."))
+    }
+}
+
+impl Display for ProjectionError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            ProjectionError::SelfCommunication(role, path, party) =>
+                write!(f, "Projection Error on role {} at {}: party {} sends a message to itself", role, display_path(path), party),
+            ProjectionError::EmptyChoice(role, path) =>
+                write!(f, "Projection Error on role {} at {}: message without any branch", role, display_path(path)),
+            ProjectionError::DuplicateLabel(role, path, label) =>
+                write!(f, "Projection Error on role {} at {}: label {} is used for more than one branch", role, display_path(path), label),
+            ProjectionError::NotMergeable(role, path, label) =>
+                write!(f, "Projection Error on role {} at {}: branch {} can not be merged with the other branches", role, display_path(path), label),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalTypeError
+{
+    // A recursion variable reached from its binder without any communication in between
+    UnguardedRecursion(String),
+    // A recursion variable without an enclosing binder
+    FreeRecVar(String),
+    // (other party, label) A label used for more than one branch of the same communication
+    DuplicateLabel(String, String),
+    // A communication with the own party
+    SelfCommunication(String),
+    // A communication with the given party that has no branches
+    EmptyChoice(String),
+    // (label, data variable) A value sent with the label uses a data variable no receive bound before
+    FreeDataVar(String, String),
+}
+
+impl Display for LocalTypeError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            LocalTypeError::UnguardedRecursion(r_var) =>
+                write!(f, "Local Type Error: recursion variable {} is not guarded by a communication", r_var),
+            LocalTypeError::FreeRecVar(r_var) =>
+                write!(f, "Local Type Error: recursion variable {} is not bound", r_var),
+            LocalTypeError::DuplicateLabel(party, label) =>
+                write!(f, "Local Type Error: label {} is used for more than one branch with party {}", label, party),
+            LocalTypeError::SelfCommunication(party) =>
+                write!(f, "Local Type Error: party {} communicates with itself", party),
+            LocalTypeError::EmptyChoice(party) =>
+                write!(f, "Local Type Error: communication with party {} has no branches", party),
+            LocalTypeError::FreeDataVar(label, var) =>
+                write!(f, "Local Type Error: data variable {} sent with label {} is not bound by a receive before", var, label),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemanticsError
+{
+    // (position of the send, position of the receive) are not a send and a receive on the same channel
+    NoCommunication(usize, usize),
+    // The process at the position is not a roll on a tag that has a memory
+    NoRoll(usize),
+}
+
+impl Display for SemanticsError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            SemanticsError::NoCommunication(snd, rcv) =>
+                write!(f, "Semantics Error: processes {} and {} can not communicate", snd, rcv),
+            SemanticsError::NoRoll(pos) =>
+                write!(f, "Semantics Error: process {} is not a roll with a matching memory", pos),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchError
+{
+    // The processes do not respect the checks of an initial configuration
+    InvalidConfiguration,
+    // A strategy was given for a participant that is not in the configuration
+    UnknownParticipant(String),
+    // (participant, io error) The trace of the participant could not be opened
+    Trace(String, String),
+}
+
+impl Display for LaunchError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            LaunchError::InvalidConfiguration =>
+                write!(f, "Launch Error: the processes do not respect the checks (pvar, tvar uniques and closed and rolls bounded)"),
+            LaunchError::UnknownParticipant(id) =>
+                write!(f, "Launch Error: there is no participant {}", id),
+            LaunchError::Trace(id, err) =>
+                write!(f, "Launch Error: the trace of {} can not be opened: {}", id, err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RollbackError
+{
+    // The rollback asks for a participant that is in no session of the run
+    UnknownParticipant(String),
+    // The histories are gone, so nothing can be rolled back anymore
+    RunOver,
+}
+
+impl Display for RollbackError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            RollbackError::UnknownParticipant(id) =>
+                write!(f, "Rollback Error: there is no participant {}", id),
+            RollbackError::RunOver =>
+                write!(f, "Rollback Error: the run is already over"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranslationError
+{
+    // The hopi process has a free process variable, so it can not be run
+    FreeVariable(String),
+    // A send or receive on the channel carries data, which hopi has no way to express
+    Data(String),
+}
+
+impl Display for TranslationError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            TranslationError::FreeVariable(x) =>
+                write!(f, "Translation Error: the process variable {} is free", x),
+            TranslationError::Data(ch_name) =>
+                write!(f, "Translation Error: the communication on channel {} carries data", ch_name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScenarioError
+{
+    // The file could not be read
+    Io(String),
+    // The process or local type of a participant does not parse, the position is the one in the whole file
+    Process(ParseError),
+    // (line, content) A line that does not declare anything
+    UnexpectedLine(usize, String),
+    // (line, participant) A participant declared a second time
+    DuplicateParticipant(usize, String),
+    // (line, participant, problems) The local type of a participant can not be encoded
+    LocalType(usize, String, Vec<LocalTypeError>),
+    // (line, participant) A strategy for a participant that is not declared
+    UnknownParticipant(usize, String),
+    // (line, participant) A participant given a strategy a second time
+    DuplicateStrategy(usize, String),
+    // (line, kind) A strategy that does not exist
+    UnknownStrategy(usize, String),
+}
+
+impl Display for ScenarioError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            ScenarioError::Io(err) =>
+                write!(f, "Scenario Error: {}", err),
+            ScenarioError::Process(err) =>
+                write!(f, "Scenario Error: {}", err),
+            ScenarioError::UnexpectedLine(line, content) =>
+                write!(f, "Scenario Error at line {}: expected '<participant> = <process>', '<participant> : <local type>' \
+                    or 'strategy <participant> = <kind>', found '{}'", line, content),
+            ScenarioError::DuplicateParticipant(line, id) =>
+                write!(f, "Scenario Error at line {}: participant {} is declared twice", line, id),
+            ScenarioError::LocalType(line, id, errs) =>
+                write!(f, "Scenario Error at line {}: the local type of {} is not valid: {}", line, id,
+                    errs.iter().map(|err| err.to_string()).collect::<Vec<_>>().join(", ")),
+            ScenarioError::UnknownParticipant(line, id) =>
+                write!(f, "Scenario Error at line {}: strategy for participant {} which is not declared", line, id),
+            ScenarioError::DuplicateStrategy(line, id) =>
+                write!(f, "Scenario Error at line {}: participant {} is given a strategy twice", line, id),
+            ScenarioError::UnknownStrategy(line, kind) =>
+                write!(f, "Scenario Error at line {}: unknown strategy '{}', expected one of order, determ, random", line, kind),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError
+{
+    // A subcommand that does not exist, or none at all
+    UnknownCommand(String),
+    // (what is missing) The command line ended too early
+    MissingArgument(String),
+    UnknownOption(String),
+    // (option, value, what was expected)
+    InvalidValue(String, String, String),
+    UnknownScenario(String),
+    Scenario(ScenarioError),
+    Launch(LaunchError),
+    // (directory, io error) The log directory can not be created
+    LogDir(String, String),
+    // Writing the output failed
+    Output(String),
+}
+
+impl Display for CliError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            CliError::UnknownCommand(cmd) =>
+                write!(f, "unknown command '{}'", cmd),
+            CliError::MissingArgument(what) =>
+                write!(f, "missing {}", what),
+            CliError::UnknownOption(opt) =>
+                write!(f, "unknown option '{}'", opt),
+            CliError::InvalidValue(opt, value, expected) =>
+                write!(f, "invalid value '{}' for {}: expected {}", value, opt, expected),
+            CliError::UnknownScenario(name) =>
+                write!(f, "there is no built-in scenario '{}', see the list command", name),
+            CliError::Scenario(err) => write!(f, "{}", err),
+            CliError::Launch(err) => write!(f, "{}", err),
+            CliError::LogDir(dir, err) => write!(f, "could not create the log directory {}: {}", dir, err),
+            CliError::Output(err) => write!(f, "could not write the output: {}", err),
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::printer::print_process;
+use super::semantics::{ConfProc, Configuration, Memory, Transition};
+use super::syntax::{ChName, PrimProcess, Process, ProcTag, TagKey};
+
+// Model checker over the reference semantics in rollpi::semantics
+// Every interleaving of communications and rolls is followed, states that only differ in the names
+//     of the tags created during the run are explored once
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExploreConfig
+{
+    // Configurations further than this many steps from the initial one are not expanded
+    pub max_depth: Option<usize>,
+    // The exploration stops after finding this many distinct configurations
+    pub max_states: Option<usize>,
+}
+
+impl Default for ExploreConfig
+{
+    fn default() -> Self
+    {
+        ExploreConfig { max_depth: None, max_states: Some(100_000) }
+    }
+}
+
+// A path from the initial configuration to a bad one
+// For a livelock, the cycle leads from the reached configuration back to itself
+#[derive(Debug, Clone)]
+pub struct Counterexample
+{
+    pub trace: Vec<Transition>,
+    pub state: Configuration,
+    pub cycle: Vec<Transition>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExploreReport
+{
+    pub states: usize,
+    pub transitions: usize,
+    // Configurations where nothing can happen anymore, but some process is not 0
+    pub deadlocks: Vec<Counterexample>,
+    // Cycles the run can stay in forever, without ever being able to terminate
+    pub livelocks: Vec<Counterexample>,
+    // False if a bound stopped the exploration, then livelocks are only reported
+    //     when they can not reach a configuration that was left unexplored
+    pub complete: bool,
+}
+
+struct Node
+{
+    conf: Configuration,
+    parent: Option<(usize, Transition)>,
+    depth: usize,
+    succs: Vec<(usize, Transition)>,
+    // All the successors are known, false for the configurations cut off by a bound
+    expanded: bool,
+}
+
+pub fn explore(conf: Vec<(String, Process)>, cfg: &ExploreConfig) -> ExploreReport
+{
+    let initial = Configuration::new(conf);
+
+    let mut seen = HashMap::new();
+    seen.insert(canonical_form(&initial), 0);
+    let mut nodes = vec![Node { conf: initial, parent: None, depth: 0, succs: vec![], expanded: false }];
+    let mut queue = VecDeque::from([0]);
+    let mut complete = true;
+    let mut transitions = 0;
+
+    while let Some(id) = queue.pop_front() {
+        if cfg.max_depth.is_some_and(|d| nodes[id].depth >= d) {
+            complete = false;
+            continue
+        }
+
+        let steps = nodes[id].conf.enabled_steps();
+        let mut succs = vec![];
+        let mut dropped = false;
+        for step in steps {
+            let mut next = nodes[id].conf.clone();
+            // Only enabled steps are taken, so this can not fail
+            let transition = next.step(&step).unwrap();
+            transitions += 1;
+
+            let key = canonical_form(&next);
+            let next_id = match seen.get(&key) {
+                Some(next_id) => *next_id,
+                None => {
+                    if cfg.max_states.is_some_and(|m| nodes.len() >= m) {
+                        complete = false;
+                        dropped = true;
+                        continue
+                    }
+                    let next_id = nodes.len();
+                    seen.insert(key, next_id);
+                    nodes.push(Node { conf: next, parent: Some((id, transition.clone())), depth: nodes[id].depth + 1, succs: vec![], expanded: false });
+                    queue.push_back(next_id);
+                    next_id
+                },
+            };
+            succs.push((next_id, transition));
+        }
+
+        nodes[id].succs = succs;
+        nodes[id].expanded = !dropped;
+    }
+
+    let deadlocks = nodes.iter().enumerate()
+        .filter(|(_, n)| n.expanded && n.succs.is_empty() && !is_terminated(&n.conf))
+        .map(|(id, n)| Counterexample { trace: trace_to(&nodes, id), state: n.conf.clone(), cycle: vec![] })
+        .collect();
+
+    let livelocks = find_livelocks(&nodes);
+
+    ExploreReport { states: nodes.len(), transitions, deadlocks, livelocks, complete }
+}
+
+// Only 0 processes are left, and no memory is needed anymore for that
+pub fn is_terminated(conf: &Configuration) -> bool
+{
+    conf.procs.iter().all(|p| matches!(p.tagged.proc, PrimProcess::End))
+}
+
+fn trace_to(nodes: &[Node], mut id: usize) -> Vec<Transition>
+{
+    let mut trace = vec![];
+    while let Some((parent, transition)) = &nodes[id].parent {
+        trace.push(transition.clone());
+        id = *parent;
+    }
+    trace.reverse();
+    trace
+}
+
+// Livelocks are the strongly connected components with a cycle from which no terminated
+//     or unexplored configuration can be reached, one counterexample is given for each
+fn find_livelocks(nodes: &[Node]) -> Vec<Counterexample>
+{
+    // Backwards search from the configurations that may still end well
+    let mut preds = vec![vec![]; nodes.len()];
+    for (id, n) in nodes.iter().enumerate() {
+        for (succ, _) in &n.succs {
+            preds[*succ].push(id);
+        }
+    }
+    let mut may_end = nodes.iter().map(|n| !n.expanded || is_terminated(&n.conf)).collect::<Vec<_>>();
+    let mut stack = (0..nodes.len()).filter(|id| may_end[*id]).collect::<Vec<_>>();
+    while let Some(id) = stack.pop() {
+        for pred in &preds[id] {
+            if !may_end[*pred] {
+                may_end[*pred] = true;
+                stack.push(*pred);
+            }
+        }
+    }
+
+    let comp = strongly_connected(nodes);
+    let mut reported = vec![false; nodes.len()];
+    let mut livelocks = vec![];
+
+    // Nodes are in breadth first order, so the first node of a component has the shortest trace
+    for id in 0..nodes.len() {
+        if may_end[id] || reported[comp[id]] {
+            continue
+        }
+        let cycle = match cycle_in_component(nodes, &comp, id) {
+            Some(cycle) => cycle,
+            None => continue,
+        };
+
+        reported[comp[id]] = true;
+        livelocks.push(Counterexample { trace: trace_to(nodes, id), state: nodes[id].conf.clone(), cycle });
+    }
+
+    livelocks
+}
+
+// Shortest path from the node back to itself, staying inside its component
+fn cycle_in_component(nodes: &[Node], comp: &[usize], start: usize) -> Option<Vec<Transition>>
+{
+    let mut prev: HashMap<usize, (usize, Transition)> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(id) = queue.pop_front() {
+        for (succ, transition) in &nodes[id].succs {
+            if comp[*succ] != comp[start] || prev.contains_key(succ) {
+                continue
+            }
+            prev.insert(*succ, (id, transition.clone()));
+            if *succ == start {
+                let mut cycle = vec![];
+                let mut at = start;
+                loop {
+                    let (p, t) = prev[&at].clone();
+                    cycle.push(t);
+                    at = p;
+                    if at == start { break }
+                }
+                cycle.reverse();
+                return Some(cycle)
+            }
+            queue.push_back(*succ);
+        }
+    }
+
+    None
+}
+
+// Component index of every node, by Kosaraju's algorithm without recursion
+fn strongly_connected(nodes: &[Node]) -> Vec<usize>
+{
+    let n = nodes.len();
+    let mut order = vec![];
+    let mut visited = vec![false; n];
+
+    for root in 0..n {
+        if visited[root] { continue }
+        visited[root] = true;
+        let mut stack = vec![(root, 0)];
+        while let Some((id, i)) = stack.pop() {
+            match nodes[id].succs.get(i) {
+                Some((succ, _)) => {
+                    stack.push((id, i + 1));
+                    if !visited[*succ] {
+                        visited[*succ] = true;
+                        stack.push((*succ, 0));
+                    }
+                },
+                None => order.push(id),
+            }
+        }
+    }
+
+    let mut preds = vec![vec![]; n];
+    for (id, node) in nodes.iter().enumerate() {
+        for (succ, _) in &node.succs {
+            preds[*succ].push(id);
+        }
+    }
+
+    let mut comp = vec![usize::MAX; n];
+    let mut count = 0;
+    for root in order.into_iter().rev() {
+        if comp[root] != usize::MAX { continue }
+        comp[root] = count;
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            for pred in &preds[id] {
+                if comp[*pred] == usize::MAX {
+                    comp[*pred] = count;
+                    stack.push(*pred);
+                }
+            }
+        }
+        count += 1;
+    }
+
+    comp
+}
+
+// Printed form of the configuration that is the same for configurations which only differ
+//     in the names of the tags and channels created during the run and in the order of their processes
+// The created names are renamed in the order they appear once the processes are sorted without them,
+//     so equal forms always mean equivalent configurations, symmetric ones may still get different forms
+pub fn canonical_form(conf: &Configuration) -> String
+{
+    let mut erased = Renaming { conf, names: None };
+    let mut items = conf.procs.iter().map(Item::Proc)
+        .chain(conf.memories.iter().map(Item::Memory))
+        .map(|item| (print_item(&item, &mut erased), item))
+        .collect::<Vec<_>>();
+    items.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut numbered = Renaming { conf, names: Some(HashMap::new()) };
+    let mut renamed = items.into_iter()
+        .map(|(_, item)| print_item(&item, &mut numbered))
+        .collect::<Vec<_>>();
+    renamed.sort();
+
+    renamed.join("\n")
+}
+
+enum Item<'a>
+{
+    Proc(&'a ConfProc),
+    Memory(&'a Memory),
+}
+
+fn print_item(item: &Item, names: &mut Renaming) -> String
+{
+    match item {
+        Item::Proc(p) =>
+            format!("{} > {}: {}", p.owner, print_tag(&names.tag(&p.tagged.tag)), print_process(&names.process(&p.tagged.proc.clone().to_process()))),
+        Item::Memory(m) =>
+            format!("[{} {}: {} | {} {}: {}; {}]",
+                m.ids.0, print_tag(&names.tag(&m.sender.tag)), print_process(&names.process(&m.sender.proc)),
+                m.ids.1, print_tag(&names.tag(&m.receiver.tag)), print_process(&names.process(&m.receiver.proc)),
+                names.key(&m.key).0),
+    }
+}
+
+fn print_tag(tag: &ProcTag) -> String
+{
+    match tag {
+        ProcTag::PTKey(TagKey(key)) => key.clone(),
+        ProcTag::PTSplit(TagKey(frag), total, TagKey(og)) => format!("{}/{}/{}", frag, total, og),
+    }
+}
+
+// Renames the tag keys and channels the configuration created during the run,
+//     the names given by the initial configuration are left as they are
+struct Renaming<'a>
+{
+    conf: &'a Configuration,
+    // The created names are numbered in the order they are met, without it they are all erased
+    names: Option<HashMap<String, String>>,
+}
+
+impl Renaming<'_>
+{
+    // The new names have a '#', so they can not be names of the initial configuration
+    fn name(&mut self, name: &str) -> String
+    {
+        match &mut self.names {
+            Some(names) => {
+                let next = format!("#{}", names.len());
+                names.entry(name.to_string()).or_insert(next).clone()
+            },
+            None => "#".to_string(),
+        }
+    }
+
+    fn key(&mut self, key: &TagKey) -> TagKey
+    {
+        if self.conf.is_created_key(key) { TagKey(self.name(&key.0)) } else { key.clone() }
+    }
+
+    fn channel(&mut self, ch_name: &ChName) -> ChName
+    {
+        if self.conf.is_created_channel(ch_name) { ChName(self.name(&ch_name.0)) } else { ch_name.clone() }
+    }
+
+    // The fragments of a tag are named after it, so they follow its renaming
+    fn tag(&mut self, tag: &ProcTag) -> ProcTag
+    {
+        match tag {
+            ProcTag::PTKey(key) => ProcTag::PTKey(self.key(key)),
+            ProcTag::PTSplit(TagKey(frag), total, og) => {
+                let renamed = self.key(og);
+                let frag = match frag.strip_suffix(og.0.as_str()) {
+                    Some(prefix) => format!("{}{}", prefix, renamed.0),
+                    None => self.key(&TagKey(frag.clone())).0,
+                };
+                ProcTag::PTSplit(TagKey(frag), *total, renamed)
+            },
+        }
+    }
+
+    // The restrictions keep their names, created channels are never bound
+    fn process(&mut self, p: &Process) -> Process
+    {
+        match p {
+            Process::End | Process::PVar(_) | Process::RollV(_) => p.clone(),
+            Process::RollK(key) => Process::RollK(self.key(key)),
+            Process::Par(a, b) => {
+                let a = self.process(a);
+                Process::Par(Box::new(a), Box::new(self.process(b)))
+            },
+            Process::Send(ch_name, data, q) =>
+                Process::Send(self.channel(ch_name), data.clone(), Box::new(self.process(q))),
+            Process::Recv(ch_name, d_vars, p_var, t_var, q) =>
+                Process::Recv(self.channel(ch_name), d_vars.clone(), p_var.clone(), t_var.clone(), Box::new(self.process(q))),
+            Process::New(ch_name, q) =>
+                Process::New(ch_name.clone(), Box::new(self.process(q))),
+        }
+    }
+}
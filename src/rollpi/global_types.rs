@@ -0,0 +1,149 @@
+use super::errors::ProjectionError;
+use super::local_types::{Label, LocalType, PartLocalType, Party, VarName};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GlobalType
+{
+    End,
+    // Message from the first party to the second one, the sender picks one of the labelled branches
+    Msg(Party, Party, Vec<(Label, GlobalType)>),
+    RAbs(VarName, Box<GlobalType>),
+    RVar(VarName),
+}
+
+impl GlobalType
+{
+    // All the parties taking part in the global type, in order of their first appearance
+    pub fn roles(&self) -> Vec<Party>
+    {
+        let mut roles = vec![];
+        self._collect_roles(&mut roles);
+        roles
+    }
+
+    fn _collect_roles(&self, roles: &mut Vec<Party>)
+    {
+        match self {
+            GlobalType::End => (),
+            GlobalType::RVar(_) => (),
+            GlobalType::RAbs(_, g) => g._collect_roles(roles),
+            GlobalType::Msg(from, to, branches) => {
+                for party in [from, to] {
+                    if !roles.contains(party) {
+                        roles.push(party.clone());
+                    }
+                }
+                branches.iter().for_each(|(_, g)| g._collect_roles(roles));
+            },
+        }
+    }
+
+    // Projects the global type onto the given role
+    // A role that does not take part in a choice must behave the same in every branch,
+    //     up to receiving different labels from the same party (merging)
+    pub fn project(&self, role: &Party) -> Result<LocalType, ProjectionError>
+    {
+        self._project(role, &mut vec![])
+    }
+
+    // Projection onto every role of the global type, in the order given by roles()
+    pub fn project_all(&self) -> Result<Vec<PartLocalType>, ProjectionError>
+    {
+        self.roles().into_iter().map(|role| {
+            let local_type = self.project(&role)?;
+            Ok(PartLocalType::new(role, local_type))
+        }).collect()
+    }
+
+    fn _project(&self, role: &Party, path: &mut Vec<Label>) -> Result<LocalType, ProjectionError>
+    {
+        match self {
+            GlobalType::End => Ok(LocalType::End),
+            GlobalType::RVar(r_label) => Ok(LocalType::RVar(r_label.clone())),
+            GlobalType::RAbs(r_label, g) => {
+                match g._project(role, path)? {
+                    // The role does not take part in the recursion at all
+                    LocalType::RVar(body_label) if &body_label == r_label => Ok(LocalType::End),
+                    // The role only jumps straight to an outer recursion
+                    LocalType::RVar(body_label) => Ok(LocalType::RVar(body_label)),
+                    body => Ok(LocalType::RAbs(r_label.clone(), Box::new(body))),
+                }
+            },
+            GlobalType::Msg(from, to, branches) => {
+                if from == to {
+                    return Err(ProjectionError::SelfCommunication(role.clone(), path.clone(), from.clone()))
+                }
+                if branches.is_empty() {
+                    return Err(ProjectionError::EmptyChoice(role.clone(), path.clone()))
+                }
+                for (i, (label, _)) in branches.iter().enumerate() {
+                    if branches[..i].iter().any(|(l, _)| l == label) {
+                        return Err(ProjectionError::DuplicateLabel(role.clone(), path.clone(), label.clone()))
+                    }
+                }
+
+                let mut projected = vec![];
+                for (label, g) in branches {
+                    path.push(label.clone());
+                    let lt = g._project(role, path);
+                    path.pop();
+                    projected.push((label.clone(), lt?));
+                }
+
+                // Global types do not describe data, the projected branches carry no values
+                if role == from {
+                    return Ok(LocalType::Send(to.clone(), projected.into_iter().map(|(label, lt)| (label, vec![], lt)).collect()))
+                }
+                if role == to {
+                    return Ok(LocalType::Recv(from.clone(), projected.into_iter().map(|(label, lt)| (label, vec![], lt)).collect()))
+                }
+
+                let mut projected = projected.into_iter();
+                let (_, first) = projected.next().unwrap();
+                projected.try_fold(first, |acc, (label, lt)| {
+                    merge(acc, lt).ok_or_else(|| ProjectionError::NotMergeable(role.clone(), path.clone(), label))
+                })
+            },
+        }
+    }
+}
+
+// Merges the behaviours of a role in two branches of a choice it is not part of
+// Receives from the same party are merged by joining their branches, anything else must be equal
+fn merge(a: LocalType, b: LocalType) -> Option<LocalType>
+{
+    match (a, b) {
+        (LocalType::Recv(party_a, branches_a), LocalType::Recv(party_b, branches_b)) if party_a == party_b => {
+            let mut merged = branches_a;
+            for (label, sorts, lt) in branches_b {
+                match merged.iter().position(|(l, _, _)| *l == label) {
+                    Some(i) if merged[i].1 == sorts => {
+                        let prev = std::mem::replace(&mut merged[i].2, LocalType::End);
+                        merged[i].2 = merge(prev, lt)?;
+                    },
+                    Some(_) => return None,
+                    None => merged.push((label, sorts, lt)),
+                }
+            }
+            Some(LocalType::Recv(party_a, merged))
+        },
+        (LocalType::Send(party_a, branches_a), LocalType::Send(party_b, branches_b)) => {
+            let same_labels = party_a == party_b && branches_a.len() == branches_b.len() &&
+                branches_a.iter().zip(branches_b.iter()).all(|((la, sa, _), (lb, sb, _))| la == lb && sa == sb);
+            if !same_labels {
+                return None
+            }
+
+            let merged = branches_a.into_iter().zip(branches_b).map(|((label, sorts, lt_a), (_, _, lt_b))| {
+                merge(lt_a, lt_b).map(|lt| (label, sorts, lt))
+            }).collect::<Option<Vec<_>>>()?;
+            Some(LocalType::Send(party_a, merged))
+        },
+        (LocalType::RAbs(r_a, body_a), LocalType::RAbs(r_b, body_b)) if r_a == r_b => {
+            Some(LocalType::RAbs(r_a, Box::new(merge(*body_a, *body_b)?)))
+        },
+        (a, b) => {
+            if a == b { Some(a) } else { None }
+        },
+    }
+}
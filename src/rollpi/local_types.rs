@@ -1,22 +1,121 @@
-use crate::rollpi::syntax::{ChName, ProcVar, TagVar};
+use std::collections::{HashMap, HashSet};
 
-use super::syntax::Process;
+use crate::rollpi::syntax::{ChName, DataBinder, DataVar, Expr, ProcVar, SessionId, TagVar};
 
-type VarName = String;
-type Label = String;
-type Party = String;
+// The sorts the receiver can give to the values sent along a label
+pub use crate::rollpi::syntax::Sort;
 
-#[derive(Clone, Debug)]
+use super::{syntax::Process, errors::LocalTypeError};
+
+pub type VarName = String;
+pub type Label = String;
+pub type Party = String;
+
+// A choice in the encoding of a local type send: the party sends one offer per label to the other party,
+//     all on the same channel, and the first offer the other party receives is the branch that runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChoicePoint
+{
+    pub party: Party,
+    pub to: Party,
+    pub labels: Vec<Label>,
+    pub channel: ChName,
+    // Offer of each label, in the order of the labels
+    pub offers: Vec<Process>,
+}
+
+// Every branch has a label, the values sent with it and the continuation
+// The sender gives an expression for each value, using the data variables bound by its receives before it,
+//     the receiver binds each value to a data variable, with a sort if it only takes values of that sort
+#[derive(Clone, Debug, PartialEq)]
 pub enum LocalType
 {
     End,
-    Send(Party, Vec<(Label, LocalType)>),
-    Recv(Party, Vec<(Label, LocalType)>),
+    Send(Party, Vec<(Label, Vec<Expr>, LocalType)>),
+    Recv(Party, Vec<(Label, Vec<DataBinder>, LocalType)>),
     RAbs(VarName, Box<LocalType>),
     RVar(VarName),
 }
 
-#[derive(Clone, Debug)]
+impl LocalType
+{
+    // Checks the local type of the given party for unguarded or free recursion variables,
+    //     duplicate labels, empty choices, communications with the party itself and data variables
+    //     sent without being received before
+    // All the problems found are returned, in the order they appear in the type
+    pub fn validate(&self, party: &Party) -> Result<(), Vec<LocalTypeError>>
+    {
+        let mut errors = vec![];
+        self._validate(party, &mut vec![], &HashSet::new(), &mut errors);
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    // The environment holds the bound recursion variables, innermost last,
+    //     together with a flag telling if a communication happened since their binder
+    // The data variables are the ones bound by the receives on the way to the local type
+    fn _validate(&self, party: &Party, env: &mut Vec<(VarName, bool)>, d_vars: &HashSet<DataVar>, errors: &mut Vec<LocalTypeError>)
+    {
+        match self {
+            LocalType::End => (),
+            LocalType::RVar(r_label) => {
+                match env.iter().rev().find(|(var, _)| var == r_label) {
+                    None => errors.push(LocalTypeError::FreeRecVar(r_label.clone())),
+                    Some((_, false)) => errors.push(LocalTypeError::UnguardedRecursion(r_label.clone())),
+                    Some((_, true)) => (),
+                }
+            },
+            LocalType::RAbs(r_label, t) => {
+                env.push((r_label.clone(), false));
+                t._validate(party, env, d_vars, errors);
+                env.pop();
+            },
+            LocalType::Send(other, opts) => {
+                validate_choice(party, other, opts, errors);
+
+                for (label, data, _) in opts {
+                    for var in data.iter().flat_map(|e| e.free_vars()).filter(|var| !d_vars.contains(var)) {
+                        errors.push(LocalTypeError::FreeDataVar(label.clone(), var.0));
+                    }
+                }
+
+                // Every recursion variable is guarded after a communication
+                let mut guarded_env = env.iter().map(|(var, _)| (var.clone(), true)).collect();
+                for (_, _, lt) in opts {
+                    lt._validate(party, &mut guarded_env, d_vars, errors);
+                }
+            },
+            LocalType::Recv(other, opts) => {
+                validate_choice(party, other, opts, errors);
+
+                let mut guarded_env = env.iter().map(|(var, _)| (var.clone(), true)).collect();
+                for (_, binders, lt) in opts {
+                    let mut inner_d_vars = d_vars.clone();
+                    inner_d_vars.extend(binders.iter().map(|DataBinder(var, _)| var.clone()));
+                    lt._validate(party, &mut guarded_env, &inner_d_vars, errors);
+                }
+            },
+        }
+    }
+}
+
+// The checks shared by sends and receives: the other party, the branches and their labels
+fn validate_choice<D>(party: &Party, other: &Party, opts: &[(Label, Vec<D>, LocalType)], errors: &mut Vec<LocalTypeError>)
+{
+    if other == party {
+        errors.push(LocalTypeError::SelfCommunication(party.clone()));
+    }
+    if opts.is_empty() {
+        errors.push(LocalTypeError::EmptyChoice(other.clone()));
+    }
+    for (i, (label, _, _)) in opts.iter().enumerate() {
+        if opts[..i].iter().any(|(l, _, _)| l == label) {
+            errors.push(LocalTypeError::DuplicateLabel(other.clone(), label.clone()));
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct PartLocalType
 {
     party: Party,
@@ -35,79 +134,150 @@ impl PartLocalType
         self.party.clone()
     }
 
-    // TODO: Maybe make Tvar tags different from each other using a counter context 
-    pub fn to_process(self: Self) -> Process
+    pub fn validate(&self) -> Result<(), Vec<LocalTypeError>>
+    {
+        self.local_type.validate(&self.party)
+    }
+
+    // Encodes the local type into a roll-pi process, refusing local types that do not validate
+    pub fn to_process(self: Self) -> Result<Process, Vec<LocalTypeError>>
+    {
+        self.to_process_in(&SessionId::default())
+    }
+
+    // Same as to_process, all the channels of the encoding belong to the given session,
+    //     so that several instances of the same protocol do not share channels
+    pub fn to_process_in(self: Self, session: &SessionId) -> Result<Process, Vec<LocalTypeError>>
+    {
+        self.to_process_with_choices_in(session).map(|(proc, _)| proc)
+    }
+
+    // Same as to_process, together with the choice points of the encoding, each one listed once
+    pub fn to_process_with_choices(self) -> Result<(Process, Vec<ChoicePoint>), Vec<LocalTypeError>>
+    {
+        self.to_process_with_choices_in(&SessionId::default())
+    }
+
+    pub fn to_process_with_choices_in(self, session: &SessionId) -> Result<(Process, Vec<ChoicePoint>), Vec<LocalTypeError>>
+    {
+        self.validate()?;
+
+        let mut names = NameSupply { session: session.clone(), ..NameSupply::default() };
+        let proc = self._to_process(&mut names, &HashMap::new());
+        Ok((proc, names.choices))
+    }
+
+    // Every binder gets a name from the supply, so no variable is bound twice on the same path,
+    //     the environment maps the recursion variables in scope to their (rec_norm, rec_comb) channels
+    fn _to_process(self: Self, names: &mut NameSupply, rec_chs: &HashMap<VarName, (ChName, ChName)>) -> Process
     {
         let PartLocalType { party, local_type } = self;
         match local_type
         {
             LocalType::End => Process::End,
+            // The sender offers every branch on the same channel, an offer is a process the receiver runs:
+            //     it takes its branch of the receiver, drops the other branches
+            //     and sends the continuation of the sender back on the option channel
+            // The sender runs the continuation, which starts by sending the values of the label on their own channel
+            // The receiver takes the first offer and drains the others before running it, so exactly one branch runs
+            //     and the next choice between the two parties only starts once this one is over
             LocalType::Send(to_party, opts) => {
-                let opt_ch = ChName(format!("comm_opt_{}_{}", party, to_party));
-                let base_snd_ch = format!("comm_snd_{}_{}", party, to_party);
-                // let ord_ch = ChName(format!("comm_ord_{}_{}", party, to_party));
+                let snd_ch = names.session.channel(&format!("comm_snd_{}_{}", party, to_party));
+                let opt_ch = names.session.channel(&format!("comm_opt_{}_{}", party, to_party));
+                let branch_chs = opts.iter()
+                    .map(|(label, _, _)| names.session.channel(&format!("comm_branch_{}_{}_{}", party, to_party, label)))
+                    .collect::<Vec<_>>();
 
-                let choice_var = ProcVar("C".to_string());
-                let receive_choice_branch = Process::Recv(opt_ch.clone(), choice_var.clone(), TagVar("_u_".to_string()), Box::new(Process::PVar(choice_var.clone())));
+                let choice_var = ProcVar(names.fresh("C"));
+                let receive_choice_branch = Process::Recv(opt_ch.clone(), vec![], choice_var.clone(), TagVar(names.fresh("u")), Box::new(Process::PVar(choice_var.clone())));
 
-                let send_branch = opts.into_iter().map(|(label, lt)| {
+                let labels = opts.iter().map(|(label, _, _)| label.clone()).collect::<Vec<_>>();
+
+                let offers = opts.into_iter().enumerate().map(|(i, (label, data, lt))| {
                     let lt_enc = PartLocalType { party: party.clone(), local_type: lt };
-                    let snd_ch = ChName(format!("{}_{}", base_snd_ch, label));
-                    Process::Send(snd_ch.clone(), Box::new(lt_enc.to_process()))
-                }).collect();
+                    let branch_var = ProcVar(names.fresh(&format!("Z_{}", label)));
+
+                    let mut cont = lt_enc._to_process(names, rec_chs);
+                    if !data.is_empty() {
+                        let dat_ch = names.session.channel(&format!("comm_dat_{}_{}_{}", party, to_party, label));
+                        cont = Process::Par(Box::new(Process::Send(dat_ch, data, Box::new(Process::End))), Box::new(cont));
+                    }
+
+                    let body = vec![
+                        Process::PVar(branch_var.clone()),
+                        Process::Send(opt_ch.clone(), vec![], Box::new(cont)),
+                    ];
+
+                    let others = branch_chs.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, ch)| ch).collect::<Vec<_>>();
+                    let drop_others = others.into_iter().rev().fold(Process::parallel_compose(body), |acc, ch| {
+                        Process::Recv(ch.clone(), vec![], ProcVar(names.fresh("Y")), TagVar(names.fresh("u")), Box::new(acc))
+                    });
 
-                let send_branch = Process::parallel_compose(send_branch);
+                    Process::Recv(branch_chs[i].clone(), vec![], branch_var, TagVar(names.fresh("u")), Box::new(drop_others))
+                }).collect::<Vec<_>>();
 
-                Process::Par(Box::new(send_branch), Box::new(receive_choice_branch))
+                let point = ChoicePoint { party: party.clone(), to: to_party.clone(), labels, channel: snd_ch.clone(), offers: offers.clone() };
+                if !names.choices.contains(&point) {
+                    names.choices.push(point);
+                }
+
+                let send_offers = offers.into_iter().map(|offer| Process::Send(snd_ch.clone(), vec![], Box::new(offer))).collect();
+
+                Process::Par(Box::new(Process::parallel_compose(send_offers)), Box::new(receive_choice_branch))
             },
             LocalType::Recv(from_party, opts) => {
-                let opt_ch = ChName(format!("comm_opt_{}_{}", from_party, party));
-                let base_snd_ch = format!("comm_snd_{}_{}", from_party, party);
-                let ord_ch = ChName(format!("comm_ord_{}_{}", from_party, party));
+                let snd_ch = names.session.channel(&format!("comm_snd_{}_{}", from_party, party));
 
-                let drain_var = ProcVar("D".to_string());
-                let drain_branch = opts.iter().fold(Process::PVar(drain_var.clone()), |acc, _cnt| {
-                    Process::Recv(ord_ch.clone(), drain_var.clone(), TagVar("_u_".to_string()), Box::new(acc))
+                // The offers after the first one are dropped
+                let offer_var = ProcVar(names.fresh("X"));
+                let drop_offers = opts[1..].iter().fold(Process::PVar(offer_var.clone()), |acc, _| {
+                    Process::Recv(snd_ch.clone(), vec![], ProcVar(names.fresh("Y")), TagVar(names.fresh("u")), Box::new(acc))
                 });
+                let take_offer = Process::Recv(snd_ch.clone(), vec![], offer_var, TagVar(names.fresh("u")), Box::new(drop_offers));
 
-                let recv_branches = opts.into_iter().map(|(label, lt)| {
+                // Every branch waits on its own channel for the offer that takes it, then for the values of its label
+                let branches = opts.into_iter().map(|(label, d_vars, lt)| {
                     let lt_enc = PartLocalType { party: party.clone(), local_type: lt };
-                    let snd_ch = ChName(format!("{}_{}", base_snd_ch, label));
-                    let x_var = ProcVar(format!("X_{}", label));
+                    let branch_ch = names.session.channel(&format!("comm_branch_{}_{}_{}", from_party, party, label));
 
-                    let recv_cont = Process::Send(ord_ch.clone(), Box::new(Process::Par(
-                        Box::new(lt_enc.to_process()),
-                        Box::new(Process::Send(opt_ch.clone(), Box::new(Process::PVar(x_var.clone())))),
-                    )));
-                    
-                    Process::Recv(snd_ch.clone(), x_var.clone(), TagVar("_u_".to_string()), Box::new(recv_cont))
-                }).collect();
+                    let mut branch = lt_enc._to_process(names, rec_chs);
+                    if !d_vars.is_empty() {
+                        let dat_ch = names.session.channel(&format!("comm_dat_{}_{}_{}", from_party, party, label));
+                        branch = Process::Recv(dat_ch, d_vars, ProcVar(names.fresh("W")), TagVar(names.fresh("u")), Box::new(branch));
+                    }
 
-                let recv_branches = Process::parallel_compose(recv_branches);
+                    Process::Send(branch_ch, vec![], Box::new(branch))
+                }).collect::<Vec<_>>();
 
                 Process::Par(
-                    Box::new(recv_branches),
-                    Box::new(drain_branch),
+                    Box::new(Process::parallel_compose(branches)),
+                    Box::new(take_offer),
                 )
             },
             LocalType::RAbs(r_label, t) => {
-                let t_enc = PartLocalType { party: party.clone(), local_type: *t };
-                let t_enc = t_enc.to_process();
+                // A nested binder with the same label gets its own channels
+                let norm_ch = names.fresh(&format!("rec_norm_{}_{}", party, r_label));
+                let norm_ch = names.session.channel(&norm_ch);
+                let comb_ch = names.fresh(&format!("rec_comb_{}_{}", party, r_label));
+                let comb_ch = names.session.channel(&comb_ch);
 
-                let norm_ch = ChName(format!("rec_norm_{}_{}", party, r_label));
-                let comb_ch = ChName(format!("rec_comb_{}_{}", party, r_label));
+                let mut inner_rec_chs = rec_chs.clone();
+                inner_rec_chs.insert(r_label.clone(), (norm_ch.clone(), comb_ch.clone()));
+
+                let t_enc = PartLocalType { party: party.clone(), local_type: *t };
+                let t_enc = t_enc._to_process(names, &inner_rec_chs);
 
-                let xvar = ProcVar(format!("X_{}", r_label));
+                let xvar = ProcVar(names.fresh(&format!("X_{}", r_label)));
 
                 let replica_proc = Process::parallel_compose(vec![
                     Process::PVar(xvar.clone()),
-                    Process::Send(comb_ch.clone(), Box::new(Process::PVar(xvar.clone()))),
-                    Process::Send(norm_ch.clone(), Box::new(t_enc.clone())),
+                    Process::Send(comb_ch.clone(), vec![], Box::new(Process::PVar(xvar.clone()))),
+                    Process::Send(norm_ch.clone(), vec![], Box::new(t_enc.clone())),
                 ]);
 
-                let recv_proc = Process::Recv(comb_ch.clone(), xvar.clone(), TagVar("_u_".to_string()), Box::new(replica_proc.clone()));
+                let recv_proc = Process::Recv(comb_ch.clone(), vec![], xvar.clone(), TagVar(names.fresh("u")), Box::new(replica_proc.clone()));
 
-                let send_proc = Process::Send(comb_ch.clone(), Box::new(recv_proc.clone()));
+                let send_proc = Process::Send(comb_ch.clone(), vec![], Box::new(recv_proc.clone()));
 
                 Process::parallel_compose(vec![
                     recv_proc, 
@@ -116,11 +286,41 @@ impl PartLocalType
                 ])
             },
             LocalType::RVar(r_label) => {
-                let norm_ch = ChName(format!("rec_norm_{}_{}", party, r_label));
-                let xvar = ProcVar(format!("X_r_{}", r_label));
+                // The local type is validated before encoding, so the variable is always bound
+                let (norm_ch, _comb_ch) = rec_chs.get(&r_label).unwrap();
+                let xvar = ProcVar(names.fresh(&format!("X_r_{}", r_label)));
 
-                Process::Recv(norm_ch.clone(), xvar.clone(), TagVar("_u_".to_string()), Box::new(Process::PVar(xvar)))
+                Process::Recv(norm_ch.clone(), vec![], xvar.clone(), TagVar(names.fresh("u")), Box::new(Process::PVar(xvar)))
             },
         }
     }
-}
\ No newline at end of file
+}
+
+// Supply of names that were not handed out before during an encoding
+#[derive(Default)]
+struct NameSupply
+{
+    used: HashSet<String>,
+    // Session the channels of the encoding belong to
+    session: SessionId,
+    // Choice points met so far
+    choices: Vec<ChoicePoint>,
+}
+
+impl NameSupply
+{
+    // Returns the base name if it is still free, otherwise the base name with the first free numeric suffix
+    fn fresh(&mut self, base: &str) -> String
+    {
+        let mut name = base.to_string();
+        let mut i = 1;
+        while self.used.contains(&name) {
+            name = format!("{}_{}", base, i);
+            i += 1;
+        }
+
+        self.used.insert(name.clone());
+        name
+    }
+}
+
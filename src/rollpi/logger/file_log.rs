@@ -1,4 +1,6 @@
 use std::fs::File;
+use std::io::Write;
+use std::path::Path;
 
 pub struct FileLogger
 {
@@ -12,24 +14,42 @@ pub struct FileLogger
 
 impl FileLogger
 {
-    pub fn new(id: String) -> Self
+    pub fn new(id: String) -> std::io::Result<Self>
     {
-        FileLogger {
-            file: File::create(format!("logs/{}.log", id)).unwrap()
-        }
+        FileLogger::in_dir(Path::new("logs"), &id, "log")
     }
 
-    pub fn log_s(self: &mut Self, msg: &str)
+    // Creates <dir>/<id>.<ext>, together with the directory if it is missing
+    pub fn in_dir(dir: &Path, id: &str, ext: &str) -> std::io::Result<Self>
     {
-        use std::io::Write;
+        std::fs::create_dir_all(dir)?;
 
-        self.file.write_all(msg.as_bytes()).unwrap()
+        Ok(FileLogger {
+            file: File::create(dir.join(format!("{}.{}", id, ext)))?
+        })
+    }
+
+    // A log that can not be written does not stop the run, so the write errors are dropped
+    pub fn log_s(self: &mut Self, msg: &str)
+    {
+        let _ = self.file.write_all(msg.as_bytes());
     }
 
     pub fn log(self: &mut Self, msg: String)
     {
-        use std::io::Write;
+        self.log_s(&msg);
+    }
+}
 
-        self.file.write_all(msg.as_bytes()).unwrap();
+impl Write for FileLogger
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>
+    {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()>
+    {
+        self.file.flush()
     }
-}
\ No newline at end of file
+}
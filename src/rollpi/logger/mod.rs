@@ -1 +1,2 @@
-pub mod file_log;
\ No newline at end of file
+pub mod file_log;
+pub mod trace;
\ No newline at end of file
@@ -0,0 +1,280 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::rollpi::printer::print_process;
+use crate::rollpi::syntax::{ChName, Process, ProcTag, TagKey, Value};
+
+use super::file_log::FileLogger;
+
+// Something that happened to a participant or to the history during a run
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent
+{
+    Send { channel: ChName, tag: ProcTag, data: Vec<Value>, payload: Process },
+    // The receiver_tag process received the sender_tag process of sender, creating new_tag
+    Recv { channel: ChName, sender: String, sender_tag: ProcTag, receiver_tag: ProcTag, new_tag: TagKey },
+    RollRequest { tag: ProcTag, target: TagKey },
+    // Sent by the history to the owner of the tag, and recorded again by the owner when it freezes it
+    FreezeSignal { tag: ProcTag, owner: String },
+    Disappear { tag: ProcTag },
+    // The history gives back the process with the given tag to its owner after the disappeared tag is gone
+    Resurrect { disappeared: ProcTag, tag: ProcTag, owner: String },
+    End { tag: ProcTag },
+}
+
+// An event with its place in the run
+// The sequence number is shared by all the participants of the run, so their traces can be merged
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceRecord
+{
+    pub seq: u64,
+    // Microseconds since the unix epoch
+    pub timestamp: u128,
+    // Participant, or history, that recorded the event
+    pub source: String,
+    pub event: TraceEvent,
+}
+
+// Destination of the events of one participant
+pub trait TraceSink : Send
+{
+    fn record(&mut self, rec: &TraceRecord);
+
+    // Free-form text, only kept by sinks meant to be read by people
+    fn note(&mut self, _msg: &str) {}
+}
+
+// Hands out the sequence numbers of a run
+#[derive(Debug, Default)]
+pub struct TraceClock
+{
+    next_seq: AtomicU64,
+}
+
+impl TraceClock
+{
+    pub fn tick(&self) -> (u64, u128)
+    {
+        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_micros()).unwrap_or_default();
+        (seq, timestamp)
+    }
+}
+
+// Records the events of one participant into all of its sinks
+pub struct Tracer
+{
+    source: String,
+    clock: Arc<TraceClock>,
+    sinks: Vec<Box<dyn TraceSink>>,
+}
+
+impl Tracer
+{
+    pub fn new(source: String, clock: Arc<TraceClock>, sinks: Vec<Box<dyn TraceSink>>) -> Self
+    {
+        Tracer { source, clock, sinks }
+    }
+
+    pub fn event(self: &mut Self, event: TraceEvent)
+    {
+        let (seq, timestamp) = self.clock.tick();
+        let rec = TraceRecord { seq, timestamp, source: self.source.clone(), event };
+        self.sinks.iter_mut().for_each(|sink| sink.record(&rec));
+    }
+
+    pub fn log(self: &mut Self, msg: String)
+    {
+        self.sinks.iter_mut().for_each(|sink| sink.note(&msg));
+    }
+}
+
+// Where the events of a run go, every participant gets its own sink for each target
+#[derive(Clone)]
+pub enum TraceTarget
+{
+    // <dir>/<id>.log in the human readable format
+    Text(PathBuf),
+    // <dir>/<id>.jsonl with one JSON object per event
+    JsonLines(PathBuf),
+    // Shared vector, mostly for tests
+    Memory(MemorySink),
+}
+
+impl Default for TraceTarget
+{
+    fn default() -> Self
+    {
+        TraceTarget::Text(PathBuf::from("logs"))
+    }
+}
+
+impl TraceTarget
+{
+    // Fails when the directory or the file of the participant can not be created
+    pub fn open(&self, id: &str) -> std::io::Result<Box<dyn TraceSink>>
+    {
+        Ok(match self {
+            TraceTarget::Text(dir) => Box::new(TextSink { file: FileLogger::in_dir(dir, id, "log")? }),
+            TraceTarget::JsonLines(dir) => Box::new(JsonLinesSink::new(FileLogger::in_dir(dir, id, "jsonl")?)),
+            TraceTarget::Memory(sink) => Box::new(sink.clone()),
+        })
+    }
+}
+
+// The format the logs always had
+pub struct TextSink
+{
+    file: FileLogger,
+}
+
+impl TextSink
+{
+    pub fn format(event: &TraceEvent) -> String
+    {
+        match event {
+            TraceEvent::Send { channel, tag, .. } =>
+                format!("INT: SEND {} to channel {} \n", tag, channel.0),
+            TraceEvent::Recv { channel, sender_tag, new_tag, .. } =>
+                format!("INT: RECV {} from channel {} as {} \n", sender_tag, channel.0, new_tag.0),
+            TraceEvent::RollRequest { tag, target } =>
+                format!("INT: ROLLK {} with target {} \n", tag, target.0),
+            TraceEvent::FreezeSignal { tag, owner } =>
+                format!("Freeze signal to {} for {}\n", owner, tag),
+            TraceEvent::Disappear { tag } =>
+                format!("Dissapearing process with tag {}\n", tag),
+            TraceEvent::Resurrect { disappeared, tag, owner } =>
+                format!("Sending ressurect message for {} to {}: {}\n", disappeared, owner, tag),
+            TraceEvent::End { tag } =>
+                format!("INT: END {} \n", tag),
+        }
+    }
+}
+
+impl TraceSink for TextSink
+{
+    fn record(&mut self, rec: &TraceRecord)
+    {
+        self.file.log(TextSink::format(&rec.event));
+    }
+
+    fn note(&mut self, msg: &str)
+    {
+        self.file.log_s(msg);
+    }
+}
+
+pub struct JsonLinesSink<W: Write + Send = FileLogger>
+{
+    out: W,
+}
+
+impl<W: Write + Send> JsonLinesSink<W>
+{
+    pub fn new(out: W) -> Self
+    {
+        JsonLinesSink { out }
+    }
+
+    pub fn format(rec: &TraceRecord) -> String
+    {
+        let mut fields = vec![
+            ("seq", rec.seq.to_string()),
+            ("timestamp", rec.timestamp.to_string()),
+            ("source", json_str(&rec.source)),
+        ];
+
+        let tag = |t: &ProcTag| json_str(&t.to_string());
+        let (kind, event_fields) = match &rec.event {
+            TraceEvent::Send { channel, tag: t, data, payload } =>
+                ("Send", vec![("channel", json_str(&channel.0)), ("tag", tag(t)),
+                    ("data", json_list(data)), ("payload", json_str(&print_process(payload)))]),
+            TraceEvent::Recv { channel, sender, sender_tag, receiver_tag, new_tag } =>
+                ("Recv", vec![("channel", json_str(&channel.0)), ("sender", json_str(sender)), ("sender_tag", tag(sender_tag)),
+                    ("receiver_tag", tag(receiver_tag)), ("new_tag", json_str(&new_tag.0))]),
+            TraceEvent::RollRequest { tag: t, target } =>
+                ("RollRequest", vec![("tag", tag(t)), ("target", json_str(&target.0))]),
+            TraceEvent::FreezeSignal { tag: t, owner } =>
+                ("FreezeSignal", vec![("tag", tag(t)), ("owner", json_str(owner))]),
+            TraceEvent::Disappear { tag: t } =>
+                ("Disappear", vec![("tag", tag(t))]),
+            TraceEvent::Resurrect { disappeared, tag: t, owner } =>
+                ("Resurrect", vec![("disappeared", tag(disappeared)), ("tag", tag(t)), ("owner", json_str(owner))]),
+            TraceEvent::End { tag: t } =>
+                ("End", vec![("tag", tag(t))]),
+        };
+        fields.push(("event", json_str(kind)));
+        fields.extend(event_fields);
+
+        let body = fields.into_iter().map(|(k, v)| format!("\"{}\":{}", k, v)).collect::<Vec<_>>().join(",");
+        format!("{{{}}}", body)
+    }
+}
+
+impl<W: Write + Send> TraceSink for JsonLinesSink<W>
+{
+    fn record(&mut self, rec: &TraceRecord)
+    {
+        // A trace that can not be written should not stop the run
+        let _ = writeln!(self.out, "{}", JsonLinesSink::<W>::format(rec));
+    }
+}
+
+fn json_str(s: &str) -> String
+{
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Values are written as JSON values, tuples as arrays
+fn json_list(values: &[Value]) -> String
+{
+    let items = values.iter().map(|v| match v {
+        Value::Int(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Str(s) => json_str(s),
+        Value::Tuple(vs) => json_list(vs),
+    }).collect::<Vec<_>>();
+    format!("[{}]", items.join(","))
+}
+
+// All the clones share the same records
+#[derive(Clone, Default)]
+pub struct MemorySink
+{
+    records: Arc<Mutex<Vec<TraceRecord>>>,
+}
+
+impl MemorySink
+{
+    // The records of all the participants, in the order of their sequence numbers
+    pub fn records(&self) -> Vec<TraceRecord>
+    {
+        let mut records = self.records.lock().unwrap().clone();
+        records.sort_by_key(|rec| rec.seq);
+        records
+    }
+}
+
+impl TraceSink for MemorySink
+{
+    fn record(&mut self, rec: &TraceRecord)
+    {
+        self.records.lock().unwrap().push(rec.clone());
+    }
+}
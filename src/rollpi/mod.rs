@@ -2,4 +2,13 @@ pub mod reductions;
 pub mod syntax;
 pub mod environment;
 pub mod local_types;
-pub mod logger;
\ No newline at end of file
+pub mod global_types;
+pub mod logger;
+pub mod parser;
+pub mod printer;
+pub mod errors;
+pub mod semantics;
+pub mod explorer;
+
+#[cfg(test)]
+mod tests;
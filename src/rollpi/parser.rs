@@ -0,0 +1,562 @@
+use super::errors::{ParseError, Position};
+use super::local_types::{Label, LocalType, Sort};
+use super::syntax::{ChName, DataBinder, DataVar, Expr, Process, ProcVar, TagKey, TagVar, Value};
+
+// Concrete syntax of roll-pi processes (lowest priority first)
+//
+//   P ::= S | P                       parallel composition (right associative)
+//       | S
+//   S ::= 0                           end process
+//       | roll k                      roll on the tag variable k
+//       | roll @k                     roll on the tag key k
+//       | a<P>                        send the process P on channel a
+//       | a[E, ..]<P>                 send the values of the expressions together with P
+//       | a(X, k).S                   receive on channel a into X, tagging with k
+//       | a[x, ..](X, k).S            receive the values into the data variables as well
+//       | a[x: Srt, ..](X, k).S       a data variable with a sort (see below) only takes values of that sort
+//       | new a.S                     restrict the channel a to S
+//       | X                           process variable
+//       | (P)
+//   E ::= 12 | -3                     integer
+//       | "text"                      string, with \" and \\ as escapes
+//       | true | false                boolean
+//       | x                           data variable
+//       | (E, ..)                     tuple, a single element one is written (E,)
+//       | (E)
+//
+// Names are made of letters, digits and '_' and can not start with a digit.
+// 'roll', 'new', 'true' and 'false' are keywords and can not be used as names. Whitespace is ignored between tokens.
+//
+// Concrete syntax of local types, read by parse_local_type
+//
+//   T ::= end                         end of the protocol
+//       | t                           recursion variable
+//       | rec t.T                     recursion
+//       | B!{Br, ..}                  send one of the branches to party B
+//       | B?{Br, ..}                  receive one of the branches from party B
+//       | B!Br | B?Br                 a single branch
+//   Br ::= l.T                        label l, then T
+//       | l(E, ..).T                  in a send, label l with the values of the expressions
+//       | l(x, ..).T                  in a receive, label l with values bound to the data variables in T
+//       | l(x: Srt, ..).T             a data variable with a sort only takes values of that sort
+//   Srt ::= int | str | bool | (Srt, ..)
+//
+// The expressions of a send can use the data variables bound by the receives before it.
+//
+// 'end' and 'rec' can not be used as recursion variables.
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token
+{
+    Name(String),
+    Zero,
+    Roll,
+    New,
+    Int(i64),
+    Str(String),
+    Bool(bool),
+    At,
+    Pipe,
+    Dot,
+    Comma,
+    Colon,
+    LAngle,
+    RAngle,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Bang,
+    Query,
+}
+
+impl Token
+{
+    fn describe(&self) -> String
+    {
+        match self {
+            Token::Name(name) => name.clone(),
+            Token::Zero => "0".to_string(),
+            Token::Roll => "roll".to_string(),
+            Token::New => "new".to_string(),
+            Token::Int(n) => n.to_string(),
+            Token::Str(s) => format!("{:?}", s),
+            Token::Bool(b) => b.to_string(),
+            Token::At => "@".to_string(),
+            Token::Pipe => "|".to_string(),
+            Token::Dot => ".".to_string(),
+            Token::Comma => ",".to_string(),
+            Token::Colon => ":".to_string(),
+            Token::LAngle => "<".to_string(),
+            Token::RAngle => ">".to_string(),
+            Token::LParen => "(".to_string(),
+            Token::RParen => ")".to_string(),
+            Token::LBracket => "[".to_string(),
+            Token::RBracket => "]".to_string(),
+            Token::LBrace => "{".to_string(),
+            Token::RBrace => "}".to_string(),
+            Token::Bang => "!".to_string(),
+            Token::Query => "?".to_string(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<(Vec<(Token, Position)>, Position), ParseError>
+{
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+    let mut pos = Position { line: 1, column: 1 };
+
+    while let Some(&c) = chars.peek() {
+        let start = pos;
+
+        if c.is_whitespace() {
+            chars.next();
+            if c == '\n' {
+                pos = Position { line: pos.line + 1, column: 1 };
+            } else {
+                pos.column += 1;
+            }
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+                pos.column += 1;
+            }
+
+            let token = match name.as_str() {
+                "roll" => Token::Roll,
+                "new" => Token::New,
+                "true" => Token::Bool(true),
+                "false" => Token::Bool(false),
+                _ => Token::Name(name),
+            };
+            tokens.push((token, start));
+            continue;
+        }
+
+        if c.is_ascii_digit() || c == '-' {
+            // The sign is part of the literal, so the smallest integer can be written
+            let mut digits = String::from(c);
+            chars.next();
+            pos.column += 1;
+            while let Some(&d) = chars.peek() {
+                if !d.is_ascii_digit() {
+                    break;
+                }
+                digits.push(d);
+                chars.next();
+                pos.column += 1;
+            }
+
+            let token = match digits.parse::<i64>() {
+                Ok(0) if digits == "0" => Token::Zero,
+                Ok(n) => Token::Int(n),
+                // A '-' without digits
+                Err(_) if digits == "-" => return Err(ParseError::UnexpectedChar(start, c)),
+                Err(_) => return Err(ParseError::IntOutOfRange(start, digits)),
+            };
+            tokens.push((token, start));
+            continue;
+        }
+
+        if c == '"' {
+            chars.next();
+            pos.column += 1;
+            let mut text = String::new();
+            loop {
+                let next = chars.next();
+                pos.column += 1;
+                match next {
+                    None => return Err(ParseError::UnexpectedEnd(pos, "'\"'".to_string())),
+                    Some('"') => break,
+                    Some('\\') => {
+                        let escaped = chars.next();
+                        pos.column += 1;
+                        match escaped {
+                            Some(e @ ('"' | '\\')) => text.push(e),
+                            Some(e) => return Err(ParseError::UnexpectedChar(Position { line: pos.line, column: pos.column - 1 }, e)),
+                            None => return Err(ParseError::UnexpectedEnd(pos, "'\"'".to_string())),
+                        }
+                    },
+                    Some('\n') => {
+                        text.push('\n');
+                        pos = Position { line: pos.line + 1, column: 1 };
+                    },
+                    Some(ch) => text.push(ch),
+                }
+            }
+            tokens.push((Token::Str(text), start));
+            continue;
+        }
+
+        let token = match c {
+            '@' => Token::At,
+            '|' => Token::Pipe,
+            '.' => Token::Dot,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '<' => Token::LAngle,
+            '>' => Token::RAngle,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '!' => Token::Bang,
+            '?' => Token::Query,
+            _ => return Err(ParseError::UnexpectedChar(start, c)),
+        };
+        chars.next();
+        pos.column += 1;
+        tokens.push((token, start));
+    }
+
+    Ok((tokens, pos))
+}
+
+struct Parser
+{
+    tokens: Vec<(Token, Position)>,
+    next: usize,
+    end_pos: Position,
+}
+
+impl Parser
+{
+    fn peek(&self) -> Option<&Token>
+    {
+        self.tokens.get(self.next).map(|(t, _)| t)
+    }
+
+    fn error(&self, expected: &str) -> ParseError
+    {
+        match self.tokens.get(self.next) {
+            Some((token, pos)) => ParseError::UnexpectedToken(*pos, token.describe(), expected.to_string()),
+            None => ParseError::UnexpectedEnd(self.end_pos, expected.to_string()),
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), ParseError>
+    {
+        if self.peek() == Some(&token) {
+            self.next += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}'", token.describe())))
+        }
+    }
+
+    fn expect_name(&mut self, what: &str) -> Result<String, ParseError>
+    {
+        if let Some(Token::Name(name)) = self.peek() {
+            let name = name.clone();
+            self.next += 1;
+            Ok(name)
+        } else {
+            Err(self.error(what))
+        }
+    }
+
+    fn parse_par(&mut self) -> Result<Process, ParseError>
+    {
+        let term = self.parse_simple()?;
+
+        if self.peek() == Some(&Token::Pipe) {
+            self.next += 1;
+            let rest = self.parse_par()?;
+            return Ok(Process::Par(Box::new(term), Box::new(rest)))
+        }
+
+        Ok(term)
+    }
+
+    // Comma separated items up to the closing token, which is consumed
+    fn parse_list<T>(&mut self, close: Token, mut item: impl FnMut(&mut Self) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError>
+    {
+        let mut items = vec![];
+        if self.peek() == Some(&close) {
+            self.next += 1;
+            return Ok(items)
+        }
+
+        loop {
+            items.push(item(self)?);
+            match self.peek() {
+                Some(Token::Comma) => self.next += 1,
+                Some(t) if *t == close => {
+                    self.next += 1;
+                    return Ok(items)
+                },
+                _ => return Err(self.error(&format!("',' or '{}'", close.describe()))),
+            }
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, ParseError>
+    {
+        let value = match self.peek() {
+            Some(Token::Zero) => Value::Int(0),
+            Some(Token::Int(n)) => Value::Int(*n),
+            Some(Token::Str(s)) => Value::Str(s.clone()),
+            Some(Token::Bool(b)) => Value::Bool(*b),
+            Some(Token::Name(_)) => {
+                let name = self.expect_name("a data variable")?;
+                return Ok(Expr::Var(DataVar(name)))
+            },
+            Some(Token::LParen) => {
+                self.next += 1;
+                if self.peek() == Some(&Token::RParen) {
+                    self.next += 1;
+                    return Ok(Expr::tuple(vec![]))
+                }
+
+                let first = self.parse_expr()?;
+                if self.peek() == Some(&Token::RParen) {
+                    self.next += 1;
+                    return Ok(first)
+                }
+
+                // (E,) is a tuple with a single element
+                self.expect(Token::Comma)?;
+                let mut items = vec![first];
+                items.extend(self.parse_list(Token::RParen, |p| p.parse_expr())?);
+                return Ok(Expr::tuple(items))
+            },
+            _ => return Err(self.error("a value, a data variable or '('")),
+        };
+
+        self.next += 1;
+        Ok(Expr::Val(value))
+    }
+
+    // An expression, with the position of the ':' and the sort if it is annotated
+    fn parse_data_item(&mut self) -> Result<(Expr, Option<(Position, Sort)>), ParseError>
+    {
+        let expr = self.parse_expr()?;
+        match self.tokens.get(self.next) {
+            Some((Token::Colon, pos)) => {
+                let pos = *pos;
+                self.next += 1;
+                Ok((expr, Some((pos, self.parse_sort()?))))
+            },
+            _ => Ok((expr, None)),
+        }
+    }
+
+    fn parse_simple(&mut self) -> Result<Process, ParseError>
+    {
+        match self.peek() {
+            Some(Token::Zero) => {
+                self.next += 1;
+                Ok(Process::End)
+            },
+            Some(Token::Roll) => {
+                self.next += 1;
+                if self.peek() == Some(&Token::At) {
+                    self.next += 1;
+                    let key = self.expect_name("a tag key")?;
+                    Ok(Process::RollK(TagKey(key)))
+                } else {
+                    let var = self.expect_name("a tag variable or '@'")?;
+                    Ok(Process::RollV(TagVar(var)))
+                }
+            },
+            Some(Token::New) => {
+                self.next += 1;
+                let ch_name = self.expect_name("a channel name")?;
+                self.expect(Token::Dot)?;
+                let body = self.parse_simple()?;
+                Ok(Process::New(ChName(ch_name), Box::new(body)))
+            },
+            Some(Token::LParen) => {
+                self.next += 1;
+                let term = self.parse_par()?;
+                self.expect(Token::RParen)?;
+                Ok(term)
+            },
+            Some(Token::Name(_)) => {
+                let name = self.expect_name("a name")?;
+
+                // Data can only come right after a channel name
+                let data_pos = self.tokens.get(self.next).map(|(_, pos)| *pos).unwrap_or(self.end_pos);
+                let data = if self.peek() == Some(&Token::LBracket) {
+                    self.next += 1;
+                    let data = self.parse_list(Token::RBracket, |p| p.parse_data_item())?;
+                    if !matches!(self.peek(), Some(Token::LAngle | Token::LParen)) {
+                        return Err(self.error("'<' or '('"))
+                    }
+                    Some(data)
+                } else {
+                    None
+                };
+
+                match self.peek() {
+                    Some(Token::LAngle) => {
+                        self.next += 1;
+                        let payload = self.parse_par()?;
+                        self.expect(Token::RAngle)?;
+
+                        // Only the data variables of a receive have sorts
+                        let data = data.unwrap_or_default().into_iter().map(|(e, sort)| match sort {
+                            None => Ok(e),
+                            Some((pos, _)) => Err(ParseError::UnexpectedToken(pos, ":".to_string(), "',' or ']'".to_string())),
+                        }).collect::<Result<Vec<_>, _>>()?;
+
+                        Ok(Process::Send(ChName(name), data, Box::new(payload)))
+                    },
+                    // A '(' right after a name is always a receive, a process variable can not be applied
+                    Some(Token::LParen) => {
+                        self.next += 1;
+                        let p_var = self.expect_name("a process variable")?;
+                        self.expect(Token::Comma)?;
+                        let t_var = self.expect_name("a tag variable")?;
+                        self.expect(Token::RParen)?;
+                        self.expect(Token::Dot)?;
+                        let cont = self.parse_simple()?;
+
+                        // A receive binds data variables, the data of a send can be any expression
+                        let d_vars = data.unwrap_or_default().into_iter().map(|(e, sort)| match e {
+                            Expr::Var(var) => Ok(DataBinder(var, sort.map(|(_, sort)| sort))),
+                            e => Err(ParseError::UnexpectedToken(data_pos, e.to_string(), "data variables".to_string())),
+                        }).collect::<Result<Vec<_>, _>>()?;
+
+                        Ok(Process::Recv(ChName(name), d_vars, ProcVar(p_var), TagVar(t_var), Box::new(cont)))
+                    },
+                    _ => Ok(Process::PVar(ProcVar(name))),
+                }
+            },
+            _ => Err(self.error("'0', 'roll', 'new', '(' or a name")),
+        }
+    }
+
+    fn parse_local_type(&mut self) -> Result<LocalType, ParseError>
+    {
+        let name = self.expect_name("'end', 'rec', a recursion variable or a party")?;
+        match name.as_str() {
+            "end" => Ok(LocalType::End),
+            "rec" => {
+                let var = self.expect_name("a recursion variable")?;
+                if var == "end" || var == "rec" {
+                    self.next -= 1;
+                    return Err(self.error("a recursion variable"))
+                }
+                self.expect(Token::Dot)?;
+                let body = self.parse_local_type()?;
+                Ok(LocalType::RAbs(var, Box::new(body)))
+            },
+            _ => {
+                let send = match self.peek() {
+                    Some(Token::Bang) => true,
+                    Some(Token::Query) => false,
+                    _ => return Ok(LocalType::RVar(name)),
+                };
+                self.next += 1;
+
+                if send {
+                    Ok(LocalType::Send(name, self.parse_branches(Self::parse_expr)?))
+                } else {
+                    Ok(LocalType::Recv(name, self.parse_branches(Self::parse_binder)?))
+                }
+            },
+        }
+    }
+
+    // The branches of a send or of a receive, which only differ by the data they carry
+    fn parse_branches<D>(&mut self, item: fn(&mut Self) -> Result<D, ParseError>) -> Result<Vec<(Label, Vec<D>, LocalType)>, ParseError>
+    {
+        if self.peek() == Some(&Token::LBrace) {
+            self.next += 1;
+            self.parse_list(Token::RBrace, |p| p.parse_branch(item))
+        } else {
+            Ok(vec![self.parse_branch(item)?])
+        }
+    }
+
+    fn parse_branch<D>(&mut self, item: fn(&mut Self) -> Result<D, ParseError>) -> Result<(Label, Vec<D>, LocalType), ParseError>
+    {
+        let label = self.expect_name("a label")?;
+        let data = if self.peek() == Some(&Token::LParen) {
+            self.next += 1;
+            self.parse_list(Token::RParen, item)?
+        } else {
+            vec![]
+        };
+        self.expect(Token::Dot)?;
+        let cont = self.parse_local_type()?;
+
+        Ok((label, data, cont))
+    }
+
+    // A data variable of a receive in a local type, with its sort if it is annotated
+    fn parse_binder(&mut self) -> Result<DataBinder, ParseError>
+    {
+        let var = self.expect_name("a data variable")?;
+        let sort = if self.peek() == Some(&Token::Colon) {
+            self.next += 1;
+            Some(self.parse_sort()?)
+        } else {
+            None
+        };
+
+        Ok(DataBinder(DataVar(var), sort))
+    }
+
+    fn parse_sort(&mut self) -> Result<Sort, ParseError>
+    {
+        if self.peek() == Some(&Token::LParen) {
+            self.next += 1;
+            return Ok(Sort::Tuple(self.parse_list(Token::RParen, |p| p.parse_sort())?))
+        }
+
+        let sort = match self.peek() {
+            Some(Token::Name(name)) if name == "int" => Sort::Int,
+            Some(Token::Name(name)) if name == "str" => Sort::Str,
+            Some(Token::Name(name)) if name == "bool" => Sort::Bool,
+            _ => return Err(self.error("'int', 'str', 'bool' or '('")),
+        };
+
+        self.next += 1;
+        Ok(sort)
+    }
+}
+
+// Parse a string into the corresponding roll-pi process
+pub fn parse(input: &str) -> Result<Process, ParseError>
+{
+    let (tokens, end_pos) = tokenize(input)?;
+    let mut parser = Parser { tokens, next: 0, end_pos };
+
+    let term = parser.parse_par()?;
+
+    if parser.peek().is_some() {
+        return Err(parser.error("end of input"))
+    }
+
+    Ok(term)
+}
+
+// Parse a string into the corresponding local type, it is not validated
+pub fn parse_local_type(input: &str) -> Result<LocalType, ParseError>
+{
+    let (tokens, end_pos) = tokenize(input)?;
+    let mut parser = Parser { tokens, next: 0, end_pos };
+
+    let local_type = parser.parse_local_type()?;
+
+    if parser.peek().is_some() {
+        return Err(parser.error("end of input"))
+    }
+
+    Ok(local_type)
+}
@@ -0,0 +1,321 @@
+use std::fmt::Display;
+
+use super::syntax::{ChName, DataBinder, DataVar, Expr, PrimeState, PrimProcess, Process, ProcTag, ProcVar, Sort, TagKey, TaggedPrimProc, TaggedProc, TagVar, Value};
+
+// Prints a process in the concrete syntax accepted by rollpi::parser,
+// so that parse(&print_process(p)) gives back p
+pub fn print_process(proc: &Process) -> String
+{
+    match proc {
+        Process::End => "0".to_string(),
+        Process::PVar(ProcVar(p_var)) => p_var.clone(),
+        Process::Par(a, b) => {
+            // Parallel composition is right associative, only a left nested one needs parenthesis
+            let left = match **a {
+                Process::Par(_, _) => format!("({})", print_process(a)),
+                _ => print_process(a),
+            };
+            format!("{} | {}", left, print_process(b))
+        },
+        Process::Send(ChName(ch_name), data, p) =>
+            format!("{}{}<{}>", ch_name, print_data(data), print_process(p)),
+        Process::Recv(ChName(ch_name), d_vars, ProcVar(p_var), TagVar(t_var), p) =>
+            format!("{}{}({}, {}).{}", ch_name, print_data(d_vars), p_var, t_var, print_prefixed(p)),
+        Process::RollV(TagVar(t_var)) =>
+            format!("roll {}", t_var),
+        Process::RollK(TagKey(t_key)) =>
+            format!("roll @{}", t_key),
+        Process::New(ChName(ch_name), p) =>
+            format!("new {}.{}", ch_name, print_prefixed(p)),
+    }
+}
+
+// The values or data variables of a communication, nothing when there are none
+fn print_data<T: Display>(data: &[T]) -> String
+{
+    if data.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]", data.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", "))
+    }
+}
+
+// The continuation of a prefix binds tighter than parallel composition
+fn print_prefixed(proc: &Process) -> String
+{
+    match proc {
+        Process::Par(_, _) => format!("({})", print_process(proc)),
+        _ => print_process(proc),
+    }
+}
+
+// Splits a parallel composition into the pieces printed on separate lines,
+// left nested compositions stay together as one parenthesised piece
+fn par_pieces(proc: &Process) -> Vec<&Process>
+{
+    match proc {
+        Process::Par(a, b) => {
+            let mut pieces = vec![&**a];
+            pieces.extend(par_pieces(b));
+            pieces
+        },
+        _ => vec![proc],
+    }
+}
+
+// Layout options for printing terms that do not fit on a single line
+#[derive(Debug, Clone, Copy)]
+pub struct PrettyConfig
+{
+    // Maximum number of characters on a line before a term gets broken up
+    pub width: usize,
+    // Number of spaces added for every nesting level of a broken term
+    pub indent: usize,
+}
+
+impl Default for PrettyConfig
+{
+    fn default() -> Self
+    {
+        PrettyConfig { width: 80, indent: 2 }
+    }
+}
+
+impl PrettyConfig
+{
+    // The result is still accepted by rollpi::parser, line breaks are only whitespace
+    pub fn process(&self, proc: &Process) -> String
+    {
+        self.layout(proc, 0)
+    }
+
+    pub fn prim_process(&self, proc: &PrimProcess) -> String
+    {
+        self.process(&proc.clone().to_process())
+    }
+
+    pub fn tagged(&self, tag: &ProcTag, proc: &Process) -> String
+    {
+        self.tagged_at(tag, proc, 0)
+    }
+
+    // One tagged prime process on every line
+    pub fn state(&self, state: &PrimeState) -> String
+    {
+        state.iter()
+            .map(|TaggedPrimProc { tag, proc }| self.tagged(tag, &proc.clone().to_process()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Memory [μ;k] of a communication between the given sender and receiver that created the tag k
+    pub fn memory(&self, sender: &TaggedProc, receiver: &TaggedProc, key: &TagKey) -> String
+    {
+        let flat = format!("[{}: {} | {}: {}; {}]",
+            sender.tag, print_process(&sender.proc), receiver.tag, print_process(&receiver.proc), key.0);
+        if flat.chars().count() <= self.width {
+            return flat
+        }
+
+        let pad = " ".repeat(self.indent);
+        format!("[\n{}{}\n{}| {}\n; {}]",
+            pad, self.tagged_at(&sender.tag, &sender.proc, self.indent),
+            pad, self.tagged_at(&receiver.tag, &receiver.proc, self.indent + 2),
+            key.0)
+    }
+
+    fn tagged_at(&self, tag: &ProcTag, proc: &Process, col: usize) -> String
+    {
+        let prefix = format!("{}: ", tag);
+        format!("{}{}", prefix, self.layout_piece(proc, col + prefix.chars().count()))
+    }
+
+    // Lays out a process starting at the given column, so that continuation lines line up with it
+    fn layout(&self, proc: &Process, col: usize) -> String
+    {
+        let flat = print_process(proc);
+        if col + flat.chars().count() <= self.width {
+            return flat
+        }
+
+        let pad = " ".repeat(col);
+        let pad_in = " ".repeat(col + self.indent);
+
+        match proc {
+            Process::Par(_, _) => {
+                par_pieces(proc).into_iter().enumerate().map(|(i, piece)| {
+                    if i == 0 {
+                        self.layout_piece(piece, col)
+                    } else {
+                        format!("\n{}| {}", pad, self.layout_piece(piece, col + 2))
+                    }
+                }).collect()
+            },
+            Process::Send(ChName(ch_name), data, p) =>
+                format!("{}{}<\n{}{}\n{}>", ch_name, print_data(data), pad_in, self.layout(p, col + self.indent), pad),
+            Process::Recv(ChName(ch_name), d_vars, ProcVar(p_var), TagVar(t_var), p) => {
+                let head = format!("{}{}({}, {}).", ch_name, print_data(d_vars), p_var, t_var);
+                match **p {
+                    Process::Par(_, _) =>
+                        format!("{}(\n{}{}\n{})", head, pad_in, self.layout(p, col + self.indent), pad),
+                    _ =>
+                        format!("{}\n{}{}", head, pad_in, self.layout(p, col + self.indent)),
+                }
+            },
+            Process::New(ChName(ch_name), p) => {
+                let head = format!("new {}.", ch_name);
+                match **p {
+                    Process::Par(_, _) =>
+                        format!("{}(\n{}{}\n{})", head, pad_in, self.layout(p, col + self.indent), pad),
+                    _ =>
+                        format!("{}\n{}{}", head, pad_in, self.layout(p, col + self.indent)),
+                }
+            },
+            _ => flat,
+        }
+    }
+
+    // A piece of a parallel composition, parenthesised if it is a composition itself
+    fn layout_piece(&self, proc: &Process, col: usize) -> String
+    {
+        match proc {
+            Process::Par(_, _) => format!("({})", self.layout(proc, col + 1)),
+            _ => self.layout(proc, col),
+        }
+    }
+
+    // The formatter width, as in {:120}, overrides the line width
+    // and the alternate flag, as in {:#}, prints everything on one line
+    fn from_formatter(f: &std::fmt::Formatter<'_>) -> Self
+    {
+        let default = PrettyConfig::default();
+        PrettyConfig {
+            width: if f.alternate() { usize::MAX } else { f.width().unwrap_or(default.width) },
+            indent: default.indent,
+        }
+    }
+}
+
+// Wrapper for displaying a PrimeState, since it is only an alias for a vector
+pub struct StateFmt<'a>(pub &'a PrimeState);
+
+impl Display for Process
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", PrettyConfig::from_formatter(f).process(self))
+    }
+}
+
+impl Display for PrimProcess
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", PrettyConfig::from_formatter(f).prim_process(self))
+    }
+}
+
+// A tuple with one element keeps a trailing comma, so it is not read back as a parenthesised value
+fn print_tuple<T: Display>(items: &[T]) -> String
+{
+    let items = items.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+    if items.len() == 1 {
+        format!("({},)", items[0])
+    } else {
+        format!("({})", items.join(", "))
+    }
+}
+
+// Strings are quoted, with '"' and '\\' escaped, so that they can be parsed back
+impl Display for Value
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Str(s) => write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+            Value::Tuple(vs) => write!(f, "{}", print_tuple(vs)),
+        }
+    }
+}
+
+impl Display for Expr
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            Expr::Val(v) => write!(f, "{}", v),
+            Expr::Var(var) => write!(f, "{}", var),
+            Expr::Tuple(es) => write!(f, "{}", print_tuple(es)),
+        }
+    }
+}
+
+impl Display for DataVar
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Display for DataBinder
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match &self.1 {
+            Some(sort) => write!(f, "{}: {}", self.0, sort),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+// Unlike values, a tuple sort with a single element has no trailing ','
+impl Display for Sort
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            Sort::Int => write!(f, "int"),
+            Sort::Str => write!(f, "str"),
+            Sort::Bool => write!(f, "bool"),
+            Sort::Tuple(sorts) => write!(f, "({})", sorts.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(", ")),
+        }
+    }
+}
+
+impl Display for ProcTag
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            ProcTag::PTKey(TagKey(key)) => write!(f, "{}", key),
+            ProcTag::PTSplit(TagKey(frag), _, _) => write!(f, "{}", frag),
+        }
+    }
+}
+
+impl Display for TaggedPrimProc
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", PrettyConfig::from_formatter(f).tagged(&self.tag, &self.proc.clone().to_process()))
+    }
+}
+
+impl Display for TaggedProc
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", PrettyConfig::from_formatter(f).tagged(&self.tag, &self.proc))
+    }
+}
+
+impl Display for StateFmt<'_>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", PrettyConfig::from_formatter(f).state(self.0))
+    }
+}
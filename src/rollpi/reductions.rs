@@ -1,6 +1,6 @@
 // trait 
 
-use super::syntax::{Process, ProcVar, TagVar, TagKey};
+use super::syntax::{all_chn_names_proc, free_chn_names_proc, ChName, DataBinder, DataVar, Process, ProcVar, TagVar, TagKey, Value};
 
 impl Process
 {
@@ -9,6 +9,32 @@ impl Process
         self.__rec_subst(p_var, in_process, true, t_var, new_tag, true)
     }
 
+    // Replaces the data variables by the received values, a receive binding the same variable shadows it
+    pub fn substitute_data(self: Self, binds: &[(DataVar, Value)]) -> Process
+    {
+        if binds.is_empty() {
+            return self;
+        }
+
+        match self {
+            Process::End | Process::PVar(_) | Process::RollK(_) | Process::RollV(_) => self,
+            Process::Par(a, b) =>
+                Process::Par(Box::new(a.substitute_data(binds)), Box::new(b.substitute_data(binds))),
+            Process::Send(ch_name, data, p) => {
+                let data = data.into_iter().map(|e| e.substitute(binds)).collect();
+                Process::Send(ch_name, data, Box::new(p.substitute_data(binds)))
+            },
+            Process::Recv(ch_name, d_vars, p_var, t_var, p) => {
+                let inner_binds = binds.iter()
+                    .filter(|(v, _)| !d_vars.iter().any(|DataBinder(var, _)| var == v))
+                    .cloned().collect::<Vec<_>>();
+                Process::Recv(ch_name, d_vars, p_var, t_var, Box::new(p.substitute_data(&inner_binds)))
+            },
+            Process::New(ch_name, p) =>
+                Process::New(ch_name, Box::new(p.substitute_data(binds))),
+        }
+    }
+
     fn __rec_subst(self: Self, p_var: ProcVar, in_process: &Process, mut ok_proc: bool, t_var: TagVar, new_tag: &TagKey, mut ok_tag: bool) -> Process
     {
         if !ok_proc && !ok_tag {
@@ -24,12 +50,12 @@ impl Process
                 Process::Par(Box::new(conv_a), Box::new(conv_b))
             },
             
-            Process::Send(ch_name, p) => {
+            Process::Send(ch_name, data, p) => {
                 let conv_p = p.__rec_subst(p_var, in_process, ok_proc, t_var, new_tag, ok_tag);
-                Process::Send(ch_name, Box::new(conv_p))
+                Process::Send(ch_name, data, Box::new(conv_p))
             },
             
-            Process::Recv(_ch_name, new_p_var, new_t_var, new_next_proc) => {
+            Process::Recv(_ch_name, d_vars, new_p_var, new_t_var, new_next_proc) => {
                 if new_p_var == p_var {
                     ok_proc = false;
                 }
@@ -37,7 +63,7 @@ impl Process
                     ok_tag = false;
                 }
                 let conv_next_proc = new_next_proc.__rec_subst(p_var, in_process, ok_proc, t_var, new_tag, ok_tag);
-                Process::Recv(_ch_name, new_p_var, new_t_var, Box::new(conv_next_proc))
+                Process::Recv(_ch_name, d_vars, new_p_var, new_t_var, Box::new(conv_next_proc))
             },
             
             Process::PVar(var) => {
@@ -58,6 +84,30 @@ impl Process
                     Process::RollV(roll_var)
                 }
             },
+            Process::New(ch_name, p) => {
+                // The substituted process must not get its channels captured by the restriction
+                let (ch_name, p) = if ok_proc && free_chn_names_proc(in_process).contains(&ch_name.0) {
+                    let new_name = fresh_chn_name(&ch_name, in_process, &p);
+                    let renamed = p.rename_channel(&ch_name, &new_name);
+                    (new_name, Box::new(renamed))
+                } else {
+                    (ch_name, p)
+                };
+                let conv_p = p.__rec_subst(p_var, in_process, ok_proc, t_var, new_tag, ok_tag);
+                Process::New(ch_name, Box::new(conv_p))
+            },
         }
     }
 }
+
+// A variant of the channel name that appears neither in the substituted process nor in the body of the restriction
+fn fresh_chn_name(ChName(ch_name): &ChName, in_process: &Process, body: &Process) -> ChName
+{
+    let mut used = free_chn_names_proc(in_process);
+    used.extend(all_chn_names_proc(body));
+
+    (0..).map(|i| format!("{}_{}", ch_name, i))
+        .find(|name| !used.contains(name))
+        .map(ChName)
+        .unwrap()
+}
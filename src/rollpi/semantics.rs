@@ -0,0 +1,369 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use super::errors::SemanticsError;
+use super::printer::PrettyConfig;
+use super::syntax::{all_chn_names_proc, all_tag_keys_proc, all_tag_keys_state, binders_accept, ChName, DataBinder, PrimeState, PrimProcess, Process, ProcTag, TagKey, TaggedPrimProc, TaggedProc};
+
+// Single threaded reference semantics of roll-pi
+// A configuration is the parallel composition of tagged prime processes k: P and memories [μ;k],
+//     it follows the same conventions for tags as the threaded runtime in rollpi::environment
+
+// A prime process of the configuration, together with the participant running it
+#[derive(Debug, Clone)]
+pub struct ConfProc
+{
+    pub owner: String,
+    pub tagged: TaggedPrimProc,
+}
+
+// The memory [μ;k] of a communication, μ being the sender and the receiver before the communication
+#[derive(Debug, Clone)]
+pub struct Memory
+{
+    // ids of sender, receiver
+    pub ids: (String, String),
+    pub sender: TaggedProc,
+    pub receiver: TaggedProc,
+    pub key: TagKey,
+}
+
+#[derive(Debug, Clone)]
+pub struct Configuration
+{
+    pub procs: Vec<ConfProc>,
+    pub memories: Vec<Memory>,
+    // Counter for creating fresh tag keys, the ones already in the configuration are skipped
+    next_key: usize,
+    // Counter for creating fresh channels when a restriction reaches the top level,
+    //     the names already in the configuration are skipped
+    next_ch: usize,
+    // Every tag key and channel created by the steps so far, even the ones rolled back since
+    created_keys: HashSet<TagKey>,
+    created_chs: HashSet<String>,
+}
+
+// A reduction that can be taken from a configuration, identified by positions in Configuration::procs
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Step
+{
+    // (position of the send, position of the receive) on the same channel
+    Com(usize, usize),
+    // Position of the roll k process, the memory [μ;k] being part of the configuration
+    Roll(usize),
+}
+
+// What happened when a step was taken
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transition
+{
+    Com { channel: ChName, sender: String, receiver: String, key: TagKey },
+    Roll { owner: String, key: TagKey },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunResult
+{
+    // No step is enabled anymore
+    Quiescent(Vec<Transition>),
+    // The step bound was reached while steps were still enabled
+    StepLimit(Vec<Transition>),
+}
+
+// The tags and memory keys that get frozen when rolling back a memory
+#[derive(Debug, Clone, Default)]
+pub struct FrozenSet
+{
+    pub keys: HashSet<TagKey>,
+}
+
+impl FrozenSet
+{
+    // A tag is frozen if it was created by a frozen memory, or is a fragment of such a tag
+    pub fn contains_tag(&self, tag: &ProcTag) -> bool
+    {
+        match tag {
+            ProcTag::PTKey(key) => self.keys.contains(key),
+            ProcTag::PTSplit(frag_key, _, og_key) => self.keys.contains(og_key) || self.keys.contains(frag_key),
+        }
+    }
+}
+
+impl Configuration
+{
+    // Each participant starts with its process tagged by its id, same as for the threaded runtime
+    pub fn new(conf: Vec<(String, Process)>) -> Self
+    {
+        let procs = conf.into_iter().flat_map(|(id, p)| {
+            let state = p.to_tagged_process(ProcTag::PTKey(TagKey(id.clone()))).to_prime_state();
+            state.into_iter().map(move |tagged| ConfProc { owner: id.clone(), tagged })
+        }).collect();
+
+        Configuration { procs, memories: vec![], next_key: 0, next_ch: 0, created_keys: HashSet::new(), created_chs: HashSet::new() }
+    }
+
+    // Whether the tag key was created by a communication, and not given by the initial configuration
+    pub fn is_created_key(&self, key: &TagKey) -> bool
+    {
+        self.created_keys.contains(key)
+    }
+
+    // Whether the channel was created when a restriction reached the top level
+    pub fn is_created_channel(&self, ChName(ch_name): &ChName) -> bool
+    {
+        self.created_chs.contains(ch_name)
+    }
+
+    // The prime processes currently run by the given participant
+    pub fn state_of(&self, owner: &str) -> PrimeState
+    {
+        self.procs.iter()
+            .filter(|p| p.owner == owner)
+            .map(|p| p.tagged.clone())
+            .collect()
+    }
+
+    pub fn memory(&self, key: &TagKey) -> Option<&Memory>
+    {
+        self.memories.iter().find(|m| &m.key == key)
+    }
+
+    // The channel names of the processes and of the memories, the restricted ones included
+    fn channels_in_use(&self) -> HashSet<String>
+    {
+        let procs = self.procs.iter().map(|p| p.tagged.proc.clone().to_process());
+        let memories = self.memories.iter().flat_map(|m| [m.sender.proc.clone(), m.receiver.proc.clone()]);
+        procs.chain(memories).flat_map(|p| all_chn_names_proc(&p)).collect()
+    }
+
+    // The tag keys of the processes and of the memories
+    fn keys_in_use(&self) -> HashSet<TagKey>
+    {
+        let mut keys = all_tag_keys_state(&self.procs.iter().map(|p| p.tagged.clone()).collect());
+        for m in &self.memories {
+            keys.insert(m.key.clone());
+            for TaggedProc { tag, proc } in [&m.sender, &m.receiver] {
+                keys.extend(tag.keys().into_iter().cloned());
+                keys.extend(all_tag_keys_proc(proc));
+            }
+        }
+        keys
+    }
+
+    // Every step that can be taken, communications first, in the order of the processes
+    // A send and a receive communicate when they are on the same channel, the data of the send has no variables left
+    //     and its values fit the data variables: as many of them, each of the sort of its variable if it has one
+    pub fn enabled_steps(&self) -> Vec<Step>
+    {
+        let mut steps = vec![];
+
+        for (i, snd) in self.procs.iter().enumerate() {
+            if let PrimProcess::Send(ChName(snd_ch), data, _) = &snd.tagged.proc {
+                for (j, rcv) in self.procs.iter().enumerate() {
+                    if let PrimProcess::Recv(ChName(rcv_ch), d_vars, _, _, _) = &rcv.tagged.proc {
+                        let values = data.iter().map(|e| e.eval()).collect::<Option<Vec<_>>>();
+                        if snd_ch == rcv_ch && values.is_some_and(|values| binders_accept(d_vars, &values)) {
+                            steps.push(Step::Com(i, j));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (i, p) in self.procs.iter().enumerate() {
+            if let PrimProcess::RollK(key) = &p.tagged.proc {
+                if self.memory(key).is_some() {
+                    steps.push(Step::Roll(i));
+                }
+            }
+        }
+
+        steps
+    }
+
+    pub fn is_quiescent(&self) -> bool
+    {
+        self.enabled_steps().is_empty()
+    }
+
+    // Causal descendants of the memory with the given key: the memories whose sender or receiver
+    //     has a frozen tag get frozen in turn, until nothing changes
+    pub fn freeze(&self, key: &TagKey) -> FrozenSet
+    {
+        let mut frozen = FrozenSet::default();
+        frozen.keys.insert(key.clone());
+
+        loop {
+            let new_keys = self.memories.iter()
+                .filter(|m| !frozen.keys.contains(&m.key))
+                .filter(|m| frozen.contains_tag(&m.sender.tag) || frozen.contains_tag(&m.receiver.tag))
+                .map(|m| m.key.clone())
+                .collect::<Vec<_>>();
+
+            if new_keys.is_empty() {
+                return frozen
+            }
+            frozen.keys.extend(new_keys);
+        }
+    }
+
+    pub fn step(&mut self, step: &Step) -> Result<Transition, SemanticsError>
+    {
+        match *step {
+            Step::Com(snd_pos, rcv_pos) => self.communicate(snd_pos, rcv_pos),
+            Step::Roll(pos) => self.roll(pos),
+        }
+    }
+
+    // k1: a[v]<P> | k2: a[x](X, γ).Q  ->  k: Q{v/x, P/X, k/γ} | [k1: a[v]<P> | k2: a[x](X, γ).Q; k]
+    fn communicate(&mut self, snd_pos: usize, rcv_pos: usize) -> Result<Transition, SemanticsError>
+    {
+        let (snd, rcv) = match (self.procs.get(snd_pos), self.procs.get(rcv_pos)) {
+            (Some(snd), Some(rcv)) => (snd.clone(), rcv.clone()),
+            _ => return Err(SemanticsError::NoCommunication(snd_pos, rcv_pos)),
+        };
+
+        let (ch_name, data, payload, d_vars, p_var, t_var, next_proc) = match (snd.tagged.proc, rcv.tagged.proc) {
+            (PrimProcess::Send(snd_ch, data, payload), PrimProcess::Recv(rcv_ch, d_vars, p_var, t_var, next_proc))
+                if snd_ch == rcv_ch =>
+                (snd_ch, data, payload, d_vars, p_var, t_var, next_proc),
+            _ => return Err(SemanticsError::NoCommunication(snd_pos, rcv_pos)),
+        };
+        // The data of a closed configuration has no variables left when it is sent
+        let values = match data.iter().map(|e| e.eval()).collect::<Option<Vec<_>>>() {
+            Some(values) if binders_accept(&d_vars, &values) => values,
+            _ => return Err(SemanticsError::NoCommunication(snd_pos, rcv_pos)),
+        };
+        let binds = d_vars.iter().map(|DataBinder(var, _)| var.clone()).zip(values).collect::<Vec<_>>();
+
+        // The parser takes names starting with '_' as well, so the fresh names skip the ones in use
+        let keys_in_use = self.keys_in_use();
+        let key = loop {
+            let key = TagKey(format!("_tag_{}", self.next_key));
+            self.next_key += 1;
+            if !keys_in_use.contains(&key) {
+                break key
+            }
+        };
+        self.created_keys.insert(key.clone());
+
+        let chs_in_use = self.channels_in_use();
+        let next_ch = &mut self.next_ch;
+        let created_chs = &mut self.created_chs;
+        let new_state = next_proc.clone()
+            .substitute_data(&binds)
+            .substitution_on_trigger(p_var.clone(), &payload, t_var.clone(), &key)
+            .to_tagged_process(ProcTag::PTKey(key.clone()))
+            .to_prime_state_with(&mut |ChName(base)| loop {
+                let ch_name = format!("_ch_{}_{}", *next_ch, base);
+                *next_ch += 1;
+                if !chs_in_use.contains(&ch_name) {
+                    created_chs.insert(ch_name.clone());
+                    break ChName(ch_name)
+                }
+            });
+
+        self.memories.push(Memory {
+            ids: (snd.owner.clone(), rcv.owner.clone()),
+            sender: TaggedProc { tag: snd.tagged.tag, proc: Process::Send(ch_name.clone(), data, Box::new(payload)) },
+            receiver: TaggedProc { tag: rcv.tagged.tag, proc: Process::Recv(ch_name.clone(), d_vars, p_var, t_var, Box::new(next_proc)) },
+            key: key.clone(),
+        });
+
+        // Remove the higher position first so the other one stays valid
+        self.procs.remove(snd_pos.max(rcv_pos));
+        self.procs.remove(snd_pos.min(rcv_pos));
+        self.procs.extend(new_state.into_iter().map(|tagged| ConfProc { owner: rcv.owner.clone(), tagged }));
+
+        Ok(Transition::Com { channel: ch_name, sender: snd.owner, receiver: rcv.owner, key })
+    }
+
+    // [μ;k] | roll k | N  ->  μ | N♮k
+    // Everything frozen by k disappears, and the parts of frozen memories that are not frozen themselves
+    //     are put back, which restores μ and the resources consumed by the descendants of k
+    fn roll(&mut self, pos: usize) -> Result<Transition, SemanticsError>
+    {
+        let (owner, key) = match self.procs.get(pos) {
+            Some(ConfProc { owner, tagged: TaggedPrimProc { proc: PrimProcess::RollK(key), .. } }) if self.memory(key).is_some() =>
+                (owner.clone(), key.clone()),
+            _ => return Err(SemanticsError::NoRoll(pos)),
+        };
+
+        let frozen = self.freeze(&key);
+
+        self.procs.remove(pos);
+        self.procs.retain(|p| !frozen.contains_tag(&p.tagged.tag));
+
+        let (rolled, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.memories)
+            .into_iter()
+            .partition(|m| frozen.keys.contains(&m.key));
+        self.memories = kept;
+
+        for Memory { ids: (snd_id, rcv_id), sender, receiver, .. } in rolled {
+            for (id, tagged) in [(snd_id, sender), (rcv_id, receiver)] {
+                if !frozen.contains_tag(&tagged.tag) {
+                    self.procs.push(ConfProc {
+                        owner: id,
+                        tagged: TaggedPrimProc { proc: tagged.proc.to_prime_process(), tag: tagged.tag },
+                    });
+                }
+            }
+        }
+
+        Ok(Transition::Roll { owner, key })
+    }
+
+    // Takes the first enabled step until none is left or the bound is reached
+    pub fn run_to_quiescence(&mut self, max_steps: usize) -> RunResult
+    {
+        self.run_to_quiescence_with(max_steps, |_| 0)
+    }
+
+    // Same as run_to_quiescence, the picker chooses the position of the next step among the enabled ones
+    pub fn run_to_quiescence_with(&mut self, max_steps: usize, mut pick: impl FnMut(&[Step]) -> usize) -> RunResult
+    {
+        let mut trace = vec![];
+
+        loop {
+            let steps = self.enabled_steps();
+            if steps.is_empty() {
+                return RunResult::Quiescent(trace)
+            }
+            if trace.len() >= max_steps {
+                return RunResult::StepLimit(trace)
+            }
+
+            let chosen = &steps[pick(&steps).min(steps.len() - 1)];
+            // Only enabled steps are taken, so this can not fail
+            trace.push(self.step(chosen).unwrap());
+        }
+    }
+}
+
+impl Display for Transition
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            Transition::Com { channel: ChName(ch_name), sender, receiver, key: TagKey(key) } =>
+                write!(f, "{} -> {} on {} creating {}", sender, receiver, ch_name, key),
+            Transition::Roll { owner, key: TagKey(key) } =>
+                write!(f, "{} rolls back {}", owner, key),
+        }
+    }
+}
+
+impl Display for Configuration
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        let cfg = PrettyConfig::default();
+        for ConfProc { owner, tagged } in &self.procs {
+            writeln!(f, "{} > {}", owner, cfg.tagged(&tagged.tag, &tagged.proc.clone().to_process()))?;
+        }
+        for Memory { sender, receiver, key, .. } in &self.memories {
+            writeln!(f, "{}", cfg.memory(sender, receiver, key))?;
+        }
+        Ok(())
+    }
+}
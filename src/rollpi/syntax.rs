@@ -13,6 +13,46 @@ pub struct TagVar(pub String);
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TagKey(pub String);
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DataVar(pub String);
+
+// A data variable bound by a receive, with a sort it only takes values of the sort
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataBinder(pub DataVar, pub Option<Sort>);
+
+// First order values that can be sent alongside a process
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value
+{
+    Int(i64),
+    Str(String),
+    Bool(bool),
+    Tuple(Vec<Value>),
+}
+
+// Sort of a value, every value has exactly one
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Sort
+{
+    Int,
+    Str,
+    Bool,
+    Tuple(Vec<Sort>),
+}
+
+// The data part of a send, its variables are bound by the receives before it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr
+{
+    Val(Value),
+    Var(DataVar),
+    Tuple(Vec<Expr>),
+}
+
+// One instance of a protocol, the unnamed default session leaves channels and ids as they are
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct SessionId(pub String);
+
 pub type PrimeState = Vec<TaggedPrimProc>;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -31,10 +71,14 @@ pub enum Process
     End,
     PVar(ProcVar),
     Par(Box<Process>, Box<Process>),
-    Send(ChName, Box<Process>),
-    Recv(ChName, ProcVar, TagVar, Box<Process>),
+    // Send on the channel the values of the expressions together with the process
+    Send(ChName, Vec<Expr>, Box<Process>),
+    // Receive the values into the data variables and the process into the process variable
+    Recv(ChName, Vec<DataBinder>, ProcVar, TagVar, Box<Process>),
     RollV(TagVar),
     RollK(TagKey),
+    // Restriction (new a) P: the channel is private to P until it is sent away (scope extrusion)
+    New(ChName, Box<Process>),
 }
 
 #[derive(Debug, Clone)]
@@ -50,8 +94,8 @@ pub enum PrimProcess
 {
     End,
     RollK(TagKey),
-    Send(ChName, Process),
-    Recv(ChName, ProcVar, TagVar, Process),
+    Send(ChName, Vec<Expr>, Process),
+    Recv(ChName, Vec<DataBinder>, ProcVar, TagVar, Process),
 }
 
 #[derive(Debug, Clone)]
@@ -70,13 +114,14 @@ impl Process
             Process::Par(_, _) => panic!("Process to prime process conversion: Par not allowed"),
             Process::End => 
                 PrimProcess::End,
-            Process::Send(chn, p) => 
-                PrimProcess::Send(chn.clone(), *p.clone()),
-            Process::Recv(chn, pv, tv, p) => 
-                PrimProcess::Recv(chn.clone(), pv.clone(), tv.clone(), *p.clone()),
+            Process::Send(chn, data, p) => 
+                PrimProcess::Send(chn.clone(), data.clone(), *p.clone()),
+            Process::Recv(chn, d_vars, pv, tv, p) => 
+                PrimProcess::Recv(chn.clone(), d_vars.clone(), pv.clone(), tv.clone(), *p.clone()),
             Process::RollV(_) => panic!("Process to prime process conversion: RollV not allowed, only RollK(ey) allowed"),
             Process::RollK(roll_key) => 
                 PrimProcess::RollK(roll_key.clone()),
+            Process::New(_, _) => panic!("Process to prime process conversion: New not allowed, it is resolved when splitting"),
         }
     }
 
@@ -97,15 +142,42 @@ impl Process
         procs.unwrap_or(Process::End)
     }
 
-    fn get_first_order_par_processes(self: Self) -> Vec<Process>
+    // Top level restrictions are opened by renaming their channel to the one given by fresh
+    fn get_first_order_par_processes(self: Self, fresh: &mut dyn FnMut(&ChName) -> ChName) -> Vec<Process>
     {
-        if let Process::Par(a, b) = self {
-            let mut vec_a = a.get_first_order_par_processes();
-            let mut vec_b = b.get_first_order_par_processes();
-            vec_a.append(&mut vec_b);
-            vec_a
-        } else {
-            vec![self]
+        match self {
+            Process::Par(a, b) => {
+                let mut vec_a = a.get_first_order_par_processes(fresh);
+                let mut vec_b = b.get_first_order_par_processes(fresh);
+                vec_a.append(&mut vec_b);
+                vec_a
+            },
+            Process::New(ch_name, p) => {
+                let new_name = fresh(&ch_name);
+                p.rename_channel(&ch_name, &new_name).get_first_order_par_processes(fresh)
+            },
+            _ => vec![self],
+        }
+    }
+
+    // Renames the free occurences of a channel, the new name is expected not to be bound in the process
+    pub fn rename_channel(self: Self, from: &ChName, to: &ChName) -> Process
+    {
+        let rename = |ch_name: ChName| if &ch_name == from { to.clone() } else { ch_name };
+
+        match self {
+            Process::End | Process::PVar(_) | Process::RollV(_) | Process::RollK(_) => self,
+            Process::Par(a, b) =>
+                Process::Par(Box::new(a.rename_channel(from, to)), Box::new(b.rename_channel(from, to))),
+            Process::Send(ch_name, data, p) =>
+                Process::Send(rename(ch_name), data, Box::new(p.rename_channel(from, to))),
+            Process::Recv(ch_name, d_vars, p_var, t_var, p) =>
+                Process::Recv(rename(ch_name), d_vars, p_var, t_var, Box::new(p.rename_channel(from, to))),
+            // The restriction shadows the channel
+            Process::New(ch_name, p) if &ch_name == from =>
+                Process::New(ch_name, p),
+            Process::New(ch_name, p) =>
+                Process::New(ch_name, Box::new(p.rename_channel(from, to))),
         }
     }
 }
@@ -119,17 +191,25 @@ impl PrimProcess
                 Process::End,
             PrimProcess::RollK(tag) => 
                 Process::RollK(tag),
-            PrimProcess::Send(ch_name, proc) => 
-                Process::Send(ch_name, Box::new(proc)),
-            PrimProcess::Recv(ch_name, p_var, t_var, proc) => 
-                Process::Recv(ch_name, p_var, t_var, Box::new(proc)),
+            PrimProcess::Send(ch_name, data, proc) => 
+                Process::Send(ch_name, data, Box::new(proc)),
+            PrimProcess::Recv(ch_name, d_vars, p_var, t_var, proc) => 
+                Process::Recv(ch_name, d_vars, p_var, t_var, Box::new(proc)),
         }
     }
 }
 
 impl TaggedProc
 {
+    // Top level restrictions keep their name, which is only sound when it is not used anywhere else,
+    //     as ensured by check_initial_conf for initial configurations
     pub fn to_prime_state(self: Self) -> PrimeState
+    {
+        self.to_prime_state_with(&mut |ch_name| ch_name.clone())
+    }
+
+    // Same as to_prime_state, top level restrictions get the channel name returned by fresh
+    pub fn to_prime_state_with(self: Self, fresh: &mut dyn FnMut(&ChName) -> ChName) -> PrimeState
     {
         let Self { tag, proc } = self; 
         let og_key = match tag {
@@ -137,7 +217,7 @@ impl TaggedProc
             ProcTag::PTSplit(t_key, _cnt, _og_t) => t_key,
         };
 
-        let vec_procs = proc.get_first_order_par_processes();
+        let vec_procs = proc.get_first_order_par_processes(fresh);
         let total_cnt_procs = vec_procs.len();
 
         vec_procs.iter().enumerate().map(|(i, p)| {
@@ -162,18 +242,47 @@ pub fn all_chn_names_proc(proc: &Process) -> HashSet<String>
             // combine iterators with chain
             iter_left.chain(iter_righ).collect()
         }
-        Process::Send(ChName(ch_name), proc) => {
+        Process::Send(ChName(ch_name), _, proc) => {
             let mut set = all_chn_names_proc(proc);
             set.insert(ch_name.clone());
             set
         },
-        Process::Recv(ChName(ch_name), _, _, proc) => {
+        Process::Recv(ChName(ch_name), _, _, _, proc) => {
             let mut set = all_chn_names_proc(proc);
             set.insert(ch_name.clone());
             set
         },
         Process::RollV(_) => HashSet::new(),
         Process::RollK(_) => HashSet::new(),
+        Process::New(ChName(ch_name), proc) => {
+            let mut set = all_chn_names_proc(proc);
+            set.insert(ch_name.clone());
+            set
+        },
+    }
+}
+
+// The channel names that are not bound by a restriction
+pub fn free_chn_names_proc(proc: &Process) -> HashSet<String>
+{
+    match proc 
+    {
+        Process::End | Process::PVar(_) | Process::RollV(_) | Process::RollK(_) => HashSet::new(),
+        Process::Par(p_left, p_right) => {
+            let mut set = free_chn_names_proc(p_left);
+            set.extend(free_chn_names_proc(p_right));
+            set
+        },
+        Process::Send(ChName(ch_name), _, proc) | Process::Recv(ChName(ch_name), _, _, _, proc) => {
+            let mut set = free_chn_names_proc(proc);
+            set.insert(ch_name.clone());
+            set
+        },
+        Process::New(ChName(ch_name), proc) => {
+            let mut set = free_chn_names_proc(proc);
+            set.remove(ch_name);
+            set
+        },
     }
 }
 
@@ -184,6 +293,44 @@ pub fn all_chn_names_state(proc: &PrimeState) -> HashSet<String>
     }).flatten().collect()
 }
 
+// The tag keys rolled back to by the process
+pub fn all_tag_keys_proc(proc: &Process) -> HashSet<TagKey>
+{
+    match proc
+    {
+        Process::End | Process::PVar(_) | Process::RollV(_) => HashSet::new(),
+        Process::RollK(key) => HashSet::from([key.clone()]),
+        Process::Par(p_left, p_right) => {
+            let mut set = all_tag_keys_proc(p_left);
+            set.extend(all_tag_keys_proc(p_right));
+            set
+        },
+        Process::Send(_, _, proc) | Process::Recv(_, _, _, _, proc) | Process::New(_, proc) => all_tag_keys_proc(proc),
+    }
+}
+
+impl ProcTag
+{
+    // The keys the tag is made of, a fragment has its own key and the one of the split tag
+    pub fn keys(&self) -> Vec<&TagKey>
+    {
+        match self {
+            ProcTag::PTKey(key) => vec![key],
+            ProcTag::PTSplit(frag_key, _, og_key) => vec![frag_key, og_key],
+        }
+    }
+}
+
+// The tag keys of the state, in the tags and in the processes
+pub fn all_tag_keys_state(state: &PrimeState) -> HashSet<TagKey>
+{
+    state.iter().flat_map(|TaggedPrimProc { tag, proc }| {
+        let mut keys = all_tag_keys_proc(&proc.clone().to_process());
+        keys.extend(tag.keys().into_iter().cloned());
+        keys
+    }).collect()
+}
+
 pub fn check_all_pvar_closed(proc: &Process) -> bool
 {
     _rec_check_pvar_closed(proc, &mut HashSet::new())
@@ -199,14 +346,16 @@ fn _rec_check_pvar_closed(proc: &Process, env: &mut HashSet<ProcVar>) -> bool
             let mut env_copy = env.clone();
             _rec_check_pvar_closed(a, &mut env_copy) && _rec_check_pvar_closed(b, env)
         },
-        Process::Send(_, p) => 
+        Process::Send(_, _, p) => 
             _rec_check_pvar_closed(p, env),
-        Process::Recv(_, pvar, _, p) => {
+        Process::Recv(_, _, pvar, _, p) => {
             env.insert(pvar.clone());
             _rec_check_pvar_closed(p, env)
         },
         Process::RollV(_) => true,
         Process::RollK(_) => true,
+        Process::New(_, p) => 
+            _rec_check_pvar_closed(p, env),
     }
 }
 
@@ -224,15 +373,42 @@ fn _rec_check_tvar_closed(proc: &Process, env: &mut HashSet<TagVar>) -> bool
             let mut env_copy = env.clone();
             _rec_check_tvar_closed(a, &mut env_copy) && _rec_check_tvar_closed(b, env)
         },
-        Process::Send(_, p) => 
+        Process::Send(_, _, p) => 
             _rec_check_tvar_closed(p, env),
-        Process::Recv(_, _, tvar, p) => {
+        Process::Recv(_, _, _, tvar, p) => {
             env.insert(tvar.clone());
             _rec_check_tvar_closed(p, env)
         },
         Process::RollV(tag_var) => 
             env.contains(tag_var),
         Process::RollK(_) => false,
+        Process::New(_, p) => 
+            _rec_check_tvar_closed(p, env),
+    }
+}
+
+// Every data variable used in a send is bound by a receive before it
+pub fn check_all_dvar_closed(proc: &Process) -> bool
+{
+    _rec_check_dvar_closed(proc, &mut HashSet::new())
+}
+
+fn _rec_check_dvar_closed(proc: &Process, env: &mut HashSet<DataVar>) -> bool
+{
+    match proc {
+        Process::End | Process::PVar(_) | Process::RollV(_) | Process::RollK(_) => true,
+        Process::Par(a, b) => {
+            let mut env_copy = env.clone();
+            _rec_check_dvar_closed(a, &mut env_copy) && _rec_check_dvar_closed(b, env)
+        },
+        Process::Send(_, data, p) =>
+            data.iter().all(|e| e.free_vars().iter().all(|v| env.contains(v))) && _rec_check_dvar_closed(p, env),
+        Process::Recv(_, d_vars, _, _, p) => {
+            env.extend(d_vars.iter().map(|DataBinder(var, _)| var.clone()));
+            _rec_check_dvar_closed(p, env)
+        },
+        Process::New(_, p) =>
+            _rec_check_dvar_closed(p, env),
     }
 }
 
@@ -253,9 +429,9 @@ fn _rec_check_unique_pvar_tvar(proc: &Process, p_env: &mut HashSet<ProcVar>, t_e
             let mut t_env_copy = t_env.clone();
             _rec_check_unique_pvar_tvar(a, &mut p_env_copy, &mut t_env_copy) && _rec_check_unique_pvar_tvar(b, p_env, t_env)
         },
-        Process::Send(_, p) => 
+        Process::Send(_, _, p) | Process::New(_, p) => 
             _rec_check_unique_pvar_tvar(p, p_env, t_env),
-        Process::Recv(_, pvar, tvar, p) => {
+        Process::Recv(_, _, pvar, tvar, p) => {
             if p_env.contains(pvar) || t_env.contains(tvar) {
                 return false;
             }
@@ -266,16 +442,167 @@ fn _rec_check_unique_pvar_tvar(proc: &Process, p_env: &mut HashSet<ProcVar>, t_e
     }
 }
 
+// Every restricted channel is bound once and is not used outside its restriction,
+//     so that the restrictions of an initial configuration can keep their names when opened
+pub fn check_new_names_bound(procs: &[Process]) -> bool
+{
+    let mut bound = HashSet::new();
+    let mut free = HashSet::new();
+
+    for proc in procs {
+        if !_rec_collect_new_names(proc, &mut bound) {
+            return false;
+        }
+        free.extend(free_chn_names_proc(proc));
+    }
+
+    bound.is_disjoint(&free)
+}
+
+fn _rec_collect_new_names(proc: &Process, bound: &mut HashSet<String>) -> bool
+{
+    match proc {
+        Process::End | Process::PVar(_) | Process::RollV(_) | Process::RollK(_) => true,
+        Process::Par(a, b) =>
+            _rec_collect_new_names(a, bound) && _rec_collect_new_names(b, bound),
+        Process::Send(_, _, p) | Process::Recv(_, _, _, _, p) =>
+            _rec_collect_new_names(p, bound),
+        Process::New(ChName(ch_name), p) =>
+            bound.insert(ch_name.clone()) && _rec_collect_new_names(p, bound),
+    }
+}
+
 pub fn check_initial_conf_list(procs: &Vec<Process>) -> bool
 {
-    procs.iter().all(|proc| check_initial_conf(proc))
+    procs.iter().all(check_initial_conf) &&
+    check_new_names_bound(procs)
 }
 
 pub fn check_initial_conf(proc: &Process) -> bool
 {
     check_all_pvar_closed(proc) && 
     check_all_tvar_closed(proc) && 
-    // check_unique_pvar_tvar(proc)
+    check_all_dvar_closed(proc) &&
+    check_unique_pvar_tvar(proc) &&
+    check_new_names_bound(std::slice::from_ref(proc))
+}
+
+impl SessionId
+{
+    pub fn is_default(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    // The channel with the given name that belongs to this session only
+    pub fn channel(&self, base: &str) -> ChName
+    {
+        if self.is_default() {
+            ChName(base.to_string())
+        } else {
+            ChName(format!("{}_{}", self.0, base))
+        }
+    }
+
+    // The name the channel has in the encoding, without the prefix of this session
+    pub fn role<'a>(&self, ch_name: &'a ChName) -> &'a str
+    {
+        if self.is_default() {
+            return &ch_name.0
+        }
+
+        ch_name.0.strip_prefix(self.0.as_str())
+            .and_then(|rest| rest.strip_prefix('_'))
+            .unwrap_or(&ch_name.0)
+    }
+
+    // Id of a party, or of the history, when running in this session
+    pub fn party_id(&self, party: &str) -> String
+    {
+        if self.is_default() {
+            party.to_string()
+        } else {
+            format!("{}.{}", self.0, party)
+        }
+    }
+}
+
+impl Expr
+{
+    // A tuple with no variables is kept as a value, so every expression has a single representation
+    pub fn tuple(items: Vec<Expr>) -> Expr
+    {
+        let tuple = Expr::Tuple(items);
+        match tuple.eval() {
+            Some(value) => Expr::Val(value),
+            None => tuple,
+        }
+    }
+
+    // The value of the expression, if it has no variables left
+    pub fn eval(&self) -> Option<Value>
+    {
+        match self {
+            Expr::Val(v) => Some(v.clone()),
+            Expr::Var(_) => None,
+            Expr::Tuple(es) => es.iter().map(|e| e.eval()).collect::<Option<Vec<_>>>().map(Value::Tuple),
+        }
+    }
+
+    pub fn free_vars(&self) -> Vec<DataVar>
+    {
+        match self {
+            Expr::Val(_) => vec![],
+            Expr::Var(var) => vec![var.clone()],
+            Expr::Tuple(es) => es.iter().flat_map(|e| e.free_vars()).collect(),
+        }
+    }
+
+    pub fn substitute(self, binds: &[(DataVar, Value)]) -> Expr
+    {
+        match self {
+            Expr::Var(var) => match binds.iter().find(|(v, _)| *v == var) {
+                Some((_, value)) => Expr::Val(value.clone()),
+                None => Expr::Var(var),
+            },
+            Expr::Tuple(es) => Expr::tuple(es.into_iter().map(|e| e.substitute(binds)).collect()),
+            Expr::Val(_) => self,
+        }
+    }
+}
 
-    return true
+impl Value
+{
+    pub fn sort(&self) -> Sort
+    {
+        match self {
+            Value::Int(_) => Sort::Int,
+            Value::Str(_) => Sort::Str,
+            Value::Bool(_) => Sort::Bool,
+            Value::Tuple(values) => Sort::Tuple(values.iter().map(|v| v.sort()).collect()),
+        }
+    }
+}
+
+impl Sort
+{
+    pub fn accepts(&self, value: &Value) -> bool
+    {
+        *self == value.sort()
+    }
+}
+
+impl DataBinder
+{
+    // A binder without a sort takes any value
+    pub fn accepts_sort(&self, sort: &Sort) -> bool
+    {
+        self.1.as_ref().is_none_or(|s| s == sort)
+    }
+}
+
+// The values can be received by the binders: there are as many of them and each has the sort of its binder
+pub fn binders_accept(d_vars: &[DataBinder], values: &[Value]) -> bool
+{
+    d_vars.len() == values.len() && d_vars.iter().zip(values).all(|(b, v)| b.accepts_sort(&v.sort()))
 }
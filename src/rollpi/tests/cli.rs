@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use crate::cli::{execute, parse_args, Command, LogFormat, RunOptions};
+use crate::rollpi::environment::launch::{run_processes_as_parties, LaunchConfig, StrategyKind};
+use crate::rollpi::errors::{CliError, LaunchError};
+use crate::rollpi::logger::trace::TraceTarget;
+use crate::rollpi::parser::parse;
+use crate::scenarios;
+
+fn args(line: &str) -> Vec<String>
+{
+    line.split_whitespace().map(|a| a.to_string()).collect()
+}
+
+#[test]
+fn test_parse_commands()
+{
+    assert_eq!(parse_args(args("")), Ok(Command::Help));
+    assert_eq!(parse_args(args("list")), Ok(Command::List));
+    assert_eq!(parse_args(args("run basic_roll_pi_test")), Ok(Command::Run("basic_roll_pi_test".to_string(), RunOptions::default())));
+
+    assert_eq!(parse_args(args("file conf.txt")), Ok(Command::File("conf.txt".into(), RunOptions::default())));
+
+    let Ok(Command::Run(name, options)) = parse_args(args(
+        "run simple_rec_lt --strategy random --strategy A=determ --seed 7 --log-dir out --log-format jsonl --timeout 1.5"
+    )) else { panic!() };
+    assert_eq!(name, "simple_rec_lt");
+    assert_eq!(options.default_strategy, Some(StrategyKind::Random));
+    assert_eq!(options.strategies.get("A"), Some(&StrategyKind::Determ));
+    assert_eq!(options.seed, Some(7));
+    assert_eq!(options.log_dir.to_str(), Some("out"));
+    assert_eq!(options.log_format, LogFormat::JsonLines);
+    assert_eq!(options.timeout, Some(Duration::from_millis(1500)));
+}
+
+#[test]
+fn test_parse_errors()
+{
+    assert_eq!(parse_args(args("walk")), Err(CliError::UnknownCommand("walk".to_string())));
+    assert!(matches!(parse_args(args("run")), Err(CliError::MissingArgument(_))));
+    assert!(matches!(parse_args(args("run x --seed")), Err(CliError::MissingArgument(_))));
+    assert_eq!(parse_args(args("run x --fast")), Err(CliError::UnknownOption("--fast".to_string())));
+    assert!(matches!(parse_args(args("run x --strategy A=smart")), Err(CliError::InvalidValue(opt, value, _)) if opt == "--strategy" && value == "A=smart"));
+    assert!(matches!(parse_args(args("run x --timeout -1")), Err(CliError::InvalidValue(..))));
+    assert!(matches!(parse_args(args("run x --log-format xml")), Err(CliError::InvalidValue(..))));
+}
+
+#[test]
+fn test_execute()
+{
+    let mut out = vec![];
+    execute(Command::List, &mut out).unwrap();
+    let listed = String::from_utf8(out).unwrap();
+    assert_eq!(listed.lines().count(), scenarios::builtin().len());
+    assert!(listed.starts_with("basic_roll_pi_test"));
+
+    let options = RunOptions { log_format: LogFormat::None, ..RunOptions::default() };
+    let mut out = vec![];
+    execute(Command::Run("basic_roll_pi_test".to_string(), options.clone()), &mut out).unwrap();
+    let summary = String::from_utf8(out).unwrap();
+    assert!(summary.starts_with("Scenario basic_roll_pi_test with participants A, B\n"), "{}", summary);
+
+    assert_eq!(execute(Command::Run("nope".to_string(), options), &mut vec![]), Err(CliError::UnknownScenario("nope".to_string())));
+
+    let file = std::env::temp_dir().join(format!("rollpi_cli_{}", std::process::id()));
+    std::fs::write(&file, "").unwrap();
+    let options = RunOptions { log_dir: file.join("logs"), ..RunOptions::default() };
+    let result = execute(Command::Run("basic_roll_pi_test".to_string(), options), &mut vec![]);
+    let _ = std::fs::remove_file(&file);
+    assert!(matches!(result, Err(CliError::LogDir(_, _))), "{:?}", result);
+}
+
+#[test]
+fn test_execute_file()
+{
+    let path = std::env::temp_dir().join(format!("pi_calculus_cli_{}.txt", std::process::id()));
+    std::fs::write(&path, "\
+CliFileA : CliFileB!{yes(1).CliFileB?ack.end, no.end}
+CliFileB : CliFileA?{yes(n: int).CliFileA!ack.end, no.end}
+strategy CliFileA = random
+").unwrap();
+
+    let options = RunOptions { log_format: LogFormat::None, seed: Some(3), ..RunOptions::default() };
+    let mut out = vec![];
+    let result = execute(Command::File(path.clone(), options), &mut out);
+    std::fs::remove_file(&path).unwrap();
+
+    result.unwrap();
+    let summary = String::from_utf8(out).unwrap();
+    assert!(summary.contains("with participants CliFileA, CliFileB\n"), "{}", summary);
+}
+
+#[test]
+fn test_launch_errors()
+{
+    let conf = vec![("LaunchA".to_string(), parse("a<0>").unwrap())];
+    let config = LaunchConfig {
+        strategies: [("LaunchB".to_string(), StrategyKind::Random)].into_iter().collect(),
+        trace_targets: Some(vec![]),
+        ..LaunchConfig::default()
+    };
+    assert_eq!(run_processes_as_parties(conf, config).err(), Some(LaunchError::UnknownParticipant("LaunchB".to_string())));
+
+    // X is free
+    let conf = vec![("LaunchA".to_string(), parse("a<X>").unwrap())];
+    assert_eq!(run_processes_as_parties(conf, LaunchConfig::default()).err(), Some(LaunchError::InvalidConfiguration));
+
+    // The log directory would be inside a file
+    let file = std::env::temp_dir().join(format!("rollpi_launch_{}", std::process::id()));
+    std::fs::write(&file, "").unwrap();
+    let conf = vec![("LaunchA".to_string(), parse("a<0>").unwrap())];
+    let config = LaunchConfig { trace_targets: Some(vec![TraceTarget::Text(file.join("logs"))]), ..LaunchConfig::default() };
+    let err = run_processes_as_parties(conf, config).err();
+    let _ = std::fs::remove_file(&file);
+    assert!(matches!(err, Some(LaunchError::Trace(_, _))), "{:?}", err);
+}
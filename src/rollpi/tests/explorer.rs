@@ -0,0 +1,134 @@
+use crate::rollpi::explorer::{canonical_form, explore, ExploreConfig};
+use crate::rollpi::parser::parse;
+use crate::rollpi::semantics::{Configuration, RunResult, Step, Transition};
+use crate::rollpi::syntax::{ChName, Process, TagKey};
+use crate::scenarios::roll_pi::{basic_roll_pi_rollback, basic_roll_pi_test};
+
+fn conf(procs: Vec<(&str, &str)>) -> Vec<(String, Process)>
+{
+    procs.into_iter().map(|(id, p)| (id.to_string(), parse(p).unwrap())).collect()
+}
+
+#[test]
+fn test_explore_terminates()
+{
+    let report = explore(conf(vec![("A", "a<0>"), ("B", "a(X, k).X")]), &ExploreConfig::default());
+
+    assert!(report.complete);
+    assert_eq!((report.states, report.transitions), (2, 1));
+    assert!(report.deadlocks.is_empty());
+    assert!(report.livelocks.is_empty());
+}
+
+#[test]
+fn test_explore_deadlock()
+{
+    let report = explore(basic_roll_pi_test(), &ExploreConfig::default());
+
+    // The received b<0> has no one to talk to
+    assert_eq!(report.deadlocks.len(), 1);
+    assert_eq!(report.deadlocks[0].trace.len(), 1);
+    assert!(report.livelocks.is_empty());
+
+    // Only one of the two ways C can receive leads to a deadlock
+    let report = explore(conf(vec![
+        ("A", "a<0> | a<b<0>>"),
+        ("B", "a(X, k).X"),
+        ("C", "a(Y, h).b(Z, g).Z"),
+    ]), &ExploreConfig::default());
+
+    assert_eq!(report.deadlocks.len(), 1);
+    let deadlock = &report.deadlocks[0];
+    assert_eq!(deadlock.trace.len(), 2);
+    assert!(deadlock.trace.iter().all(|t| matches!(t, Transition::Com { .. })));
+    assert!(deadlock.state.state_of("C").iter().any(|p| format!("{}", p.proc) == "b(Z, g).Z"));
+}
+
+#[test]
+fn test_explore_livelock()
+{
+    let report = explore(basic_roll_pi_rollback(), &ExploreConfig::default());
+
+    // Rolling back brings the configuration back to the start, up to the names of the tags
+    assert!(report.complete);
+    assert_eq!(report.states, 3);
+    assert!(report.deadlocks.is_empty());
+    assert_eq!(report.livelocks.len(), 1);
+
+    let livelock = &report.livelocks[0];
+    assert!(livelock.trace.is_empty());
+    assert_eq!(livelock.cycle.len(), 3);
+    assert!(matches!(livelock.cycle[2], Transition::Roll { .. }));
+}
+
+#[test]
+fn test_explore_bounds()
+{
+    let report = explore(basic_roll_pi_rollback(), &ExploreConfig { max_depth: Some(1), max_states: None });
+    assert!(!report.complete);
+    assert_eq!(report.states, 2);
+    // The unexplored configuration could still terminate
+    assert!(report.livelocks.is_empty());
+
+    let report = explore(basic_roll_pi_test(), &ExploreConfig { max_depth: None, max_states: Some(1) });
+    assert!(!report.complete);
+    assert_eq!(report.states, 1);
+    assert!(report.deadlocks.is_empty());
+}
+
+#[test]
+fn test_canonical_renaming()
+{
+    let initial = Configuration::new(conf(vec![
+        ("A", "a<0> | b<0>"),
+        ("B", "a(X, k).new c.c<X>"),
+        ("C", "b(Y, h).new d.d<Y>"),
+    ]));
+
+    let com = |conf: &Configuration, ch: &str| {
+        conf.enabled_steps().into_iter().find(|s| match s {
+            Step::Com(i, _) => format!("{}", conf.procs[*i].tagged.proc).starts_with(ch),
+            _ => false,
+        }).unwrap()
+    };
+
+    // The same communications in different orders create differently named tags and channels
+    let mut first = initial.clone();
+    first.step(&com(&first, "a")).unwrap();
+    first.step(&com(&first, "b")).unwrap();
+
+    let mut second = initial.clone();
+    second.step(&com(&second, "b")).unwrap();
+    second.step(&com(&second, "a")).unwrap();
+
+    assert!(format!("{}", first) != format!("{}", second));
+    assert_eq!(canonical_form(&first), canonical_form(&second));
+    assert!(canonical_form(&first) != canonical_form(&initial));
+}
+
+#[test]
+fn test_canonical_keeps_user_names()
+{
+    // The user names are shaped like the ones the steps create
+    let mut conf = Configuration::new(conf(vec![
+        ("A", "a<0>"),
+        ("B", "a(X, k).new c.(c<X> | _ch_0_c<0>)"),
+        ("C", "_ch_0_c(Y, h).roll @_tag_0"),
+    ]));
+    let RunResult::Quiescent(trace) = conf.run_to_quiescence(10) else { panic!() };
+
+    // The steps created other names, so B reached C through the user channel and not through the opened one
+    let keys = trace.iter()
+        .map(|t| match t { Transition::Com { key, .. } => key.clone(), _ => panic!("{}", t) })
+        .collect::<Vec<_>>();
+    assert!(keys.iter().all(|key| conf.is_created_key(key) && key != &TagKey("_tag_0".to_string())));
+    assert!(!conf.is_created_key(&TagKey("_tag_0".to_string())));
+    assert!(!conf.is_created_channel(&ChName("_ch_0_c".to_string())));
+    assert!(conf.is_created_channel(&ChName("_ch_1_c".to_string())));
+
+    // The canonical form renames the created names only
+    let form = canonical_form(&conf);
+    let lines = form.lines().collect::<Vec<_>>();
+    assert_eq!(lines[..2], ["B > sp_0_#0/2/#0: #1<0>", "C > sp_0_#2/1/#2: roll @_tag_0"]);
+    assert_eq!(lines[3], "[B sp_1_#0/2/#0: _ch_0_c<0> | C sp_0_C/1/C: _ch_0_c(Y, h).roll @_tag_0; #2]");
+}
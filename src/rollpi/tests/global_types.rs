@@ -0,0 +1,101 @@
+use crate::rollpi::errors::ProjectionError;
+use crate::rollpi::global_types::GlobalType;
+use crate::rollpi::local_types::LocalType;
+use crate::scenarios::loc_types::{simple_rec_global_type, simple_rec_local_types};
+
+fn msg(from: &str, to: &str, branches: Vec<(&str, GlobalType)>) -> GlobalType
+{
+    GlobalType::Msg(from.to_string(), to.to_string(),
+        branches.into_iter().map(|(l, g)| (l.to_string(), g)).collect())
+}
+
+fn lt_branches<D>(branches: Vec<(&str, LocalType)>) -> Vec<(String, Vec<D>, LocalType)>
+{
+    branches.into_iter().map(|(l, lt)| (l.to_string(), vec![], lt)).collect()
+}
+
+#[test]
+fn test_projection_scenario()
+{
+    let projected = simple_rec_global_type().project_all().unwrap();
+
+    assert_eq!(projected, simple_rec_local_types());
+}
+
+#[test]
+fn test_roles()
+{
+    let g = msg("A", "B", vec![
+        ("l1", msg("B", "C", vec![("l2", msg("C", "A", vec![("l3", GlobalType::End)]))])),
+    ]);
+
+    assert_eq!(g.roles(), vec!["A".to_string(), "B".to_string(), "C".to_string()]);
+}
+
+#[test]
+fn test_projection_merge()
+{
+    let g = msg("A", "B", vec![
+        ("ok", msg("B", "C", vec![("ok", GlobalType::End)])),
+        ("ko", msg("B", "C", vec![("ko", GlobalType::End)])),
+    ]);
+
+    let expected = LocalType::Recv("B".to_string(), lt_branches(vec![
+        ("ok", LocalType::End),
+        ("ko", LocalType::End),
+    ]));
+
+    assert_eq!(g.project(&"C".to_string()), Ok(expected));
+}
+
+#[test]
+fn test_projection_recursion()
+{
+    // C can not tell when the loop between A and B is over
+    let g = GlobalType::RAbs("t".to_string(), Box::new(msg("A", "B", vec![
+        ("again", GlobalType::RVar("t".to_string())),
+        ("stop", msg("B", "C", vec![("done", GlobalType::End)])),
+    ])));
+    assert_eq!(g.project(&"C".to_string()), Err(ProjectionError::NotMergeable("C".to_string(), vec![], "stop".to_string())));
+
+    let g_c_free = GlobalType::RAbs("t".to_string(), Box::new(msg("A", "B", vec![
+        ("again", GlobalType::RVar("t".to_string())),
+    ])));
+    assert_eq!(g_c_free.project(&"C".to_string()), Ok(LocalType::End));
+
+    let g_c_loop = GlobalType::RAbs("t".to_string(), Box::new(msg("A", "B", vec![
+        ("again", msg("B", "C", vec![("done", GlobalType::RVar("t".to_string()))])),
+    ])));
+    let expected_c_loop = LocalType::RAbs("t".to_string(), Box::new(
+        LocalType::Recv("B".to_string(), lt_branches(vec![("done", LocalType::RVar("t".to_string()))]))
+    ));
+    assert_eq!(g_c_loop.project(&"C".to_string()), Ok(expected_c_loop));
+}
+
+#[test]
+fn test_projection_errors()
+{
+    let not_mergeable = msg("A", "B", vec![
+        ("l1", msg("C", "A", vec![("x", GlobalType::End)])),
+        ("l2", msg("A", "C", vec![("y", GlobalType::End)])),
+    ]);
+    let err = not_mergeable.project(&"C".to_string()).unwrap_err();
+    assert_eq!(err, ProjectionError::NotMergeable("C".to_string(), vec![], "l2".to_string()));
+    assert_eq!(format!("{}", err), "Projection Error on role C at the top level: branch l2 can not be merged with the other branches");
+
+    // Projecting onto the parties of the choice still works
+    assert!(not_mergeable.project(&"A".to_string()).is_ok());
+
+    let self_comm = msg("A", "B", vec![
+        ("l1", msg("B", "A", vec![("l2", msg("B", "B", vec![("l3", GlobalType::End)]))])),
+    ]);
+    let err = self_comm.project(&"A".to_string()).unwrap_err();
+    assert_eq!(err, ProjectionError::SelfCommunication("A".to_string(), vec!["l1".to_string(), "l2".to_string()], "B".to_string()));
+    assert_eq!(format!("{}", err), "Projection Error on role A at branch l1.l2: party B sends a message to itself");
+
+    let duplicate = msg("A", "B", vec![("l", GlobalType::End), ("l", GlobalType::End)]);
+    assert!(matches!(duplicate.project(&"B".to_string()), Err(ProjectionError::DuplicateLabel(_, _, _))));
+
+    let empty = msg("A", "B", vec![]);
+    assert!(matches!(empty.project_all(), Err(ProjectionError::EmptyChoice(_, _))));
+}
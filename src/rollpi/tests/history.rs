@@ -0,0 +1,71 @@
+use crate::rollpi::environment::runner::HistoryGraph;
+use crate::rollpi::parser::parse;
+use crate::rollpi::syntax::{ProcTag, TagKey, TaggedProc};
+
+fn key(k: &str) -> ProcTag
+{
+    ProcTag::PTKey(TagKey(k.to_string()))
+}
+
+fn split(frag: &str, total: i32, og: &str) -> ProcTag
+{
+    ProcTag::PTSplit(TagKey(frag.to_string()), total, TagKey(og.to_string()))
+}
+
+// A sends on a to B, the continuation of B is split in two and one fragment talks to C
+fn graph() -> HistoryGraph
+{
+    let mut graph = HistoryGraph::default();
+
+    let (snd, rcv, k1) = (split("sp_0_A", 1, "A"), split("sp_0_B", 1, "B"), key("k1"));
+    graph.tag_owner.insert(snd.clone(), "A".to_string());
+    graph.tag_owner.insert(rcv.clone(), "B".to_string());
+    graph.tag_owner.insert(k1.clone(), "B".to_string());
+    graph.join_links.insert(snd.clone(), k1.clone());
+    graph.join_links.insert(rcv.clone(), k1.clone());
+    graph.memories.insert(k1.clone(), (
+        TaggedProc { tag: snd, proc: parse("a<0>").unwrap() },
+        TaggedProc { tag: rcv, proc: parse("a(X, k).(b<0> | roll k)").unwrap() },
+    ));
+
+    let (frag, c_rcv, k2) = (split("sp_0_k1", 2, "k1"), split("sp_0_C", 1, "C"), key("k2"));
+    graph.tag_owner.insert(frag.clone(), "B".to_string());
+    graph.tag_owner.insert(c_rcv.clone(), "C".to_string());
+    graph.tag_owner.insert(k2.clone(), "C".to_string());
+    graph.join_links.insert(frag.clone(), k2.clone());
+    graph.join_links.insert(c_rcv.clone(), k2.clone());
+    graph.branch_links.insert(k1.clone(), vec![frag.clone()]);
+
+    graph.frozen_tags.extend([k1, frag, k2]);
+    graph
+}
+
+#[test]
+fn test_dot_nodes()
+{
+    let dot = graph().to_dot();
+
+    assert!(dot.starts_with("digraph history {\n"));
+    assert!(dot.ends_with("\n}"));
+
+    // Labelled with the tag and its owner, frozen tags are filled
+    assert!(dot.contains(r#"    "sp_0_A" [label="sp_0_A\nA"];"#), "{}", dot);
+    assert!(dot.contains(r#"    "k1" [label="k1\nB", style=filled, fillcolor=lightblue];"#), "{}", dot);
+    assert!(dot.contains(r#"    "sp_0_k1" [label="sp_0_k1\nB", style=filled, fillcolor=lightblue];"#), "{}", dot);
+    assert_eq!(dot.matches("[label=").count(), 6);
+}
+
+#[test]
+fn test_dot_edges()
+{
+    let dot = graph().to_dot();
+
+    assert!(dot.contains(r#"    "sp_0_A" -> "k1" [style=solid];"#));
+    assert!(dot.contains(r#"    "sp_0_B" -> "k1" [style=solid];"#));
+    assert!(dot.contains(r#"    "sp_0_k1" -> "k2" [style=solid];"#));
+    assert!(dot.contains(r#"    "k1" -> "sp_0_k1" [style=dashed];"#));
+    assert_eq!(dot.matches(" -> ").count(), 5);
+
+    // The rendering does not depend on the iteration order of the maps
+    assert_eq!(dot, graph().to_dot());
+}
@@ -0,0 +1,201 @@
+use crate::rollpi::errors::LocalTypeError;
+use crate::rollpi::local_types::{LocalType, PartLocalType, Sort};
+use crate::rollpi::semantics::{Configuration, RunResult, Transition};
+use crate::rollpi::syntax::{all_chn_names_proc, check_initial_conf, check_unique_pvar_tvar, ChName, DataBinder, DataVar, Expr, Value};
+use crate::scenarios::loc_types::simple_rec_local_types;
+
+fn rabs(r_label: &str, lt: LocalType) -> LocalType
+{
+    LocalType::RAbs(r_label.to_string(), Box::new(lt))
+}
+
+fn rvar(r_label: &str) -> LocalType
+{
+    LocalType::RVar(r_label.to_string())
+}
+
+fn send(party: &str, branches: Vec<(&str, LocalType)>) -> LocalType
+{
+    LocalType::Send(party.to_string(), branches.into_iter().map(|(l, lt)| (l.to_string(), vec![], lt)).collect())
+}
+
+fn recv(party: &str, branches: Vec<(&str, LocalType)>) -> LocalType
+{
+    LocalType::Recv(party.to_string(), branches.into_iter().map(|(l, lt)| (l.to_string(), vec![], lt)).collect())
+}
+
+fn validate_a(lt: LocalType) -> Result<(), Vec<LocalTypeError>>
+{
+    lt.validate(&"A".to_string())
+}
+
+#[test]
+fn test_valid()
+{
+    for p in simple_rec_local_types() {
+        assert_eq!(p.validate(), Ok(()));
+    }
+
+    // A nested binder shadowing an outer one is fine as long as it is guarded
+    let shadowed = rabs("t", send("B", vec![
+        ("l1", rabs("t", recv("B", vec![("l2", rvar("t"))]))),
+        ("l3", rvar("t")),
+    ]));
+    assert_eq!(validate_a(shadowed), Ok(()));
+}
+
+#[test]
+fn test_recursion_errors()
+{
+    assert_eq!(validate_a(rabs("t", rvar("t"))), Err(vec![LocalTypeError::UnguardedRecursion("t".to_string())]));
+    assert_eq!(validate_a(rabs("t", rabs("s", rvar("t")))), Err(vec![LocalTypeError::UnguardedRecursion("t".to_string())]));
+    assert_eq!(validate_a(send("B", vec![("l", rvar("t"))])), Err(vec![LocalTypeError::FreeRecVar("t".to_string())]));
+
+    // The inner binder is unguarded even if the outer one is guarded
+    let inner_unguarded = rabs("t", send("B", vec![("l", rabs("s", rvar("s")))]));
+    assert_eq!(validate_a(inner_unguarded), Err(vec![LocalTypeError::UnguardedRecursion("s".to_string())]));
+}
+
+#[test]
+fn test_branch_errors()
+{
+    let all_errors = send("A", vec![
+        ("l", LocalType::End),
+        ("l", recv("B", vec![])),
+    ]);
+
+    assert_eq!(validate_a(all_errors), Err(vec![
+        LocalTypeError::SelfCommunication("A".to_string()),
+        LocalTypeError::DuplicateLabel("A".to_string(), "l".to_string()),
+        LocalTypeError::EmptyChoice("B".to_string()),
+    ]));
+}
+
+#[test]
+fn test_data_var_errors()
+{
+    let var = |x: &str| DataVar(x.to_string());
+    let forward = |sent: &str| LocalType::Recv("B".to_string(), vec![
+        ("in".to_string(), vec![DataBinder(var("x"), Some(Sort::Int))], LocalType::Send("C".to_string(), vec![
+            ("out".to_string(), vec![Expr::Tuple(vec![Expr::Var(var(sent)), Expr::Val(Value::Bool(true))])], LocalType::End),
+        ])),
+    ]);
+
+    // Only the variables received before can be sent
+    assert_eq!(validate_a(forward("x")), Ok(()));
+    assert_eq!(validate_a(forward("y")), Err(vec![LocalTypeError::FreeDataVar("out".to_string(), "y".to_string())]));
+}
+
+#[test]
+fn test_encoder_refuses_invalid()
+{
+    let invalid = PartLocalType::new("A".to_string(), rabs("t", rvar("t")));
+    assert_eq!(invalid.to_process(), Err(vec![LocalTypeError::UnguardedRecursion("t".to_string())]));
+
+    for p in simple_rec_local_types() {
+        assert!(p.to_process().is_ok());
+    }
+}
+
+// Every local type of party A talking to party B with at most the given nesting depth,
+//     which uses only the recursion variables bound in the given scope
+fn enumerate(depth: usize, scope: &Vec<&'static str>) -> Vec<LocalType>
+{
+    let mut types = vec![LocalType::End];
+    types.extend(scope.iter().map(|r_label| rvar(r_label)));
+
+    if depth == 0 {
+        return types
+    }
+
+    let smaller = enumerate(depth - 1, scope);
+
+    for r_label in ["t", "s"] {
+        let mut inner_scope = scope.clone();
+        inner_scope.push(r_label);
+        types.extend(enumerate(depth - 1, &inner_scope).into_iter().map(|lt| rabs(r_label, lt)));
+    }
+
+    for lt in &smaller {
+        types.push(send("B", vec![("l1", lt.clone())]));
+        types.push(recv("B", vec![("l1", lt.clone())]));
+    }
+
+    // Keep the number of two branch choices small, the continuations are already covered above
+    for lt_1 in smaller.iter().take(4) {
+        for lt_2 in &smaller {
+            types.push(send("B", vec![("l1", lt_1.clone()), ("l2", lt_2.clone())]));
+            types.push(recv("B", vec![("l1", lt_2.clone()), ("l2", lt_1.clone())]));
+        }
+    }
+
+    types
+}
+
+#[test]
+fn test_encoding_unique_names()
+{
+    let valid = enumerate(3, &vec![]).into_iter()
+        .map(|lt| PartLocalType::new("A".to_string(), lt))
+        .filter(|p| p.validate().is_ok())
+        .collect::<Vec<_>>();
+
+    // Make sure the enumeration actually covers nested and shadowed recursion
+    assert!(valid.len() > 1000, "only {} local types", valid.len());
+
+    for p in valid {
+        let proc = p.clone().to_process().unwrap();
+        assert!(check_unique_pvar_tvar(&proc), "Names bound twice in the encoding of {:?}", p);
+        assert!(check_initial_conf(&proc), "Initial configuration check failed for {:?}", p);
+    }
+}
+
+#[test]
+fn test_encoding_shadowed_recursion()
+{
+    // The inner t shadows the outer one, so the two loops must not share their channels
+    let shadowed = rabs("t", send("B", vec![
+        ("l1", rabs("t", recv("B", vec![("l2", rvar("t"))]))),
+        ("l3", rvar("t")),
+    ]));
+    let proc = PartLocalType::new("A".to_string(), shadowed).to_process().unwrap();
+    let channels = all_chn_names_proc(&proc);
+
+    for ch in ["rec_norm_A_t", "rec_comb_A_t", "rec_norm_A_t_1", "rec_comb_A_t_1"] {
+        assert!(channels.contains(ch), "Missing channel {} in {:?}", ch, channels);
+    }
+}
+
+#[test]
+fn test_sorts()
+{
+    let pair = Sort::Tuple(vec![Sort::Str, Sort::Bool]);
+    assert!(pair.accepts(&Value::Tuple(vec![Value::Str("s".to_string()), Value::Bool(true)])));
+    assert!(!pair.accepts(&Value::Tuple(vec![Value::Str("s".to_string())])));
+    assert!(!Sort::Int.accepts(&Value::Bool(true)));
+}
+
+#[test]
+fn test_encoding_with_sorts()
+{
+    let data = vec![Expr::Val(Value::Int(5)), Expr::Val(Value::Tuple(vec![Value::Str("s".to_string()), Value::Bool(true)]))];
+    let binders = vec![
+        DataBinder(DataVar("x".to_string()), Some(Sort::Int)),
+        DataBinder(DataVar("y".to_string()), Some(Sort::Tuple(vec![Sort::Str, Sort::Bool]))),
+    ];
+    let a = LocalType::Send("B".to_string(), vec![("lb".to_string(), data, LocalType::End)]);
+    let b = LocalType::Recv("A".to_string(), vec![("lb".to_string(), binders, LocalType::End)]);
+
+    let a = PartLocalType::new("A".to_string(), a).to_process().unwrap();
+    let b = PartLocalType::new("B".to_string(), b).to_process().unwrap();
+    assert!(check_initial_conf(&a) && check_initial_conf(&b));
+    // The sender sends its values, the receiver binds them to its data variables
+    assert!(a.to_string().contains("comm_dat_A_B_lb[5, (\"s\", true)]<0>"), "{}", a);
+    assert!(b.to_string().contains("comm_dat_A_B_lb[x: int, y: (str, bool)]"), "{}", b);
+
+    // The receiver binds as many data variables as the sender sends values, so the label goes through
+    let mut conf = Configuration::new(vec![("A".to_string(), a), ("B".to_string(), b)]);
+    let result = conf.run_to_quiescence(20);
+    let RunResult::Quiescent(trace) = result else { panic!("{:?}", result) };
+    assert!(trace.iter().any(|t| matches!(t, Transition::Com { channel: ChName(ch), .. } if ch == "comm_dat_A_B_lb")), "{:?}", trace);
+}
\ No newline at end of file
@@ -0,0 +1,26 @@
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod parser;
+#[cfg(test)]
+mod printer;
+#[cfg(test)]
+mod global_types;
+#[cfg(test)]
+mod local_types;
+#[cfg(test)]
+mod semantics;
+
+#[cfg(test)]
+mod runner;
+#[cfg(test)]
+mod strategies;
+#[cfg(test)]
+mod trace;
+#[cfg(test)]
+mod history;
+#[cfg(test)]
+mod explorer;
+#[cfg(test)]
+mod cli;
+#[cfg(test)]
+mod scenario_file;
\ No newline at end of file
@@ -0,0 +1,249 @@
+use crate::rollpi::errors::{ParseError, Position};
+use crate::rollpi::local_types::{LocalType, PartLocalType, Sort};
+use crate::rollpi::parser::{parse, parse_local_type};
+use crate::rollpi::printer::print_process;
+use crate::rollpi::syntax::{ChName, DataBinder, DataVar, Expr, Process, ProcVar, TagKey, TagVar, Value};
+use crate::scenarios;
+
+fn run_tests(tests: Vec<(&str, &Process)>)
+{
+    for (test, expected) in tests {
+        match parse(test) {
+            Ok(process) => assert_eq!(process, *expected),
+            Err(err) => panic!("Expected a process for {:?}, but got {}", test, err),
+        }
+    }
+}
+
+fn assert_round_trip(proc: &Process)
+{
+    let printed = print_process(proc);
+    match parse(&printed) {
+        Ok(parsed) => assert_eq!(parsed, *proc, "Round trip failed for {}", printed),
+        Err(err) => panic!("Could not parse back {}: {}", printed, err),
+    }
+}
+
+#[test]
+fn test_primary()
+{
+    let rollV = Process::RollV(TagVar("k".to_string()));
+    let rollK = Process::RollK(TagKey("k".to_string()));
+    let pvar = Process::PVar(ProcVar("X".to_string()));
+
+    run_tests(vec![
+        ("0", &Process::End),
+        ("((0))", &Process::End),
+        ("roll k", &rollV),
+        ("roll @k", &rollK),
+        ("X", &pvar),
+        (" ( X ) ", &pvar),
+    ])
+}
+
+#[test]
+fn test_actions()
+{
+    let send = Process::Send(ChName("a".to_string()), vec![], Box::new(Process::End));
+    let recv = Process::Recv(ChName("a".to_string()), vec![], ProcVar("X".to_string()), TagVar("k".to_string()),
+        Box::new(Process::Send(ChName("b".to_string()), vec![], Box::new(Process::PVar(ProcVar("X".to_string()))))));
+
+    run_tests(vec![
+        ("a<0>", &send),
+        ("a(X, k).b<X>", &recv),
+        ("a(X,k).(b<X>)", &recv),
+    ])
+}
+
+#[test]
+fn test_restriction()
+{
+    let send = Process::Send(ChName("a".to_string()), vec![], Box::new(Process::End));
+    let restricted = Process::New(ChName("a".to_string()), Box::new(send.clone()));
+    let restricted_par = Process::New(ChName("a".to_string()), Box::new(Process::Par(Box::new(send.clone()), Box::new(send.clone()))));
+    let prefix_first = Process::Par(Box::new(restricted.clone()), Box::new(send.clone()));
+
+    run_tests(vec![
+        ("new a.a<0>", &restricted),
+        ("new a.(a<0> | a<0>)", &restricted_par),
+        ("new a.a<0> | a<0>", &prefix_first),
+    ]);
+
+    assert_round_trip(&restricted_par);
+    assert_round_trip(&prefix_first);
+    assert!(parse("new new.0").is_err());
+}
+
+#[test]
+fn test_par_prio()
+{
+    let send_a = Process::Send(ChName("a".to_string()), vec![], Box::new(Process::End));
+    let send_b = Process::Send(ChName("b".to_string()), vec![], Box::new(Process::End));
+    let recv = |cont: Process| Process::Recv(ChName("a".to_string()), vec![], ProcVar("X".to_string()), TagVar("k".to_string()), Box::new(cont));
+
+    let prefix_first = Process::Par(Box::new(recv(send_a.clone())), Box::new(send_b.clone()));
+    let par_first = recv(Process::Par(Box::new(send_a.clone()), Box::new(send_b.clone())));
+    let right_assoc = Process::parallel_compose(vec![send_a.clone(), send_b.clone(), Process::End]);
+    let left_nested = Process::Par(Box::new(Process::Par(Box::new(send_a.clone()), Box::new(send_b.clone()))), Box::new(Process::End));
+
+    run_tests(vec![
+        ("a(X, k).a<0> | b<0>", &prefix_first),
+        ("a(X, k).(a<0> | b<0>)", &par_first),
+        ("a<0> | b<0> | 0", &right_assoc),
+        ("(a<0> | b<0>) | 0", &left_nested),
+    ])
+}
+
+#[test]
+fn test_errors()
+{
+    let expect_pos = |input: &str, line: usize, column: usize| {
+        match parse(input) {
+            Ok(p) => panic!("Expected an error for {:?}, but got {:?}", input, p),
+            Err(err) => assert_eq!(err.position(), Position { line, column }, "{}", err),
+        }
+    };
+
+    expect_pos("a<0", 1, 4);
+    expect_pos("a(X k).0", 1, 5);
+    expect_pos("a<0>\n  | b(X, k)", 2, 12);
+    expect_pos("roll roll", 1, 6);
+    expect_pos("0 0", 1, 3);
+
+    assert!(matches!(parse("a<0> $ b<0>"), Err(ParseError::UnexpectedChar(Position { line: 1, column: 6 }, '$'))));
+    assert!(matches!(parse(""), Err(ParseError::UnexpectedEnd(_, _))));
+}
+
+#[test]
+fn test_round_trip_scenarios()
+{
+    let confs = vec![
+        scenarios::roll_pi::basic_roll_pi_test(),
+        scenarios::roll_pi::basic_roll_pi_rollback(),
+        scenarios::roll_pi::complex_roll_pi_rollback(),
+    ];
+
+    for (_id, proc) in confs.into_iter().flatten() {
+        assert_round_trip(&proc);
+    }
+}
+
+#[test]
+fn test_round_trip_local_type_encoding()
+{
+    let lt = LocalType::RAbs("t".to_string(), Box::new(
+        LocalType::Send("B".to_string(), vec![
+            ("lb_1".to_string(), vec![], LocalType::Recv("B".to_string(), vec![("lb_2".to_string(), vec![], LocalType::RVar("t".to_string()))])),
+            ("lb_3".to_string(), vec![], LocalType::End),
+        ])
+    ));
+
+    assert_round_trip(&PartLocalType::new("A".to_string(), lt).to_process().unwrap());
+}
+
+#[test]
+fn test_parse_scenario()
+{
+    let (_, expected) = scenarios::roll_pi::basic_roll_pi_rollback().remove(1);
+
+    run_tests(vec![
+        ("a(x, g).(b<0> | b(y, s).roll g)", &expected),
+    ])
+}
+
+#[test]
+fn test_data()
+{
+    let send = Process::Send(ChName("a".to_string()), vec![
+        Expr::Val(Value::Int(-5)),
+        Expr::Val(Value::Str("say \"hi\" \\".to_string())),
+        Expr::Val(Value::Tuple(vec![Value::Bool(true), Value::Tuple(vec![Value::Int(1)])])),
+    ], Box::new(Process::End));
+    let recv = Process::Recv(ChName("a".to_string()), vec![DataBinder(DataVar("x".to_string()), None), DataBinder(DataVar("y".to_string()), None)],
+        ProcVar("X".to_string()), TagVar("k".to_string()),
+        Box::new(Process::Send(ChName("b".to_string()), vec![Expr::Tuple(vec![Expr::Var(DataVar("x".to_string())), Expr::Val(Value::Bool(false))])], Box::new(Process::PVar(ProcVar("X".to_string()))))));
+
+    run_tests(vec![
+        (r#"a[-5, "say \"hi\" \\", (true, (1,))]<0>"#, &send),
+        ("a[x, y](X, k).b[(x, false)]<X>", &recv),
+    ]);
+    assert_round_trip(&send);
+    assert_round_trip(&recv);
+}
+
+#[test]
+fn test_data_errors()
+{
+    let expect_pos = |input: &str, line: usize, column: usize| {
+        match parse(input) {
+            Ok(p) => panic!("Expected an error for {:?}, but got {:?}", input, p),
+            Err(err) => assert_eq!(err.position(), Position { line, column }, "{}", err),
+        }
+    };
+
+    // Receivers bind variables, not values, the error points at the data list
+    expect_pos("a[x, 1](X, k).0", 1, 2);
+    expect_pos("a[1, 2<0>", 1, 7);
+    expect_pos("a[(1, 2]<0>", 1, 8);
+    // Only received data has sorts
+    expect_pos("a[1: int]<0>", 1, 4);
+    expect_pos("a[x: foo](X, k).0", 1, 6);
+
+    // The whole literal, sign included, has to fit in an i64
+    assert_eq!(parse("a[-9223372036854775808, 9223372036854775807]<0>").unwrap(),
+        Process::Send(ChName("a".to_string()), vec![Expr::Val(Value::Int(i64::MIN)), Expr::Val(Value::Int(i64::MAX))], Box::new(Process::End)));
+    assert_eq!(parse("a[1, 9223372036854775808]<0>"), Err(ParseError::IntOutOfRange(Position { line: 1, column: 6 }, "9223372036854775808".to_string())));
+    assert_eq!(parse("a[-9223372036854775809]<0>"), Err(ParseError::IntOutOfRange(Position { line: 1, column: 3 }, "-9223372036854775809".to_string())));
+}
+
+#[test]
+fn test_sorted_data()
+{
+    let recv = Process::Recv(ChName("a".to_string()), vec![
+        DataBinder(DataVar("x".to_string()), Some(Sort::Int)),
+        DataBinder(DataVar("y".to_string()), Some(Sort::Tuple(vec![Sort::Str, Sort::Bool]))),
+        DataBinder(DataVar("z".to_string()), None),
+    ], ProcVar("X".to_string()), TagVar("k".to_string()), Box::new(Process::End));
+
+    run_tests(vec![
+        ("a[x: int, y: (str, bool), z](X, k).0", &recv),
+        ("a[x:int, y :(str,bool), z](X, k).0", &recv),
+    ]);
+    assert_round_trip(&recv);
+}
+
+#[test]
+fn test_local_types()
+{
+    let var = |x: &str| DataVar(x.to_string());
+    let lt = LocalType::RAbs("t".to_string(), Box::new(
+        LocalType::Send("B".to_string(), vec![
+            ("lb_1".to_string(), vec![Expr::Val(Value::Int(1)), Expr::Tuple(vec![Expr::Val(Value::Str("s".to_string())), Expr::Var(var("x"))])],
+                LocalType::Recv("B".to_string(), vec![
+                    ("lb_2".to_string(), vec![DataBinder(var("y"), Some(Sort::Tuple(vec![Sort::Str, Sort::Bool]))), DataBinder(var("z"), None)],
+                        LocalType::RVar("t".to_string())),
+                ])),
+            ("lb_3".to_string(), vec![], LocalType::End),
+        ])
+    ));
+
+    assert_eq!(parse_local_type("rec t. B!{lb_1(1, (\"s\", x)).B?lb_2(y: (str, bool), z).t, lb_3.end}"), Ok(lt.clone()));
+    assert_eq!(parse_local_type("rec t.\n  B!{lb_1(1, (\"s\", x)).B?{lb_2(y:(str,bool), z).t},\n     lb_3.end}"), Ok(lt));
+    assert_eq!(parse_local_type("A?l().end"), Ok(LocalType::Recv("A".to_string(), vec![("l".to_string(), vec![], LocalType::End)])));
+
+    let expect_pos = |input: &str, line: usize, column: usize| {
+        match parse_local_type(input) {
+            Ok(lt) => panic!("Expected an error for {:?}, but got {:?}", input, lt),
+            Err(err) => assert_eq!(err.position(), Position { line, column }, "{}", err),
+        }
+    };
+
+    expect_pos("rec end. B!l.end", 1, 5);
+    expect_pos("B!{l.end m.end}", 1, 10);
+    expect_pos("B?l(x: float).end", 1, 8);
+    expect_pos("B?l(1).end", 1, 5);
+    expect_pos("B!l(x: int).end", 1, 6);
+    expect_pos("B?l", 1, 4);
+    expect_pos("end end", 1, 5);
+    assert!(matches!(parse_local_type("B!l.end $"), Err(ParseError::UnexpectedChar(Position { line: 1, column: 9 }, '$'))));
+}
\ No newline at end of file
@@ -0,0 +1,83 @@
+use crate::rollpi::local_types::{LocalType, PartLocalType};
+use crate::rollpi::parser::parse;
+use crate::rollpi::printer::{PrettyConfig, StateFmt};
+use crate::rollpi::syntax::{ProcTag, TagKey, TaggedProc};
+
+fn big_encoding() -> crate::rollpi::syntax::Process
+{
+    let lt = LocalType::RAbs("t".to_string(), Box::new(
+        LocalType::Send("B".to_string(), vec![
+            ("lb_1".to_string(), vec![], LocalType::Recv("B".to_string(), vec![("lb_2".to_string(), vec![], LocalType::RVar("t".to_string()))])),
+            ("lb_3".to_string(), vec![], LocalType::End),
+        ])
+    ));
+
+    PartLocalType::new("A".to_string(), lt).to_process().unwrap()
+}
+
+#[test]
+fn test_display_short()
+{
+    let proc = parse("a(X, k).(X | b<roll k>) | c<0>").unwrap();
+
+    assert_eq!(format!("{}", proc), "a(X, k).(X | b<roll k>) | c<0>");
+    assert_eq!(format!("{}", parse("roll @k").unwrap()), "roll @k");
+}
+
+#[test]
+fn test_display_width()
+{
+    let proc = big_encoding();
+
+    let flat = format!("{:#}", proc);
+    assert!(!flat.contains('\n'));
+
+    for width in [20, 40, 80, 120] {
+        let pretty = format!("{:w$}", proc, w = width);
+        assert!(pretty.contains('\n'));
+        assert_eq!(parse(&pretty).unwrap(), proc, "{}", pretty);
+    }
+
+    // Lines only go over the width when a single name does not fit
+    let cfg = PrettyConfig { width: 80, indent: 4 };
+    let pretty = cfg.process(&proc);
+    assert_eq!(parse(&pretty).unwrap(), proc);
+    assert!(pretty.lines().all(|l| l.len() <= 80), "{}", pretty);
+    assert!(pretty.lines().any(|l| l.starts_with("    ")));
+
+    // The width counts characters, not bytes
+    let proc = parse("a[\"ééééé\"]<b<0>>").unwrap();
+    assert_eq!(PrettyConfig { width: 16, indent: 4 }.process(&proc), "a[\"ééééé\"]<b<0>>");
+}
+
+#[test]
+fn test_display_state()
+{
+    let tagged = parse("a<0> | b(X, g).roll g").unwrap()
+        .to_tagged_process(ProcTag::PTKey(TagKey("k".to_string())));
+
+    assert_eq!(format!("{}", tagged), "k: (a<0> | b(X, g).roll g)");
+
+    let state = tagged.to_prime_state();
+    assert_eq!(format!("{}", state[1]), "sp_1_k: b(X, g).roll g");
+    assert_eq!(format!("{}", StateFmt(&state)), "sp_0_k: a<0>\nsp_1_k: b(X, g).roll g");
+}
+
+#[test]
+fn test_display_memory()
+{
+    let sender = TaggedProc {
+        tag: ProcTag::PTKey(TagKey("k1".to_string())),
+        proc: parse("a<0>").unwrap(),
+    };
+    let receiver = TaggedProc {
+        tag: ProcTag::PTKey(TagKey("k2".to_string())),
+        proc: parse("a(X, g).X").unwrap(),
+    };
+
+    let cfg = PrettyConfig::default();
+    assert_eq!(cfg.memory(&sender, &receiver, &TagKey("k".to_string())), "[k1: a<0> | k2: a(X, g).X; k]");
+
+    let narrow = PrettyConfig { width: 10, indent: 2 };
+    assert_eq!(narrow.memory(&sender, &receiver, &TagKey("k".to_string())), "[\n  k1: a<0>\n  | k2: a(X, g).\n          X\n; k]");
+}
@@ -0,0 +1,631 @@
+use std::time::Duration;
+
+use crate::rollpi::environment::components::choice::{ChoiceOracle, ScriptedChoices};
+use crate::rollpi::environment::components::picker::Strategy;
+use crate::rollpi::environment::components::strategies::SimpleOrder::SimpleOrderStrat;
+use crate::rollpi::environment::components::strategies::SimpleRandom::{RandWeights, SimpleRandStrat};
+use crate::rollpi::environment::generator::{CrashPolicy, Generator};
+use crate::rollpi::environment::runner::{HistoryGraph, PartyStatus, RollbackHandle, RunOutcome, RunReport, Runner, RunningContext};
+use crate::rollpi::errors::{LaunchError, RollbackError};
+use crate::rollpi::logger::trace::{MemorySink, TraceEvent, TraceTarget};
+use crate::rollpi::parser::{parse, parse_local_type};
+use crate::rollpi::local_types::{ChoicePoint, LocalType, PartLocalType};
+use crate::rollpi::syntax::{all_chn_names_proc, PrimProcess, Process, ProcTag, SessionId, TagKey, Value};
+
+// The participant ids are also the names of the log files, so every test uses its own ids
+fn run(conf: Vec<(&str, &str)>) -> RunReport
+{
+    run_with(conf, || None, |_| ())
+}
+
+// The history of the default session
+fn history(report: &RunReport) -> &HistoryGraph
+{
+    &report.histories[&SessionId::default()]
+}
+
+fn random(seed: u64) -> Option<Box<dyn Strategy>>
+{
+    Some(Box::new(SimpleRandStrat::new(Some(seed), RandWeights::default())))
+}
+
+fn run_with(conf: Vec<(&str, &str)>, strategy: impl Fn() -> Option<Box<dyn Strategy>>, setup: impl FnOnce(&mut Generator)) -> RunReport
+{
+    let mut generator = Generator::default();
+    for (id, proc) in conf {
+        let state = parse(proc).unwrap()
+            .to_tagged_process(ProcTag::PTKey(TagKey(id.to_string())))
+            .to_prime_state();
+        generator.take_participant_conf(state, Some(id.to_string()), strategy()).unwrap();
+    }
+
+    setup(&mut generator);
+
+    let (parties, hist) = generator.generate_participants().unwrap();
+    Runner::new(RunningContext { parties, hist }).run()
+}
+
+#[test]
+fn test_run_terminates()
+{
+    let report = run(vec![("TermA", "a<0>"), ("TermB", "a(X, k).X")]);
+
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+    assert!(report.pending.is_empty());
+    assert_eq!(report.parties.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(), vec!["TermA", "TermB"]);
+    assert!(report.parties.iter().all(|p| p.live_state.is_empty()));
+
+    // The received 0 ended at the receiver, the communication is remembered by the history
+    assert!(matches!(report.parties[1].dead_state[..], [ref p] if matches!(p.proc, PrimProcess::End)));
+    assert_eq!(history(&report).memories.len(), 1);
+    assert!(history(&report).to_dot().contains("\\nTermB\""));
+}
+
+#[test]
+fn test_run_deadlocks()
+{
+    let report = run(vec![("DeadA", "a<0>"), ("DeadB", "b(X, k).X")]);
+
+    assert_eq!(report.outcome, RunOutcome::Deadlocked);
+    assert!(history(&report).memories.is_empty());
+
+    // The message on a is never received and the receive on b is stuck
+    assert_eq!(report.pending.len(), 1);
+    assert_eq!(report.pending[0].0, "a");
+    assert!(report.parties[0].live_state.is_empty());
+    assert!(matches!(report.parties[1].live_state[..], [ref p] if matches!(p.proc, PrimProcess::Recv(..))));
+    assert!(format!("{}", report).starts_with("Run deadlocked"));
+}
+
+#[test]
+fn test_run_random_strategy()
+{
+    // Either send of A can go first, B takes both messages in whichever order they come
+    for seed in 0..4 {
+        let report = run_with(
+            vec![("RandA", "a<0> | b<0>"), ("RandB", "a(X, k).b(Y, h).(X | Y)")],
+            || random(seed),
+            |_| (),
+        );
+
+        assert_eq!(report.outcome, RunOutcome::Terminated, "seed {}", seed);
+        assert_eq!(history(&report).memories.len(), 2);
+    }
+}
+
+#[test]
+fn test_crash_rollback()
+{
+    // CrashB is killed right after receiving on a, before it can send on c
+    let report = run_with(
+        vec![("CrashA", "a<0>"), ("CrashB", "a(X, k).c<X>"), ("CrashC", "c(Y, h).Y")],
+        || random(0),
+        |g| g.inject_crash("CrashB", 1),
+    );
+
+    assert_eq!(report.parties.iter().map(|p| p.status).collect::<Vec<_>>(),
+        vec![PartyStatus::Finished, PartyStatus::Crashed, PartyStatus::Finished]);
+    assert!(history(&report).crashed.contains("CrashB"));
+
+    // The communication on a is rolled back, so CrashA sends again and nobody receives it
+    assert!(history(&report).memories.is_empty());
+    assert_eq!(report.pending.iter().map(|(ch, _)| ch.as_str()).collect::<Vec<_>>(), vec!["a"]);
+    assert!(matches!(report.parties[2].live_state[..], [ref p] if matches!(p.proc, PrimProcess::Recv(..))));
+    assert_eq!(report.outcome, RunOutcome::Deadlocked);
+}
+
+#[test]
+fn test_run_trace()
+{
+    let memory = MemorySink::default();
+    let report = run_with(
+        vec![("TraceA", "a<0>"), ("TraceB", "a(X, k).X")],
+        || random(1),
+        |g| g.set_trace_targets(vec![TraceTarget::Memory(memory.clone())]),
+    );
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+
+    let events = memory.records().into_iter().map(|r| (r.source, r.event)).collect::<Vec<_>>();
+    assert_eq!(events.len(), 3);
+    assert!(matches!(&events[0], (s, TraceEvent::Send { .. }) if s == "TraceA"));
+    assert!(matches!(&events[1], (s, TraceEvent::Recv { sender, .. }) if s == "TraceB" && sender == "TraceA"));
+    assert!(matches!(&events[2], (s, TraceEvent::End { .. }) if s == "TraceB"));
+}
+
+#[test]
+fn test_run_data()
+{
+    let memory = MemorySink::default();
+    let report = run_with(
+        vec![("DataA", "a[5, \"hi\"]<0>"), ("DataB", "a[x, y](X, k).b[(y, x)]<X>"), ("DataC", "b[z](Y, h).Y")],
+        || None,
+        |g| g.set_trace_targets(vec![TraceTarget::Memory(memory.clone())]),
+    );
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+
+    // DataB forwards the values it received as a pair
+    let sent = memory.records().into_iter()
+        .filter_map(|r| match r.event { TraceEvent::Send { data, .. } => Some((r.source, data)), _ => None })
+        .collect::<Vec<_>>();
+    assert!(sent.contains(&("DataA".to_string(), vec![Value::Int(5), Value::Str("hi".to_string())])));
+    assert!(sent.contains(&("DataB".to_string(), vec![Value::Tuple(vec![Value::Str("hi".to_string()), Value::Int(5)])])));
+}
+
+#[test]
+fn test_run_sorted_data()
+{
+    let report = run(vec![
+        ("SortA", "a[5]<0>"),
+        ("SortB", "a[x: str](X, k).b[x]<0>"),
+        ("SortC", "b[y: int](Y, h).0 | b[z](Z, j).0"),
+        ("SortD", "a[\"hi\"]<0>"),
+    ]);
+    assert_eq!(report.outcome, RunOutcome::Deadlocked);
+
+    // SortB only takes the string, the integer of SortA stays on the channel whatever came first
+    assert_eq!(report.pending.len(), 1);
+    assert_eq!(report.pending[0].1.sender_id, "SortA");
+    assert_eq!(report.pending[0].1.data, vec![Value::Int(5)]);
+
+    // The string forwarded by SortB is only taken by the receive without a sort
+    let party_c = report.parties.iter().find(|p| p.id == "SortC").unwrap();
+    assert!(matches!(&party_c.live_state[..], [p] if matches!(&p.proc, PrimProcess::Recv(ch, ..) if ch.0 == "b")), "{:?}", party_c.live_state);
+}
+
+#[test]
+fn test_local_type_payload()
+{
+    // PayB forwards the value it got from PayA to PayC
+    let memory = MemorySink::default();
+    let conf = [
+        ("PayA", "PayB!lb(7).end"),
+        ("PayB", "PayA?lb(x: int).PayC!fwd((x, \"b\")).end"),
+        ("PayC", "PayB?fwd(y: (int, str)).end"),
+    ];
+    let mut generator = Generator::default();
+    for (id, lt) in conf {
+        let proc = PartLocalType::new(id.to_string(), parse_local_type(lt).unwrap()).to_process().unwrap();
+        generator.take_participant_conf(proc.to_tagged_process(ProcTag::PTKey(TagKey(id.to_string()))).to_prime_state(), Some(id.to_string()), None).unwrap();
+    }
+    generator.set_trace_targets(vec![TraceTarget::Memory(memory.clone())]);
+    let (parties, hist) = generator.generate_participants().unwrap();
+    let report = Runner::new(RunningContext { parties, hist }).run();
+
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+    assert!(report.pending.is_empty(), "{:?}", report.pending);
+
+    // The values are sent by the senders themselves, and the one of PayA reaches the binder of PayB
+    let data_sends = memory.records().into_iter()
+        .filter_map(|r| match r.event {
+            TraceEvent::Send { channel, data, .. } if channel.0.starts_with("comm_dat_") => Some((r.source, channel.0, data)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(data_sends, vec![
+        ("PayA".to_string(), "comm_dat_PayA_PayB_lb".to_string(), vec![Value::Int(7)]),
+        ("PayB".to_string(), "comm_dat_PayB_PayC_fwd".to_string(), vec![Value::Tuple(vec![Value::Int(7), Value::Str("b".to_string())])]),
+    ]);
+    assert!(memory.records().iter().any(|r| r.source == "PayC"
+        && matches!(&r.event, TraceEvent::Recv { channel, .. } if channel.0 == "comm_dat_PayB_PayC_fwd")));
+}
+
+#[test]
+fn test_open_data_rejected()
+{
+    // Nothing binds x, so there is no value to send
+    let mut generator = Generator::default();
+    let state = parse("a[x]<0>").unwrap().to_tagged_process(ProcTag::PTKey(TagKey("OpenA".to_string()))).to_prime_state();
+    assert_eq!(generator.take_participant_conf(state, Some("OpenA".to_string()), None), Err(LaunchError::InvalidConfiguration));
+    assert!(!generator.take_session_conf(SessionId("open".to_string()), vec![("A".to_string(), parse("a[x]<0>").unwrap(), None)]));
+}
+
+#[test]
+fn test_order_defers_sends()
+{
+    let memory = MemorySink::default();
+    let report = run_with(
+        vec![
+            ("DeferA", "late<0>"),
+            ("DeferB", "comm_a<0> | comm_a(X, k).(comm_b<0> | comm_b(Y, h).late(Z, j).Z)"),
+        ],
+        || None,
+        |g| g.set_trace_targets(vec![TraceTarget::Memory(memory.clone())]),
+    );
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+
+    // The send on a channel that is not a comm_ one waits until DeferB is done with its communications
+    let events = memory.records().into_iter().map(|r| (r.source, r.event)).collect::<Vec<_>>();
+    let late = events.iter().position(|(s, e)| s == "DeferA" && matches!(e, TraceEvent::Send { .. })).unwrap();
+    let comm_b = events.iter().position(|(_, e)| matches!(e, TraceEvent::Recv { channel, .. } if channel.0 == "comm_b")).unwrap();
+    assert!(comm_b < late, "{:?}", events);
+}
+
+#[test]
+fn test_run_timeout()
+{
+    let mut generator = Generator::default();
+    for (id, proc) in [("LoopA", "a<0>"), ("LoopB", "a(X, k).roll k")] {
+        let state = parse(proc).unwrap().to_tagged_process(ProcTag::PTKey(TagKey(id.to_string()))).to_prime_state();
+        generator.take_participant_conf(state, Some(id.to_string()), None).unwrap();
+    }
+
+    // LoopB keeps rolling back the communication, so the run never gets quiescent
+    let (parties, hist) = generator.generate_participants().unwrap();
+    let mut runner = Runner::new(RunningContext { parties, hist });
+    runner.set_timeout(Duration::from_millis(200));
+    let report = runner.run();
+
+    assert_eq!(report.outcome, RunOutcome::TimedOut);
+    assert!(format!("{}", report).starts_with("Run timed out"));
+}
+
+#[test]
+fn test_crash_abort()
+{
+    let report = run_with(
+        vec![("AbortA", "a<0>"), ("AbortB", "a(X, k).c<X>"), ("AbortC", "c(Y, h).Y")],
+        || random(0),
+        |g| {
+            g.inject_crash("AbortB", 1);
+            g.set_crash_policy(CrashPolicy::Abort);
+        },
+    );
+
+    assert_eq!(report.parties.iter().map(|p| p.status).collect::<Vec<_>>(),
+        vec![PartyStatus::Aborted, PartyStatus::Crashed, PartyStatus::Aborted]);
+    assert_eq!(report.outcome, RunOutcome::Aborted);
+    assert!(format!("{}", report).contains("AbortB crashed"));
+}
+
+#[test]
+fn test_run_fresh_channel()
+{
+    let report = run(vec![
+        ("FreshT", "go<0>"),
+        ("FreshA", "go(X, k).new c.(a<c<0>> | c(Y, h).Y)"),
+        ("FreshB", "a(Z, j).Z"),
+    ]);
+
+    // B replies on the channel allocated when the restriction was opened
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+    assert!(report.pending.is_empty());
+    assert_eq!(history(&report).memories.len(), 3);
+}
+
+#[test]
+fn test_run_fresh_channel_in_use()
+{
+    let report = run(vec![
+        ("InUseA", "a<0>"),
+        ("InUseB", "a(X, k).new c.c<0>"),
+        ("InUseC", "_ch_0_c(Y, h).Y"),
+    ]);
+
+    // The channel opened by B skips the name C receives on, so nobody takes the message
+    assert_eq!(report.outcome, RunOutcome::Deadlocked);
+    assert_eq!(report.pending.iter().map(|(ch, _)| ch.as_str()).collect::<Vec<_>>(), vec!["_ch_1_c"]);
+    assert!(matches!(report.parties[2].live_state[..], [ref p] if matches!(p.proc, PrimProcess::Recv(..))));
+    assert_eq!(history(&report).memories.len(), 1);
+}
+
+// Runs one instance of the configuration in every given session
+fn run_sessions(sessions: Vec<(&str, Vec<(&str, Process)>)>, setup: impl FnOnce(&mut Generator)) -> RunReport
+{
+    let mut generator = Generator::default();
+    for (session, conf) in sessions {
+        let conf = conf.into_iter().map(|(party, proc)| (party.to_string(), proc, random(0))).collect();
+        assert!(generator.take_session_conf(SessionId(session.to_string()), conf));
+    }
+
+    setup(&mut generator);
+
+    let (parties, hist) = generator.generate_participants().unwrap();
+    Runner::new(RunningContext { parties, hist }).run()
+}
+
+#[test]
+fn test_run_sessions()
+{
+    // A tells B to stop, encoded in each session on its own channels
+    let party_a = PartLocalType::new("A".to_string(), LocalType::Send("B".to_string(), vec![("stop".to_string(), vec![], LocalType::End)]));
+    let party_b = PartLocalType::new("B".to_string(), LocalType::Recv("A".to_string(), vec![("stop".to_string(), vec![], LocalType::End)]));
+    let instance = |session: &str| {
+        let session_id = SessionId(session.to_string());
+        vec![
+            ("A", party_a.clone().to_process_in(&session_id).unwrap()),
+            ("B", party_b.clone().to_process_in(&session_id).unwrap()),
+        ]
+    };
+    assert!(all_chn_names_proc(&instance("SessOne")[0].1).iter().all(|ch| ch.starts_with("SessOne_")));
+
+    let report = run_sessions(vec![("SessOne", instance("SessOne")), ("SessTwo", instance("SessTwo"))], |_| ());
+
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+    assert_eq!(report.parties.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(),
+        vec!["SessOne.A", "SessOne.B", "SessTwo.A", "SessTwo.B"]);
+    assert_eq!(report.histories.keys().map(|s| s.0.as_str()).collect::<Vec<_>>(), vec!["SessOne", "SessTwo"]);
+
+    // Each history only saw the communications of its own session
+    let one = &report.histories[&SessionId("SessOne".to_string())];
+    let two = &report.histories[&SessionId("SessTwo".to_string())];
+    assert!(!one.memories.is_empty());
+    assert_eq!(one.memories.len(), two.memories.len());
+    assert!(one.tag_owner.values().all(|owner| owner.starts_with("SessOne.")));
+}
+
+#[test]
+fn test_session_rollback_isolated()
+{
+    // Both sessions use the channel a, only the first one crashes and rolls back
+    let conf = || vec![
+        ("A", parse("a<0>").unwrap()),
+        ("B", parse("a(X, k).c<X>").unwrap()),
+        ("C", parse("c(Y, h).Y").unwrap()),
+    ];
+    let report = run_sessions(vec![("IsoOne", conf()), ("IsoTwo", conf())], |g| g.inject_crash("IsoOne.B", 1));
+
+    let one = &report.histories[&SessionId("IsoOne".to_string())];
+    let two = &report.histories[&SessionId("IsoTwo".to_string())];
+    assert!(one.crashed.contains("IsoOne.B"));
+    assert!(one.memories.is_empty());
+    assert!(two.crashed.is_empty());
+    assert_eq!(two.memories.len(), 2);
+
+    // The resent message of the first session stays in its own channel
+    assert_eq!(report.pending.len(), 1);
+    assert_eq!(report.pending[0].1.sender_id, "IsoOne.A");
+    assert!(report.parties.iter().filter(|p| p.id.starts_with("IsoTwo.")).all(|p| p.live_state.is_empty()));
+    assert_eq!(report.outcome, RunOutcome::Deadlocked);
+}
+
+// A send to the other party, or a receive from it, with branches that carry no values
+fn choice(other: &str, send: bool, branches: Vec<(&str, LocalType)>) -> LocalType
+{
+    if send {
+        LocalType::Send(other.to_string(), branches.into_iter().map(|(l, lt)| (l.to_string(), vec![], lt)).collect())
+    } else {
+        LocalType::Recv(other.to_string(), branches.into_iter().map(|(l, lt)| (l.to_string(), vec![], lt)).collect())
+    }
+}
+
+// A chooses up to three times whether to go on (lb_1) or to stop (lb_2), B follows the choices of A
+// The oracle, if any, makes the choices of A
+fn run_choices(a: &str, b: &str, mut strategy_a: Box<dyn Strategy>, oracle: Option<Box<dyn ChoiceOracle>>, memory: &MemorySink) -> RunReport
+{
+    let nested = |other: &str, send: bool| {
+        (0..3).fold(LocalType::End, |next, _| {
+            choice(other, send, vec![("lb_1", next), ("lb_2", LocalType::End)])
+        })
+    };
+
+    let (proc_a, points) = PartLocalType::new(a.to_string(), nested(b, true)).to_process_with_choices().unwrap();
+    let proc_b = PartLocalType::new(b.to_string(), nested(a, false)).to_process().unwrap();
+    if let Some(oracle) = oracle {
+        strategy_a.set_choice_oracle(points, oracle);
+    }
+
+    let mut generator = Generator::default();
+    for (id, proc, strategy) in [(a, proc_a, Some(strategy_a)), (b, proc_b, None)] {
+        let state = proc.to_tagged_process(ProcTag::PTKey(TagKey(id.to_string()))).to_prime_state();
+        generator.take_participant_conf(state, Some(id.to_string()), strategy).unwrap();
+    }
+    generator.set_trace_targets(vec![TraceTarget::Memory(memory.clone())]);
+
+    let (parties, hist) = generator.generate_participants().unwrap();
+    Runner::new(RunningContext { parties, hist }).run()
+}
+
+// The labels the participant chose towards the other one, in order: at every choice point the first offer it sent
+fn chosen_labels(memory: &MemorySink, id: &str, other: &str) -> Vec<String>
+{
+    let offer_ch = format!("comm_snd_{}_{}", id, other);
+    let branch_prefix = format!("comm_branch_{}_{}_", id, other);
+    let mut points = vec![];
+    let mut labels = vec![];
+    for r in memory.records().into_iter().filter(|r| r.source == id) {
+        let TraceEvent::Send { channel, tag, payload: Process::Recv(branch, ..), .. } = r.event else { continue };
+        let Some(label) = branch.0.strip_prefix(&branch_prefix).filter(|_| channel.0 == offer_ch) else { continue };
+
+        let point = match tag {
+            ProcTag::PTKey(key) | ProcTag::PTSplit(_, _, key) => key,
+        };
+        if !points.contains(&point) {
+            points.push(point);
+            labels.push(label.to_string());
+        }
+    }
+    labels
+}
+
+#[test]
+fn test_choices_without_oracle()
+{
+    // Every offer is sent and drained, one of them runs
+    let memory = MemorySink::default();
+    let report = run_choices("PlainA", "PlainB", Box::new(SimpleOrderStrat::default()), None, &memory);
+
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+    assert!(report.pending.is_empty(), "{:?}", report.pending);
+
+    let branches = || vec![("l1", LocalType::End), ("l2", LocalType::End)];
+    let mut generator = Generator::default();
+    for (id, lt) in [("TwoA", choice("TwoB", true, branches())), ("TwoB", choice("TwoA", false, branches()))] {
+        let proc = PartLocalType::new(id.to_string(), lt).to_process().unwrap();
+        generator.take_participant_conf(proc.to_tagged_process(ProcTag::PTKey(TagKey(id.to_string()))).to_prime_state(), Some(id.to_string()), None).unwrap();
+    }
+    let (parties, hist) = generator.generate_participants().unwrap();
+    let report = Runner::new(RunningContext { parties, hist }).run();
+
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+    assert!(report.pending.is_empty(), "{:?}", report.pending);
+}
+
+#[test]
+fn test_scripted_choices()
+{
+    let memory = MemorySink::default();
+    let oracle = ScriptedChoices::new(vec!["lb_1", "lb_1", "lb_2"]);
+    let report = run_choices("ScriptA", "ScriptB", Box::new(SimpleOrderStrat::default()), Some(Box::new(oracle)), &memory);
+
+    // The offers that were not chosen are sent after the chosen one and dropped
+    assert_eq!(chosen_labels(&memory, "ScriptA", "ScriptB"), vec!["lb_1", "lb_1", "lb_2"]);
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+    assert!(report.pending.is_empty(), "{:?}", report.pending);
+}
+
+#[test]
+fn test_session_recursion_order()
+{
+    // A makes B go around the loop twice before stopping, both channel kinds carry the session prefix
+    let branches = |other: &str, send: bool| {
+        let branches = vec![("again", LocalType::RVar("t".to_string())), ("stop", LocalType::End)];
+        LocalType::RAbs("t".to_string(), Box::new(choice(other, send, branches)))
+    };
+    let session = SessionId("RecSess".to_string());
+    let (proc_a, points) = PartLocalType::new("A".to_string(), branches("B", true)).to_process_with_choices_in(&session).unwrap();
+    let proc_b = PartLocalType::new("B".to_string(), branches("A", false)).to_process_in(&session).unwrap();
+
+    let mut strategy_a = SimpleOrderStrat::default();
+    strategy_a.set_choice_oracle(points, Box::new(ScriptedChoices::new(vec!["again", "again", "stop"])));
+
+    let memory = MemorySink::default();
+    let mut generator = Generator::default();
+    let conf = vec![
+        ("A".to_string(), proc_a, Some(Box::new(strategy_a) as Box<dyn Strategy>)),
+        ("B".to_string(), proc_b, Some(Box::new(SimpleOrderStrat::default()) as Box<dyn Strategy>)),
+    ];
+    assert!(generator.take_session_conf(session.clone(), conf));
+    generator.set_trace_targets(vec![TraceTarget::Memory(memory.clone())]);
+    let (parties, hist) = generator.generate_participants().unwrap();
+    // The replicas of the recursion keep unfolding once the protocol is done
+    let mut runner = Runner::new(RunningContext { parties, hist });
+    runner.set_timeout(Duration::from_millis(500));
+    let report = runner.run();
+    assert_eq!(report.outcome, RunOutcome::TimedOut);
+
+    // Every round, B takes the choice of A before A unfolds the recursion again
+    let events = memory.records().into_iter().map(|r| (r.source, r.event)).collect::<Vec<_>>();
+    let norm_sends = events.iter().enumerate()
+        .filter(|(_, (s, e))| s == "RecSess.A" && matches!(e, TraceEvent::Send { channel, .. } if channel.0 == "RecSess_rec_norm_A_t"))
+        .map(|(i, _)| i).collect::<Vec<_>>();
+    let branch_recvs = events.iter().enumerate()
+        .filter(|(_, (s, e))| s == "RecSess.B" && matches!(e, TraceEvent::Recv { channel, .. } if channel.0.starts_with("RecSess_comm_branch_A_B_")))
+        .map(|(i, _)| i).collect::<Vec<_>>();
+    assert_eq!(branch_recvs.len(), 6, "{:?}", events);
+    assert!(norm_sends.len() >= 2, "{:?}", events);
+    assert!(norm_sends.iter().take(2).zip(branch_recvs.chunks(2)).all(|(norm, round)| round.iter().all(|b| b < norm)), "{:?}", events);
+}
+
+#[test]
+fn test_closure_choices()
+{
+    let memory = MemorySink::default();
+    let mut asked = 0;
+    let oracle = move |point: &ChoicePoint| {
+        assert_eq!((point.party.as_str(), point.to.as_str()), ("ClosureA", "ClosureB"));
+        asked += 1;
+        Some(if asked == 1 { "lb_1" } else { "lb_2" }.to_string())
+    };
+    let strategy = SimpleRandStrat::new(Some(5), RandWeights::default());
+
+    let report = run_choices("ClosureA", "ClosureB", Box::new(strategy), Some(Box::new(oracle)), &memory);
+
+    assert_eq!(chosen_labels(&memory, "ClosureA", "ClosureB"), vec!["lb_1", "lb_2"]);
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+}
+
+// Runs the configuration on another thread, the handle keeps it going until inject returns
+fn run_with_handle(conf: Vec<(&str, &str)>, memory: &MemorySink, inject: impl FnOnce(&RollbackHandle)) -> RunReport
+{
+    let mut generator = Generator::default();
+    for (id, proc) in conf {
+        let state = parse(proc).unwrap().to_tagged_process(ProcTag::PTKey(TagKey(id.to_string()))).to_prime_state();
+        generator.take_participant_conf(state, Some(id.to_string()), None).unwrap();
+    }
+    generator.set_trace_targets(vec![TraceTarget::Memory(memory.clone())]);
+
+    let (parties, hist) = generator.generate_participants().unwrap();
+    let mut runner = Runner::new(RunningContext { parties, hist });
+    let handle = runner.rollback_handle();
+    let run = std::thread::spawn(move || runner.run());
+
+    assert!(handle.wait_quiescent(Duration::from_secs(5)));
+    inject(&handle);
+    drop(handle);
+
+    run.join().unwrap()
+}
+
+fn received_tags(memory: &MemorySink) -> Vec<TagKey>
+{
+    memory.records().into_iter()
+        .filter_map(|r| match r.event {
+            TraceEvent::Recv { new_tag, .. } => Some(new_tag),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_external_rollback_tag()
+{
+    let memory = MemorySink::default();
+    let report = run_with_handle(vec![("ExtTagA", "a<0>"), ("ExtTagB", "a(X, k).X")], &memory, |handle| {
+        let tags = received_tags(&memory);
+        assert_eq!(tags.len(), 1);
+
+        // A tag without a memory is left alone
+        assert_eq!(handle.roll_back_tag(TagKey("nope".to_string())), Ok(()));
+        assert_eq!(handle.roll_back_tag(tags[0].clone()), Ok(()));
+        assert!(handle.wait_quiescent(Duration::from_secs(5)));
+    });
+
+    // The communication is undone, then happens again
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+    let tags = received_tags(&memory);
+    assert_eq!(tags.len(), 2);
+    assert_eq!(history(&report).memories.keys().cloned().collect::<Vec<_>>(), vec![ProcTag::PTKey(tags[1].clone())]);
+    assert!(memory.records().iter().any(|r| matches!(&r.event, TraceEvent::FreezeSignal { tag, .. } if *tag == ProcTag::PTKey(tags[0].clone()))));
+}
+
+#[test]
+fn test_external_rollback_last()
+{
+    let memory = MemorySink::default();
+    let report = run_with_handle(vec![("ExtLastA", "a<0> | b<0>"), ("ExtLastB", "a(X, k).b(Y, h).(X | Y)")], &memory, |handle| {
+        assert_eq!(handle.roll_back_last("Nobody", 1), Err(RollbackError::UnknownParticipant("Nobody".to_string())));
+        assert_eq!(handle.roll_back_last("ExtLastA", 1), Ok(()));
+        assert!(handle.wait_quiescent(Duration::from_secs(5)));
+    });
+
+    // Only the communication on b is rolled back, the one on a stays
+    assert_eq!(report.outcome, RunOutcome::Terminated);
+    let tags = received_tags(&memory);
+    assert_eq!(tags.len(), 3);
+    let mut kept = history(&report).memories.keys().cloned().collect::<Vec<_>>();
+    kept.sort_by_key(|tag| tag.to_string());
+    let mut expected = vec![ProcTag::PTKey(tags[0].clone()), ProcTag::PTKey(tags[2].clone())];
+    expected.sort_by_key(|tag| tag.to_string());
+    assert_eq!(kept, expected);
+}
+
+#[test]
+fn test_external_rollback_after_run()
+{
+    let mut generator = Generator::default();
+    for (id, proc) in [("ExtLoopA", "a<0>"), ("ExtLoopB", "a(X, k).roll k")] {
+        let state = parse(proc).unwrap().to_tagged_process(ProcTag::PTKey(TagKey(id.to_string()))).to_prime_state();
+        generator.take_participant_conf(state, Some(id.to_string()), None).unwrap();
+    }
+    generator.set_trace_targets(vec![]);
+
+    // The handle holds the run, but the timeout still ends it
+    let (parties, hist) = generator.generate_participants().unwrap();
+    let mut runner = Runner::new(RunningContext { parties, hist });
+    runner.set_timeout(Duration::from_millis(100));
+    let handle = runner.rollback_handle();
+    let report = runner.run();
+
+    assert_eq!(report.outcome, RunOutcome::TimedOut);
+    assert_eq!(handle.roll_back_last("ExtLoopA", 1), Err(RollbackError::RunOver));
+}
\ No newline at end of file
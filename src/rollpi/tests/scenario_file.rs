@@ -0,0 +1,96 @@
+use crate::rollpi::environment::launch::StrategyKind;
+use crate::rollpi::errors::{LocalTypeError, ParseError, Position, ScenarioError};
+use crate::rollpi::local_types::{LocalType, PartLocalType};
+use crate::rollpi::parser::parse;
+use crate::rollpi::syntax::{Expr, Value};
+use crate::scenarios::file::{parse_scenario, parse_scenario_file};
+
+#[test]
+fn test_parse_scenario_file()
+{
+    let conf = parse_scenario("\
+# A sends a process that B runs
+A = a<b<0>>
+
+B = a(X, k).
+      (X | b(Y, h).roll k)
+").unwrap();
+
+    assert_eq!(conf, vec![
+        ("A".to_string(), parse("a<b<0>>").unwrap()),
+        ("B".to_string(), parse("a(X, k).(X | b(Y, h).roll k)").unwrap()),
+    ]);
+}
+
+#[test]
+fn test_scenario_file_errors()
+{
+    let expect_pos = |text: &str, line: usize, column: usize| {
+        match parse_scenario(text) {
+            Err(ScenarioError::Process(err)) => assert_eq!(err.position(), Position { line, column }, "{}", err),
+            other => panic!("Expected a process error for {:?}, but got {:?}", text, other),
+        }
+    };
+
+    // Positions are the ones in the file, not in the process
+    expect_pos("A = a<0>\nB = b<0", 2, 8);
+    expect_pos("A = a<0>\nB = b(X, k).\n\n    X X", 4, 7);
+    assert!(matches!(parse_scenario("A = a<$>"), Err(ScenarioError::Process(ParseError::UnexpectedChar(Position { line: 1, column: 7 }, '$')))));
+
+    assert_eq!(parse_scenario("A = a<0>\n  \nfoo"), Err(ScenarioError::UnexpectedLine(3, "foo".to_string())));
+    assert_eq!(parse_scenario("  a<0>"), Err(ScenarioError::UnexpectedLine(1, "a<0>".to_string())));
+    assert_eq!(parse_scenario("A = a<0>\nA = b<0>"), Err(ScenarioError::DuplicateParticipant(2, "A".to_string())));
+    assert_eq!(parse_scenario("A = a<0>\nA : B!l.end"), Err(ScenarioError::DuplicateParticipant(2, "A".to_string())));
+    assert_eq!(parse_scenario("A B = a<0>"), Err(ScenarioError::UnexpectedLine(1, "A B = a<0>".to_string())));
+    assert_eq!(parse_scenario("strategy A : B!l.end"), Err(ScenarioError::UnexpectedLine(1, "strategy A : B!l.end".to_string())));
+}
+
+#[test]
+fn test_local_type_participants()
+{
+    let scenario = parse_scenario_file("\
+A = a<0>
+# B and C follow a protocol
+B : rec t. C!{more(1, (\"s\", true)).t,
+              stop.end}
+C : rec t. C_rec(B)
+strategy B = random
+").err();
+    // C_rec(B) does not parse as a local type, the error is on line 5
+    assert!(matches!(scenario, Some(ScenarioError::Process(ref err)) if err.position() == Position { line: 5, column: 17 }), "{:?}", scenario);
+
+    let scenario = parse_scenario_file("\
+A = a<0>
+# B and C follow a protocol
+B : rec t. C!{more(1, (\"s\", true)).t,
+              stop.end}
+C : rec t. B?{more(n: int, p: (str, bool)).t, stop.end}
+strategy B = random
+strategy C = order
+").unwrap();
+
+    let b_type = LocalType::RAbs("t".to_string(), Box::new(LocalType::Send("C".to_string(), vec![
+        ("more".to_string(), vec![Expr::Val(Value::Int(1)), Expr::Val(Value::Tuple(vec![Value::Str("s".to_string()), Value::Bool(true)]))],
+            LocalType::RVar("t".to_string())),
+        ("stop".to_string(), vec![], LocalType::End),
+    ])));
+    assert_eq!(scenario.conf.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>(), vec!["A", "B", "C"]);
+    assert_eq!(scenario.conf[1].1, PartLocalType::new("B".to_string(), b_type).to_process().unwrap());
+    assert_eq!(scenario.strategies, [("B".to_string(), StrategyKind::Random), ("C".to_string(), StrategyKind::Order)].into_iter().collect());
+}
+
+#[test]
+fn test_local_type_and_strategy_errors()
+{
+    assert_eq!(parse_scenario("A : A!l.end"),
+        Err(ScenarioError::LocalType(1, "A".to_string(), vec![LocalTypeError::SelfCommunication("A".to_string())])));
+    assert_eq!(parse_scenario("A = a<0>\n\nB : rec t. t"),
+        Err(ScenarioError::LocalType(3, "B".to_string(), vec![LocalTypeError::UnguardedRecursion("t".to_string())])));
+
+    // A strategy can come before its participant, but the participant has to be declared
+    assert!(parse_scenario_file("strategy A = determ\nA = a<0>").is_ok());
+    assert_eq!(parse_scenario_file("A = a<0>\nstrategy B = determ"), Err(ScenarioError::UnknownParticipant(2, "B".to_string())));
+    assert_eq!(parse_scenario_file("A = a<0>\nstrategy A = fast"), Err(ScenarioError::UnknownStrategy(2, "fast".to_string())));
+    assert_eq!(parse_scenario_file("A = a<0>\nstrategy A = order\nstrategy A = order"),
+        Err(ScenarioError::DuplicateStrategy(3, "A".to_string())));
+}
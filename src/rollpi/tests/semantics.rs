@@ -0,0 +1,231 @@
+use crate::rollpi::errors::SemanticsError;
+use crate::rollpi::parser::parse;
+use crate::rollpi::semantics::{ConfProc, Configuration, RunResult, Step, Transition};
+use crate::rollpi::syntax::{check_initial_conf, check_initial_conf_list, ChName, Expr, PrimProcess, Process, ProcTag, ProcVar, TagKey, TaggedPrimProc, TagVar, Value};
+use crate::scenarios::roll_pi::{basic_roll_pi_rollback, basic_roll_pi_test};
+
+fn conf(procs: Vec<(&str, &str)>) -> Configuration
+{
+    Configuration::new(procs.into_iter().map(|(id, p)| (id.to_string(), parse(p).unwrap())).collect())
+}
+
+// The printed prime processes of the configuration, sorted so the order of the processes does not matter
+fn printed_procs(conf: &Configuration) -> Vec<String>
+{
+    let mut procs = conf.procs.iter()
+        .map(|p| format!("{} > {}", p.owner, p.tagged.proc))
+        .collect::<Vec<_>>();
+    procs.sort();
+    procs
+}
+
+#[test]
+fn test_communication()
+{
+    let mut conf = Configuration::new(basic_roll_pi_test());
+
+    assert_eq!(conf.enabled_steps(), vec![Step::Com(0, 1)]);
+
+    let result = conf.run_to_quiescence(10);
+    let RunResult::Quiescent(trace) = result else { panic!("{:?}", result) };
+    assert_eq!(trace.len(), 1);
+    assert!(matches!(&trace[0], Transition::Com { sender, receiver, .. } if sender == "A" && receiver == "B"));
+
+    // The payload b<0> now runs at the receiver, the memory keeps both ends of the communication
+    assert_eq!(printed_procs(&conf), vec!["B > b<0>"]);
+    assert_eq!(conf.memories.len(), 1);
+    assert!(conf.state_of("A").is_empty());
+}
+
+#[test]
+fn test_rollback_restores_initial()
+{
+    let mut conf = Configuration::new(basic_roll_pi_rollback());
+    let initial = printed_procs(&conf);
+
+    conf.step(&Step::Com(0, 1)).unwrap();
+    let com_b = conf.enabled_steps();
+    assert_eq!(com_b.len(), 1);
+    conf.step(&com_b[0]).unwrap();
+
+    let roll = conf.enabled_steps();
+    assert!(matches!(roll[..], [Step::Roll(_)]));
+    let transition = conf.step(&roll[0]).unwrap();
+    assert_eq!(transition, Transition::Roll { owner: "B".to_string(), key: TagKey("_tag_0".to_string()) });
+
+    // Rolling back the first communication also undoes the one on b that depends on it
+    assert_eq!(printed_procs(&conf), initial);
+    assert!(conf.memories.is_empty());
+
+    // The configuration can roll back forever, so it never becomes quiescent
+    assert!(matches!(conf.run_to_quiescence(20), RunResult::StepLimit(trace) if trace.len() == 20));
+}
+
+#[test]
+fn test_rollback_keeps_independent()
+{
+    let mut conf = conf(vec![
+        ("A", "a<0> | b<0>"),
+        ("B", "a(X, k).c(Y, h).roll k"),
+        ("C", "b(Z, l).0"),
+        ("D", "c<0>"),
+    ]);
+
+    // Communications on a, b and c, the one on b is concurrent with the others
+    while let Some(step) = conf.enabled_steps().into_iter().find(|s| matches!(s, Step::Com(..))) {
+        conf.step(&step).unwrap();
+    }
+    assert_eq!(conf.memories.len(), 3);
+
+    let roll = conf.enabled_steps();
+    conf.step(&roll[0]).unwrap();
+
+    // The communications on a and c are undone, the one on b stays
+    assert_eq!(conf.memories.len(), 1);
+    assert_eq!(printed_procs(&conf), vec!["A > a<0>", "B > a(X, k).c(Y, h).roll k", "C > 0", "D > c<0>"]);
+}
+
+#[test]
+fn test_step_errors()
+{
+    let mut conf = conf(vec![("A", "a<0>"), ("B", "b(X, k).X")]);
+
+    assert!(conf.is_quiescent());
+    assert_eq!(conf.step(&Step::Com(0, 1)), Err(SemanticsError::NoCommunication(0, 1)));
+    assert_eq!(conf.step(&Step::Com(0, 5)), Err(SemanticsError::NoCommunication(0, 5)));
+    assert_eq!(conf.step(&Step::Roll(0)), Err(SemanticsError::NoRoll(0)));
+
+    // A roll without its memory is stuck
+    let conf = self::conf(vec![("A", "roll @k")]);
+    assert!(matches!(conf.procs[0].tagged.proc, PrimProcess::RollK(_)));
+    assert!(conf.is_quiescent());
+}
+
+#[test]
+fn test_scope_extrusion()
+{
+    let mut conf = conf(vec![
+        ("T", "go<0>"),
+        ("A", "go(X, k).new c.(a<c<0>> | c(Y, h).Y)"),
+        ("B", "a(Z, j).Z"),
+    ]);
+
+    // The restriction is opened on a fresh channel, which B learns through a and then uses to reply
+    let result = conf.run_to_quiescence(10);
+    let RunResult::Quiescent(trace) = result else { panic!("{:?}", result) };
+    let channels = trace.iter()
+        .map(|t| match t { Transition::Com { channel: ChName(ch), .. } => ch.clone(), _ => panic!("{}", t) })
+        .collect::<Vec<_>>();
+    assert_eq!(channels, vec!["go", "a", "_ch_0_c"]);
+    assert_eq!(printed_procs(&conf), vec!["A > 0"]);
+
+    // Rolling back the first communication brings the restriction back
+    conf.procs.push(ConfProc {
+        owner: "T".to_string(),
+        tagged: TaggedPrimProc { tag: ProcTag::PTKey(TagKey("T".to_string())), proc: PrimProcess::RollK(TagKey("_tag_0".to_string())) },
+    });
+    conf.step(&Step::Roll(conf.procs.len() - 1)).unwrap();
+    assert!(conf.memories.is_empty());
+    assert!(printed_procs(&conf).contains(&"A > go(X, k).new c.(a<c<0>> | c(Y, h).Y)".to_string()));
+}
+
+#[test]
+fn test_fresh_names_skip_the_names_in_use()
+{
+    let mut conf = conf(vec![
+        ("A", "a<0>"),
+        ("B", "a(X, k).new c.c<0>"),
+        ("C", "_ch_0_c(Y, h).Y"),
+        ("D", "roll @_tag_0"),
+    ]);
+
+    // The created channel and key take the next names, C does not receive on the channel opened by B
+    //     and D has no memory to roll back to
+    let result = conf.run_to_quiescence(10);
+    let RunResult::Quiescent(trace) = result else { panic!("{:?}", result) };
+    assert_eq!(trace.len(), 1);
+    assert!(matches!(&trace[0], Transition::Com { key: TagKey(key), .. } if key == "_tag_1"));
+    assert_eq!(printed_procs(&conf), vec!["B > _ch_1_c<0>", "C > _ch_0_c(Y, h).Y", "D > roll @_tag_0"]);
+    assert_eq!(conf.memories.len(), 1);
+}
+
+#[test]
+fn test_substitution_avoids_capture()
+{
+    let body = parse("new c.(X | c<0>)").unwrap();
+    let payload = parse("c<0>").unwrap();
+
+    let result = body.substitution_on_trigger(ProcVar("X".to_string()), &payload, TagVar("k".to_string()), &TagKey("t".to_string()));
+    assert_eq!(result, parse("new c_0.(c<0> | c_0<0>)").unwrap());
+
+    // Nothing to rename when the substituted process does not use the restricted channel
+    let body = parse("new c.(X | c<0>)").unwrap();
+    let result = body.substitution_on_trigger(ProcVar("X".to_string()), &parse("d<0>").unwrap(), TagVar("k".to_string()), &TagKey("t".to_string()));
+    assert_eq!(result, parse("new c.(d<0> | c<0>)").unwrap());
+}
+
+#[test]
+fn test_initial_conf_restrictions()
+{
+    assert!(check_initial_conf(&parse("new a.(a<0> | a(X, k).X)").unwrap()));
+    assert!(check_initial_conf(&parse("a(X, k).new b.b<X>").unwrap()));
+    // Used outside of its restriction, or bound twice
+    assert!(!check_initial_conf(&parse("new a.a<0> | a(X, k).X").unwrap()));
+    assert!(!check_initial_conf(&parse("new a.a<0> | new a.a(X, k).X").unwrap()));
+    assert!(!check_initial_conf_list(&vec![parse("new a.a<0>").unwrap(), parse("new a.a(X, k).X").unwrap()]));
+    assert!(!check_initial_conf_list(&vec![parse("new a.a<0>").unwrap(), parse("a(X, k).X").unwrap()]));
+}
+
+#[test]
+fn test_data_communication()
+{
+    let mut conf = conf(vec![
+        ("A", "a[5, \"hi\"]<0>"),
+        ("B", "a[x, y](X, k).b[(y, x)]<X>"),
+        ("C", "b[z](Y, h).0"),
+        ("D", "a[x](X, k).0"),
+    ]);
+
+    // D expects a single value, so it never matches the message of A
+    let result = conf.run_to_quiescence(10);
+    let RunResult::Quiescent(trace) = result else { panic!("{:?}", result) };
+    assert_eq!(trace.len(), 2);
+    assert_eq!(printed_procs(&conf), vec!["C > 0", "D > a[x](X, k).0"]);
+
+    // The memory keeps the values that were consumed, so a rollback sends them again
+    let memory = conf.memories.iter().find(|m| m.ids.1 == "B").unwrap();
+    assert!(matches!(&memory.sender.proc, Process::Send(_, data, _)
+        if *data == vec![Expr::Val(Value::Int(5)), Expr::Val(Value::Str("hi".to_string()))]));
+
+    conf.procs.push(ConfProc {
+        owner: "T".to_string(),
+        tagged: TaggedPrimProc { tag: ProcTag::PTKey(TagKey("T".to_string())), proc: PrimProcess::RollK(memory.key.clone()) },
+    });
+    conf.step(&Step::Roll(conf.procs.len() - 1)).unwrap();
+    assert!(printed_procs(&conf).contains(&"A > a[5, \"hi\"]<0>".to_string()));
+}
+
+#[test]
+fn test_sorted_data_communication()
+{
+    // The value of A is not a string, so B can not take it
+    let mut conf = conf(vec![("A", "a[5]<0>"), ("B", "a[x: str](X, k).b[x]<X>")]);
+    assert!(conf.is_quiescent());
+    assert_eq!(conf.step(&Step::Com(0, 1)), Err(SemanticsError::NoCommunication(0, 1)));
+
+    conf.procs.extend(Configuration::new(vec![("C".to_string(), parse("a[\"hi\"]<0>").unwrap())]).procs);
+    let result = conf.run_to_quiescence(10);
+    let RunResult::Quiescent(trace) = result else { panic!("{:?}", result) };
+    assert_eq!(trace.len(), 1);
+    assert_eq!(printed_procs(&conf), vec!["A > a[5]<0>", "B > b[\"hi\"]<0>"]);
+}
+
+#[test]
+fn test_initial_conf_data()
+{
+    assert!(check_initial_conf(&parse("a[1, (true, \"s\")]<0>").unwrap()));
+    assert!(check_initial_conf(&parse("a[x](X, k).b[(x, 1)]<X>").unwrap()));
+    // Data variables must be bound by an enclosing receiver
+    assert!(!check_initial_conf(&parse("a[x]<0>").unwrap()));
+    assert!(!check_initial_conf(&parse("a[x](X, k).0 | b[x]<0>").unwrap()));
+}
\ No newline at end of file
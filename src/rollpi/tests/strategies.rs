@@ -0,0 +1,57 @@
+use crate::rollpi::environment::components::strategies::SimpleRandom::{ActionKind, RandWeights, SimpleRandStrat};
+
+fn picks(strat: &SimpleRandStrat, candidates: &[ActionKind], n: usize) -> Vec<usize>
+{
+    (0..n).map(|_| strat.pick(candidates).unwrap()).collect()
+}
+
+#[test]
+fn test_random_replay()
+{
+    let candidates = [ActionKind::Send, ActionKind::Recv, ActionKind::Roll, ActionKind::End];
+
+    let first = SimpleRandStrat::new(Some(42), RandWeights::default());
+    let replay = SimpleRandStrat::new(Some(first.seed()), RandWeights::default());
+    let other = SimpleRandStrat::new(Some(43), RandWeights::default());
+
+    let first_picks = picks(&first, &candidates, 100);
+    assert_eq!(first_picks, picks(&replay, &candidates, 100));
+    assert_ne!(first_picks, picks(&other, &candidates, 100));
+
+    // A seed is made up when none is given
+    let unseeded = SimpleRandStrat::default();
+    assert_eq!(picks(&unseeded, &candidates, 100), picks(&SimpleRandStrat::new(Some(unseeded.seed()), RandWeights::default()), &candidates, 100));
+}
+
+#[test]
+fn test_random_uniform()
+{
+    let strat = SimpleRandStrat::new(Some(7), RandWeights::default());
+    let candidates = [ActionKind::Send, ActionKind::Send, ActionKind::Recv];
+
+    let mut counts = [0; 3];
+    for i in picks(&strat, &candidates, 3000) {
+        counts[i] += 1;
+    }
+
+    assert!(counts.iter().all(|c| (800..1200).contains(c)), "{:?}", counts);
+    assert_eq!(strat.pick(&[]), None);
+}
+
+#[test]
+fn test_random_weights()
+{
+    let weights = RandWeights { send: 3, recv: 1, roll: 0, end: 0 };
+    let strat = SimpleRandStrat::new(Some(7), weights);
+
+    let candidates = [ActionKind::Roll, ActionKind::Send, ActionKind::Recv, ActionKind::End];
+    let chosen = picks(&strat, &candidates, 4000);
+
+    // Kinds with weight 0 are never picked while something else is enabled
+    assert!(chosen.iter().all(|i| *i == 1 || *i == 2));
+    let sends = chosen.iter().filter(|i| **i == 1).count();
+    assert!((2800..3200).contains(&sends), "{}", sends);
+
+    // but still are when they are all that is left
+    assert_eq!(strat.pick(&[ActionKind::End]), Some(0));
+}
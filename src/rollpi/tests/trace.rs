@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use crate::rollpi::logger::file_log::FileLogger;
+use crate::rollpi::logger::trace::{JsonLinesSink, MemorySink, TextSink, TraceClock, TraceEvent, TraceRecord, TraceTarget, Tracer};
+use crate::rollpi::parser::parse;
+use crate::rollpi::syntax::{ChName, ProcTag, TagKey, Value};
+
+fn key(k: &str) -> ProcTag
+{
+    ProcTag::PTKey(TagKey(k.to_string()))
+}
+
+#[test]
+fn test_json_lines()
+{
+    let rec = TraceRecord {
+        seq: 3,
+        timestamp: 17,
+        source: "A".to_string(),
+        event: TraceEvent::Send { channel: ChName("a".to_string()), tag: key("k"),
+            data: vec![Value::Int(-1), Value::Str("x".to_string()), Value::Tuple(vec![Value::Bool(true)])], payload: parse("b<0>").unwrap() },
+    };
+
+    assert_eq!(JsonLinesSink::<Vec<u8>>::format(&rec),
+        r#"{"seq":3,"timestamp":17,"source":"A","event":"Send","channel":"a","tag":"k","data":[-1,"x",[true]],"payload":"b<0>"}"#);
+
+    let rec = TraceRecord {
+        event: TraceEvent::Resurrect { disappeared: key("k\"1"), tag: key("k2"), owner: "B\n".to_string() },
+        ..rec
+    };
+    assert_eq!(JsonLinesSink::<Vec<u8>>::format(&rec),
+        r#"{"seq":3,"timestamp":17,"source":"A","event":"Resurrect","disappeared":"k\"1","tag":"k2","owner":"B\n"}"#);
+}
+
+#[test]
+fn test_tracer_sinks()
+{
+    let clock = Arc::new(TraceClock::default());
+    let memory = MemorySink::default();
+
+    let mut tracer_a = Tracer::new("A".to_string(), clock.clone(), vec![TraceTarget::Memory(memory.clone()).open("A").unwrap()]);
+    let mut tracer_b = Tracer::new("B".to_string(), clock.clone(), vec![TraceTarget::Memory(memory.clone()).open("B").unwrap()]);
+
+    tracer_a.event(TraceEvent::End { tag: key("k1") });
+    tracer_b.log("only kept by the text files".to_string());
+    tracer_b.event(TraceEvent::Disappear { tag: key("k2") });
+    tracer_a.event(TraceEvent::RollRequest { tag: key("k3"), target: TagKey("k1".to_string()) });
+
+    // The sequence numbers are shared by both tracers
+    let records = memory.records();
+    assert_eq!(records.iter().map(|r| (r.seq, r.source.as_str())).collect::<Vec<_>>(), vec![(0, "A"), (1, "B"), (2, "A")]);
+    assert!(records.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+    assert_eq!(TextSink::format(&records[2].event), "INT: ROLLK k3 with target k1 \n");
+}
+
+#[test]
+fn test_missing_log_dir()
+{
+    let dir = std::env::temp_dir().join(format!("rollpi_trace_{}", std::process::id())).join("nested");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut logger = FileLogger::in_dir(&dir, "A", "log").unwrap();
+    logger.log("created\n".to_string());
+
+    assert_eq!(std::fs::read_to_string(dir.join("A.log")).unwrap(), "created\n");
+    let _ = std::fs::remove_dir_all(dir.parent().unwrap());
+}
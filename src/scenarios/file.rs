@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::rollpi::{environment::launch::StrategyKind, errors::{ParseError, Position, ScenarioError}, local_types::PartLocalType, parser::{parse, parse_local_type}, syntax::Process};
+
+// A scenario file declares one participant per line, either as <participant> = <process>
+//     or as <participant> : <local type>, which is encoded into a process
+// A line strategy <participant> = <kind> gives a strategy to a participant declared anywhere in the file
+// The process or local type can go on over the next lines as long as they are indented,
+//     lines starting with # and empty lines are skipped
+//
+//     # A sends a process that B runs
+//     A = a<b<0>>
+//     B = a(X, k).
+//           X
+//     # C and D follow a protocol
+//     C : rec t. D!{more(1).t, stop.end}
+//     D : rec t. C?{more(n: int).t, stop.end}
+//     strategy C = random
+pub fn load(path: &Path) -> Result<Vec<(String, Process)>, ScenarioError>
+{
+    load_file(path).map(|scenario| scenario.conf)
+}
+
+pub fn parse_scenario(text: &str) -> Result<Vec<(String, Process)>, ScenarioError>
+{
+    parse_scenario_file(text).map(|scenario| scenario.conf)
+}
+
+// The participants of a scenario file in the order they are declared, with the strategies given in the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioFile
+{
+    pub conf: Vec<(String, Process)>,
+    pub strategies: HashMap<String, StrategyKind>,
+}
+
+pub fn load_file(path: &Path) -> Result<ScenarioFile, ScenarioError>
+{
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| ScenarioError::Io(format!("could not read {}: {}", path.display(), err)))?;
+    parse_scenario_file(&text)
+}
+
+pub fn parse_scenario_file(text: &str) -> Result<ScenarioFile, ScenarioError>
+{
+    let mut conf: Vec<(String, Process)> = vec![];
+    let mut strategy_decls: Vec<Declaration> = vec![];
+
+    for decl in declarations(text)? {
+        if decl.kind == DeclKind::Strategy {
+            strategy_decls.push(decl);
+            continue;
+        }
+
+        if conf.iter().any(|(id, _)| *id == decl.id) {
+            return Err(ScenarioError::DuplicateParticipant(decl.line, decl.id))
+        }
+
+        let proc = match decl.kind {
+            DeclKind::Process => parse(&decl.body).map_err(|err| ScenarioError::Process(decl.locate(err)))?,
+            _ => {
+                let local_type = parse_local_type(&decl.body).map_err(|err| ScenarioError::Process(decl.locate(err)))?;
+                PartLocalType::new(decl.id.clone(), local_type).to_process()
+                    .map_err(|errs| ScenarioError::LocalType(decl.line, decl.id.clone(), errs))?
+            },
+        };
+        conf.push((decl.id, proc));
+    }
+
+    // The strategies are checked once all the participants are known
+    let mut strategies = HashMap::new();
+    for decl in strategy_decls {
+        if !conf.iter().any(|(id, _)| *id == decl.id) {
+            return Err(ScenarioError::UnknownParticipant(decl.line, decl.id))
+        }
+
+        let kind = decl.body.trim().parse().map_err(|_| ScenarioError::UnknownStrategy(decl.line, decl.body.trim().to_string()))?;
+        if strategies.insert(decl.id.clone(), kind).is_some() {
+            return Err(ScenarioError::DuplicateStrategy(decl.line, decl.id))
+        }
+    }
+
+    Ok(ScenarioFile { conf, strategies })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeclKind
+{
+    Process,
+    LocalType,
+    Strategy,
+}
+
+// The text of a declaration, with where it starts in the file
+struct Declaration
+{
+    id: String,
+    kind: DeclKind,
+    body: String,
+    line: usize,
+    // Column of the first character of the body on the first line
+    column: usize,
+}
+
+impl Declaration
+{
+    // Positions in the body are relative to its first line
+    fn locate(&self, err: ParseError) -> ParseError
+    {
+        err.map_position(|Position { line, column }| Position {
+            line: self.line + line - 1,
+            column: if line == 1 { self.column + column - 1 } else { column },
+        })
+    }
+}
+
+fn declarations(text: &str) -> Result<Vec<Declaration>, ScenarioError>
+{
+    let mut decls: Vec<Declaration> = vec![];
+
+    for (i, content) in text.lines().enumerate() {
+        let line = i + 1;
+        let trimmed = content.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            // Keeps the lines of the body in step with the lines of the file
+            if let Some(decl) = decls.last_mut() {
+                decl.body.push('\n');
+            }
+            continue;
+        }
+
+        // Indented lines go on with the body of the previous declaration
+        if content.starts_with(char::is_whitespace) {
+            match decls.last_mut() {
+                Some(decl) => {
+                    decl.body.push('\n');
+                    decl.body.push_str(content);
+                    continue;
+                },
+                None => return Err(ScenarioError::UnexpectedLine(line, trimmed.to_string())),
+            }
+        }
+
+        let unexpected = || ScenarioError::UnexpectedLine(line, trimmed.to_string());
+
+        // Neither '=' nor ':' can be part of a participant name, so the first one ends the head
+        let Some(sep) = content.find(['=', ':']) else {
+            return Err(unexpected())
+        };
+        let (head, body) = (&content[..sep], &content[sep + 1..]);
+
+        let (id, kind) = match (head.split_whitespace().collect::<Vec<_>>().as_slice(), &content[sep..sep + 1]) {
+            ([id], "=") => (*id, DeclKind::Process),
+            ([id], _) => (*id, DeclKind::LocalType),
+            (["strategy", id], "=") => (*id, DeclKind::Strategy),
+            _ => return Err(unexpected()),
+        };
+        if !id.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(unexpected())
+        }
+
+        decls.push(Declaration {
+            id: id.to_string(),
+            kind,
+            body: body.to_string(),
+            line,
+            column: content[..sep + 1].chars().count() + 1,
+        });
+    }
+
+    Ok(decls)
+}
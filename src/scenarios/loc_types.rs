@@ -1,13 +1,13 @@
-use crate::rollpi::{local_types::{PartLocalType, LocalType}, syntax::{check_initial_conf_list, Process}};
+use crate::rollpi::{local_types::{PartLocalType, LocalType}, global_types::GlobalType, syntax::{check_initial_conf_list, Process}};
 
-pub fn simple_rec_lt() -> Vec<(String, Process)>
+pub fn simple_rec_local_types() -> Vec<PartLocalType>
 {
     let party_a = PartLocalType::new("A".to_string(), 
         LocalType::RAbs("t".to_string(), Box::new(
             LocalType::Send("B".to_string(), vec![
-                ("lb_2".to_string(), LocalType::End),
-                ("lb_1".to_string(), LocalType::Recv("B".to_string(), 
-                                        vec![("lb_3".to_string(), LocalType::RVar("t".to_string()))])),
+                ("lb_2".to_string(), vec![], LocalType::End),
+                ("lb_1".to_string(), vec![], LocalType::Recv("B".to_string(), 
+                                        vec![("lb_3".to_string(), vec![], LocalType::RVar("t".to_string()))])),
             ])
         ))
     );
@@ -15,28 +15,58 @@ pub fn simple_rec_lt() -> Vec<(String, Process)>
     let party_b = PartLocalType::new("B".to_string(), 
         LocalType::RAbs("t".to_string(), Box::new(
             LocalType::Recv("A".to_string(), vec![
-                ("lb_2".to_string(), LocalType::End),
-                ("lb_1".to_string(), LocalType::Send("A".to_string(), 
-                                        vec![("lb_3".to_string(), LocalType::RVar("t".to_string()))])),
+                ("lb_2".to_string(), vec![], LocalType::End),
+                ("lb_1".to_string(), vec![], LocalType::Send("A".to_string(), 
+                                        vec![("lb_3".to_string(), vec![], LocalType::RVar("t".to_string()))])),
             ])
         ))
     );
 
-    let party_localtypes = vec![
+    vec![
         party_a,
         party_b,
-    ];
+    ]
+}
 
-    println!("Party A: {:?}", party_localtypes.get(0));
+// The global type whose projections are the local types of simple_rec_local_types
+pub fn simple_rec_global_type() -> GlobalType
+{
+    GlobalType::RAbs("t".to_string(), Box::new(
+        GlobalType::Msg("A".to_string(), "B".to_string(), vec![
+            ("lb_2".to_string(), GlobalType::End),
+            ("lb_1".to_string(), GlobalType::Msg("B".to_string(), "A".to_string(),
+                                    vec![("lb_3".to_string(), GlobalType::RVar("t".to_string()))])),
+        ])
+    ))
+}
 
-    let party_names: Vec<_> = party_localtypes.iter().map(|p| p.get_name()).collect();
-    let party_procs: Vec<_> = party_localtypes.into_iter().map(|p| p.to_process()).collect();
+pub fn simple_rec_lt() -> Vec<(String, Process)>
+{
+    local_types_to_conf(simple_rec_local_types())
+}
 
-    println!("Party A: {:?}", party_procs.get(0));
+pub fn simple_rec_gt() -> Vec<(String, Process)>
+{
+    match simple_rec_global_type().project_all() {
+        Ok(party_localtypes) => local_types_to_conf(party_localtypes),
+        Err(err) => panic!("{}", err),
+    }
+}
+
+fn local_types_to_conf(party_localtypes: Vec<PartLocalType>) -> Vec<(String, Process)>
+{
+    let party_names: Vec<_> = party_localtypes.iter().map(|p| p.get_name()).collect();
+    let party_procs: Vec<_> = party_localtypes.into_iter().map(|p| {
+        let name = p.get_name();
+        p.to_process().unwrap_or_else(|errs| {
+            let msgs = errs.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+            panic!("The local type of {} is not valid: {}", name, msgs.join("; "))
+        })
+    }).collect();
 
     if check_initial_conf_list(&party_procs) == false {
         panic!("The processes do not respect the checks! (pvar, tvar uniques and closed and rolls bounded)")
     } else {
         party_names.into_iter().zip(party_procs.into_iter()).collect()
     }
-}
\ No newline at end of file
+}
@@ -1,2 +1,24 @@
 pub mod loc_types;
-pub mod roll_pi;
\ No newline at end of file
+pub mod roll_pi;
+pub mod file;
+
+use crate::rollpi::syntax::Process;
+
+pub type Scenario = fn() -> Vec<(String, Process)>;
+
+// The scenarios that can be run by name, with a short description
+pub fn builtin() -> Vec<(&'static str, &'static str, Scenario)>
+{
+    vec![
+        ("basic_roll_pi_test", "A sends b<0> to B, which runs it", roll_pi::basic_roll_pi_test),
+        ("basic_roll_pi_rollback", "B receives from A and rolls the communication back", roll_pi::basic_roll_pi_rollback),
+        ("complex_roll_pi_rollback", "A rollback that spreads over three participants", roll_pi::complex_roll_pi_rollback),
+        ("simple_rec_lt", "Encoding of two recursive local types, runs until stopped", loc_types::simple_rec_lt),
+        ("simple_rec_gt", "Same as simple_rec_lt, with the local types projected from a global type", loc_types::simple_rec_gt),
+    ]
+}
+
+pub fn find_builtin(name: &str) -> Option<Scenario>
+{
+    builtin().into_iter().find(|(n, _, _)| *n == name).map(|(_, _, scenario)| scenario)
+}
\ No newline at end of file
@@ -6,7 +6,7 @@ pub fn basic_roll_pi_test() -> Vec<(String, Process)>
     // using this should give an error
     let _cont_1 = Process::PVar(ProcVar("p".to_string()));
     // using this option should work ok
-    let cont_2 = Process::Send(ChName("b".to_string()), Box::new(Process::End));
+    let cont_2 = Process::Send(ChName("b".to_string()), vec![], Box::new(Process::End));
 
     let parties = vec![
         "A".to_string(),
@@ -17,10 +17,11 @@ pub fn basic_roll_pi_test() -> Vec<(String, Process)>
     let processes = vec![
         Process::Send(
             ch_a.clone(),
+            vec![],
             Box::new(cont_2),
         ),
 
-        Process::Recv(ch_a.clone(), ProcVar("pv".to_string()), TagVar("tv".to_string()), 
+        Process::Recv(ch_a.clone(), vec![], ProcVar("pv".to_string()), TagVar("tv".to_string()), 
             Box::new(Process::PVar(ProcVar("pv".to_string()))),
         ),
 
@@ -38,6 +39,7 @@ pub fn basic_roll_pi_rollback() -> Vec<(String, Process)>
     let ch_b = ChName("b".to_string());
 
     let x_var = ProcVar("x".to_string());
+    let y_var = ProcVar("y".to_string());
     let g_tag = TagVar("g".to_string());
     // let g_key = TagKey("g".to_string());
 
@@ -52,15 +54,16 @@ pub fn basic_roll_pi_rollback() -> Vec<(String, Process)>
     let processes = vec![
         Process::Send(
             ch_a.clone(),
+            vec![],
             Box::new(Process::End),
         ),
 
-        Process::Recv(ch_a.clone(), x_var.clone(), g_tag.clone(), Box::new(
+        Process::Recv(ch_a.clone(), vec![], x_var.clone(), g_tag.clone(), Box::new(
             Process::Par(
-                Box::new(Process::Send(ch_b.clone(), Box::new(
+                Box::new(Process::Send(ch_b.clone(), vec![], Box::new(
                     Process::End
                 ))),
-                Box::new(Process::Recv(ch_b.clone(), x_var.clone(), s_tag.clone(), Box::new(
+                Box::new(Process::Recv(ch_b.clone(), vec![], y_var.clone(), s_tag.clone(), Box::new(
                     Process::RollV(g_tag.clone()
                 ))))
             )
@@ -80,29 +83,33 @@ pub fn complex_roll_pi_rollback() -> Vec<(String, Process)>
 
     let y_var = ProcVar("y".to_string());
     let z_var = ProcVar("z".to_string());
+    let v_var = ProcVar("v".to_string());
+    let w_var = ProcVar("w".to_string());
 
     let u_tag = TagVar("u".to_string());
     let g_tag = TagVar("g".to_string());
+    let h_tag = TagVar("h".to_string());
+    let s_tag = TagVar("s".to_string());
     let roll_g = Process::RollV(g_tag.clone());
 
-    let party_a = Process::Send(ch_a.clone(), Box::new(Process::End));
+    let party_a = Process::Send(ch_a.clone(), vec![], Box::new(Process::End));
 
-    let party_b = Process::Recv(ch_a.clone(), y_var.clone(), g_tag.clone(), Box::new(
+    let party_b = Process::Recv(ch_a.clone(), vec![], y_var.clone(), g_tag.clone(), Box::new(
         Process::parallel_compose(vec![
-            Process::Send(ch_c.clone(), Box::new(Process::End)),
+            Process::Send(ch_c.clone(), vec![], Box::new(Process::End)),
 
-            Process::Send(ch_b.clone(), Box::new(Process::End)),
+            Process::Send(ch_b.clone(), vec![], Box::new(Process::End)),
 
-            Process::Recv(ch_b.clone(), y_var.clone(), u_tag.clone(), Box::new(
-                Process::Recv(ch_b.clone(), y_var.clone(), u_tag.clone(), Box::new(
+            Process::Recv(ch_b.clone(), vec![], v_var.clone(), h_tag.clone(), Box::new(
+                Process::Recv(ch_b.clone(), vec![], w_var.clone(), s_tag.clone(), Box::new(
                     roll_g
                 ))
             ))
         ])
     ));
 
-    let party_c = Process::Recv(ch_c.clone(), z_var, u_tag.clone(), Box::new(
-        Process::Send(ch_b.clone(), Box::new(Process::End))
+    let party_c = Process::Recv(ch_c.clone(), vec![], z_var, u_tag.clone(), Box::new(
+        Process::Send(ch_b.clone(), vec![], Box::new(Process::End))
     ));
 
     vec![